@@ -0,0 +1,59 @@
+//! Counts the heap allocations made while failing to create temporary files
+//! because of name collisions.
+//!
+//! Run with `cargo bench --bench errors`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tempfile::Builder;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const ITERATIONS: usize = 1_000;
+
+fn main() {
+    // A long base path makes any copy of it on the error path more costly.
+    let base = tempfile::tempdir().unwrap();
+    let dir = base.path().join("a".repeat(200));
+    std::fs::create_dir(&dir).unwrap();
+
+    // With no random characters, every attempt collides with this file.
+    let _existing = Builder::new()
+        .prefix("collide")
+        .rand_bytes(0)
+        .tempfile_in(&dir)
+        .unwrap();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..ITERATIONS {
+        let err = Builder::new()
+            .prefix("collide")
+            .rand_bytes(0)
+            .tempfile_in(&dir)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    }
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+
+    println!(
+        "{:.1} allocations per failed creation",
+        (after - before) as f64 / ITERATIONS as f64
+    );
+}
@@ -0,0 +1,61 @@
+//! Compares large sequential writes into a fresh `NamedTempFile` with and
+//! without `Builder::reserve_extents`.
+//!
+//! Run with `cargo bench --bench extents`. On Linux, the number of extents of
+//! each file is printed too if `filefrag` is installed.
+
+use std::io::Write;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use tempfile::{Builder, NamedTempFile};
+
+const FILE_LEN: usize = 256 * 1024 * 1024;
+const CHUNK_LEN: usize = 64 * 1024;
+const ITERATIONS: u32 = 5;
+
+fn write(file: &mut NamedTempFile) {
+    let chunk = vec![0xa5u8; CHUNK_LEN];
+    for _ in 0..FILE_LEN / CHUNK_LEN {
+        file.write_all(&chunk).unwrap();
+    }
+    file.as_file().sync_all().unwrap();
+}
+
+fn extents(file: &NamedTempFile) -> Option<String> {
+    let output = Command::new("filefrag").arg(file.path()).output().ok()?;
+    let output = String::from_utf8(output.stdout).ok()?;
+    output.rsplit(": ").next().map(|s| s.trim().to_owned())
+}
+
+fn time(builder: &Builder) -> (Duration, Option<String>) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut total = Duration::new(0, 0);
+    let mut last_extents = None;
+    for _ in 0..ITERATIONS {
+        let mut file = builder.tempfile_in(&dir).unwrap();
+        let start = Instant::now();
+        write(&mut file);
+        total += start.elapsed();
+        last_extents = extents(&file);
+    }
+    (total / ITERATIONS, last_extents)
+}
+
+fn main() {
+    let (plain, plain_extents) = time(&Builder::new());
+    let (reserved, reserved_extents) = time(Builder::new().reserve_extents(FILE_LEN as u64));
+
+    println!(
+        "without reserve_extents: {:?} per {} MiB file ({})",
+        plain,
+        FILE_LEN >> 20,
+        plain_extents.as_deref().unwrap_or("extents unknown")
+    );
+    println!(
+        "with reserve_extents:    {:?} per {} MiB file ({})",
+        reserved,
+        FILE_LEN >> 20,
+        reserved_extents.as_deref().unwrap_or("extents unknown")
+    );
+}
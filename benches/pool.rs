@@ -0,0 +1,38 @@
+//! Compares handing out temporary files from a `TempFilePool` with creating
+//! a fresh `NamedTempFile` each time.
+//!
+//! Run with `cargo bench --bench pool`.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use tempfile::{NamedTempFile, TempFilePool};
+
+const ITERATIONS: u32 = 10_000;
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let fresh = time(|| {
+        let mut file = NamedTempFile::new_in(&dir).unwrap();
+        file.write_all(b"data").unwrap();
+    });
+
+    let mut pool = TempFilePool::new_in(&dir, 16).unwrap();
+    let pooled = time(|| {
+        let mut file = pool.get().unwrap();
+        file.write_all(b"data").unwrap();
+        pool.put(file).unwrap();
+    });
+
+    println!("fresh NamedTempFile: {:?} per allocation", fresh);
+    println!("TempFilePool:        {:?} per allocation", pooled);
+}
@@ -0,0 +1,48 @@
+//! Compares the throughput of many threads creating temporary files in one
+//! directory with and without `Builder::thread_sharded`.
+//!
+//! Run with `cargo bench --bench sharded`.
+
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::Builder;
+
+const THREADS: usize = 64;
+const FILES_PER_THREAD: usize = 500;
+
+fn run(dir: &Path, sharded: bool) -> Duration {
+    let start = Instant::now();
+    let handles: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let dir = dir.to_path_buf();
+            thread::spawn(move || {
+                let mut builder = Builder::new();
+                if sharded {
+                    builder.thread_sharded(true);
+                }
+                for _ in 0..FILES_PER_THREAD {
+                    builder.tempfile_in(&dir).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    let total = (THREADS * FILES_PER_THREAD) as f64;
+    for &sharded in &[false, true] {
+        let elapsed = run(dir.path(), sharded);
+        println!(
+            "sharded={}: {:.0} files/s",
+            sharded,
+            total / elapsed.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Unpacking of test fixture archives, for `TempDir::from_tar` and
+//! `TempDir::from_zip`.
+//!
+//! Parsing is left to the `tar` and `zip` crates. Only regular files and
+//! directories are extracted; links, devices and other special entries are
+//! rejected, as are paths that could land outside of the destination. File
+//! modes are kept as archived; directories are made owner-accessible on top
+//! of theirs.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::IoResultExt;
+
+fn invalid<T>(msg: &str) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+/// Unpack the tar archive read from `reader` into the existing directory
+/// `dest`.
+#[cfg(feature = "tar")]
+pub(crate) fn extract_tar<R: io::Read>(reader: R, dest: &Path) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = dest.join(entry_path(&entry.path()?)?);
+        let kind = entry.header().entry_type();
+        let mode = entry.header().mode()?;
+        let is_file = kind.is_file() || kind.is_contiguous();
+        // Old archives mark directories with a trailing slash only.
+        if kind.is_dir() || (is_file && entry.path_bytes().ends_with(b"/")) {
+            create_dir(&path, mode)?;
+        } else if is_file {
+            create_file(&path, mode, &mut entry)?;
+        } else {
+            return invalid("unsupported tar entry type (only files and directories are)");
+        }
+    }
+    Ok(())
+}
+
+/// Unpack the zip archive read from `reader` into the existing directory
+/// `dest`.
+#[cfg(feature = "zip")]
+pub(crate) fn extract_zip<R: io::Read + io::Seek>(reader: R, dest: &Path) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let path = dest.join(entry_path(Path::new(entry.name()))?);
+        if entry.is_symlink() {
+            return invalid("unsupported zip entry type (only files and directories are)");
+        }
+        // Archives made outside of Unix don't record modes.
+        if entry.is_dir() {
+            create_dir(&path, entry.unix_mode().unwrap_or(0o755))?;
+        } else {
+            let mode = entry.unix_mode().unwrap_or(0o644);
+            create_file(&path, mode, &mut entry)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check that an entry's path is relative and has no `..` components, so
+/// that it can't land outside of the destination.
+fn entry_path(path: &Path) -> io::Result<&Path> {
+    let is_relative = path.components().next().is_some()
+        && path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !is_relative {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "archive entry path is absolute or contains `..`",
+        ))
+        .with_err_path(|| PathBuf::from(path));
+    }
+    Ok(path)
+}
+
+fn create_dir(path: &Path, mode: u32) -> io::Result<()> {
+    fs::create_dir_all(path).with_err_path(|| path)?;
+    // Keep the tree writable so that it can be filled in and cleaned up.
+    set_mode(path, mode | 0o700)
+}
+
+fn create_file<R: io::Read>(path: &Path, mode: u32, data: &mut R) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_err_path(|| parent)?;
+    }
+    let mut file = File::create(path).with_err_path(|| path)?;
+    io::copy(data, &mut file).with_err_path(|| path)?;
+    set_mode(path, mode)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777)).with_err_path(|| path)
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
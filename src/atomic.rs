@@ -0,0 +1,114 @@
+//! Atomic replacement of files through a temporary sibling.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, IoSlice, Write};
+use std::path::{Path, PathBuf};
+
+use crate::NamedTempFile;
+
+/// Start writing a file that will atomically replace `path` once committed.
+///
+/// This is a drop-in replacement for `File::create(path)` for when readers
+/// must never see a partially written file: the data goes (buffered) to a
+/// temporary sibling created with [`NamedTempFile::for_target`], and
+/// [`AtomicWriter::commit`] syncs it to disk and renames it over `path`. If
+/// the writer is dropped without being committed, e.g. because an error
+/// occurred while producing the data, the temporary file is deleted and
+/// `path` is left untouched.
+///
+/// # Errors
+///
+/// If the temporary file cannot be created, `Err` is returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::io::{self, Write};
+/// # fn run() -> Result<(), io::Error> {
+/// let mut writer = tempfile::atomic_writer("/etc/app/config.toml")?;
+/// writeln!(writer, "answer = 42")?;
+/// writer.commit()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`NamedTempFile::for_target`]: struct.NamedTempFile.html#method.for_target
+/// [`AtomicWriter::commit`]: struct.AtomicWriter.html#method.commit
+pub fn atomic_writer<P: AsRef<Path>>(path: P) -> io::Result<AtomicWriter> {
+    let target = path.as_ref().to_path_buf();
+    let file = NamedTempFile::for_target(&target)?;
+    Ok(AtomicWriter {
+        file: BufWriter::new(file),
+        target,
+    })
+}
+
+/// A buffered writer that atomically replaces its target file when
+/// committed.
+///
+/// Created by [`atomic_writer`]. Note that flushing only writes the buffer to
+/// the temporary file; nothing happens to the target until [`commit`].
+///
+/// [`atomic_writer`]: fn.atomic_writer.html
+/// [`commit`]: #method.commit
+pub struct AtomicWriter {
+    file: BufWriter<NamedTempFile>,
+    target: PathBuf,
+}
+
+impl AtomicWriter {
+    /// Get the path that will be replaced on commit.
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Get the temporary file's path.
+    pub fn temp_path(&self) -> &Path {
+        self.file.get_ref().path()
+    }
+
+    /// Flush and sync the written data, then atomically rename the temporary
+    /// file over the target, returning the open file.
+    ///
+    /// The contents are synchronized to disk before the rename, so after a
+    /// crash the target holds either its old or its new contents in full.
+    /// The containing directory isn't synchronized, so the rename itself may
+    /// still be lost.
+    ///
+    /// # Errors
+    ///
+    /// If the data cannot be written or synced, or the file cannot be renamed,
+    /// `Err` is returned, the temporary file is deleted and the target is left
+    /// untouched.
+    pub fn commit(self) -> io::Result<File> {
+        let file = self
+            .file
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        Ok(file.persist_flushed(&self.target, true)?)
+    }
+}
+
+impl fmt::Debug for AtomicWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicWriter")
+            .field("target", &self.target)
+            .field("temp_path", &self.temp_path())
+            .finish()
+    }
+}
+
+impl Write for AtomicWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.file.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,125 @@
+//! Temporary files inside a `cap_std::fs::Dir`.
+
+use cap_std::fs::{Dir, File};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::IoResultExt;
+
+/// A named temporary file created relative to a capability-based
+/// [`cap_std::fs::Dir`].
+///
+/// Created by [`Builder::tempfile_in_dir`]. The file is only ever addressed
+/// by its name relative to the directory handle, never by an absolute path,
+/// and is deleted through that handle when dropped.
+///
+/// Requires the `cap-std` feature.
+///
+/// [`cap_std::fs::Dir`]: https://docs.rs/cap-std/*/cap_std/fs/struct.Dir.html
+/// [`Builder::tempfile_in_dir`]: struct.Builder.html#method.tempfile_in_dir
+pub struct CapTempFile<'d> {
+    dir: &'d Dir,
+    name: PathBuf,
+    file: File,
+}
+
+impl<'d> CapTempFile<'d> {
+    pub(crate) fn new(dir: &'d Dir, name: PathBuf, file: File) -> CapTempFile<'d> {
+        CapTempFile { dir, name, file }
+    }
+
+    /// Get the temporary file's name, relative to its directory.
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Get a mutable reference to the underlying file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Close and remove the temporary file.
+    ///
+    /// Use this if you want to detect errors in deleting the file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be deleted, `Err` is returned.
+    pub fn close(self) -> io::Result<()> {
+        let (dir, name, file) = self.into_parts();
+        drop(file);
+        dir.remove_file(&name).with_err_path(|| name)
+    }
+
+    /// Persist the temporary file under `new_name` in the same directory,
+    /// replacing any existing file, and return the open file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be renamed, `Err` is returned and the temporary file
+    /// is deleted.
+    pub fn persist<P: AsRef<Path>>(self, new_name: P) -> io::Result<File> {
+        let (dir, name, file) = self.into_parts();
+        match dir.rename(&name, dir, new_name.as_ref()) {
+            Ok(()) => Ok(file),
+            Err(e) => {
+                let _ = dir.remove_file(&name);
+                Err(e).with_err_path(|| name)
+            }
+        }
+    }
+
+    /// Take the fields apart without running the destructor.
+    fn into_parts(self) -> (&'d Dir, PathBuf, File) {
+        let this = std::mem::ManuallyDrop::new(self);
+        // Safe: `this` is never used (or dropped) again, so each field is
+        // moved out exactly once.
+        unsafe {
+            (
+                this.dir,
+                std::ptr::read(&this.name),
+                std::ptr::read(&this.file),
+            )
+        }
+    }
+}
+
+impl<'d> Drop for CapTempFile<'d> {
+    fn drop(&mut self) {
+        let _ = self.dir.remove_file(&self.name);
+    }
+}
+
+impl<'d> fmt::Debug for CapTempFile<'d> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CapTempFile({:?})", self.name)
+    }
+}
+
+impl<'d> Read for CapTempFile<'d> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf).with_err_path(|| &self.name)
+    }
+}
+
+impl<'d> Write for CapTempFile<'d> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf).with_err_path(|| &self.name)
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush().with_err_path(|| &self.name)
+    }
+}
+
+impl<'d> Seek for CapTempFile<'d> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos).with_err_path(|| &self.name)
+    }
+}
@@ -0,0 +1,108 @@
+//! Temporary files handed down to child processes.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::error::IoResultExt;
+use crate::file;
+use crate::NamedTempFile;
+
+/// A temporary file meant to be used by child processes, created with
+/// [`Builder::for_children`].
+///
+/// The file is opened so that children can use it either way:
+///
+/// * Its handle is inheritable. On Unix, this means the descriptor is opened
+///   without `O_CLOEXEC`, so it stays open across `exec` and a child can use
+///   it by number (see `as_file().as_raw_fd()`). Every child spawned while
+///   the file is open inherits it, not only the ones it's meant for. On
+///   Windows, the handle is inherited by children spawned with handle
+///   inheritance enabled, which `std::process::Command` does.
+/// * On Windows, it's opened with `FILE_SHARE_READ | FILE_SHARE_WRITE |
+///   FILE_SHARE_DELETE`, so children can open it by path while the parent
+///   holds it, and the parent can still delete it while children have it
+///   open. On Unix, files can always be opened by other processes.
+///
+/// # Resource Leaking
+///
+/// Unlike a [`NamedTempFile`], dropping a `ChildTempFile` only closes the
+/// parent's handle and leaves the file in place, as the children may still
+/// be using it by path. Deleting it is deferred until
+/// [`ChildTempFile::cleanup`] is called, typically once all children have
+/// exited; without that call, the file is leaked.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{self, Write};
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// use tempfile::Builder;
+///
+/// let mut file = Builder::new().for_children()?;
+/// writeln!(file.as_file_mut(), "input for the children")?;
+///
+/// // ... spawn children that read `file.path()`, and wait for them ...
+///
+/// file.cleanup()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Builder::for_children`]: struct.Builder.html#method.for_children
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+/// [`ChildTempFile::cleanup`]: #method.cleanup
+pub struct ChildTempFile {
+    // Disarmed, so that dropping it leaves the file alone.
+    file: NamedTempFile,
+}
+
+impl ChildTempFile {
+    pub(crate) fn new(file: NamedTempFile) -> ChildTempFile {
+        ChildTempFile {
+            file: file.disarm(),
+        }
+    }
+
+    /// Get the temporary file's path.
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &File {
+        self.file.as_file()
+    }
+
+    /// Get a mutable reference to the underlying file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.file.as_file_mut()
+    }
+
+    /// Close the parent's handle and delete the file.
+    ///
+    /// Children that still have the file open keep working on Unix (on a
+    /// file that no longer has a name) and on Windows (the file disappears
+    /// once they close it).
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be deleted, `Err` is returned.
+    pub fn cleanup(self) -> io::Result<()> {
+        let (file, path) = self.file.into_parts();
+        drop(file);
+        file::remove_file(&path).with_err_path(|| &*path)
+    }
+}
+
+impl fmt::Debug for ChildTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ChildTempFile({:?})", self.path())
+    }
+}
@@ -0,0 +1,371 @@
+//! A spooled temporary file that compresses its data once it's on disk.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::file::tempfile;
+
+/// The size of the independently compressed blocks the on-disk data is split
+/// into: the unit of work for a read or write after a seek.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// A [`SpooledTempFile`] whose rolled-over data is compressed with zstd.
+///
+/// The in-memory phase is the same as for a `SpooledTempFile`: data is kept
+/// as is until it outgrows `max_size`. After that, it's stored in an anonymous
+/// temporary file as a sequence of 64 KiB blocks, each compressed on its own,
+/// with an index of where each one is. Reads decompress the block they land
+/// in, and writes collect changes in it; the block is compressed and written
+/// out once the file moves on to another block, or is flushed.
+///
+/// # Performance
+///
+/// Seeking is free in itself, but the first read or write after moving to a
+/// different block decompresses that block (and compresses the one it left,
+/// if it had changes). Reading or writing at random offsets therefore costs
+/// up to a block's worth of compression work per jump, while sequential
+/// access costs about as much as compressing the data once.
+///
+/// Changed blocks are appended to the file rather than written back in
+/// place, as their compressed size changes, so overwriting data that's
+/// already on disk makes the file grow. It's meant for data that's mostly
+/// written once and read back.
+///
+/// Requires the `zstd` feature.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Read, Seek, SeekFrom, Write};
+///
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// let mut file = tempfile::spooled_tempfile_compressed(1024, 3);
+/// for _ in 0..1000 {
+///     file.write_all(b"highly compressible ")?;
+/// }
+/// assert!(file.is_rolled());
+/// file.flush()?;
+/// assert!(file.compressed_len().unwrap() < 20_000);
+///
+/// file.seek(SeekFrom::Start(0))?;
+/// let mut start = [0; 20];
+/// file.read_exact(&mut start)?;
+/// assert_eq!(&start, b"highly compressible ");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`SpooledTempFile`]: struct.SpooledTempFile.html
+pub struct CompressedSpooledTempFile {
+    max_size: usize,
+    level: i32,
+    inner: Inner,
+}
+
+enum Inner {
+    InMemory(Cursor<Vec<u8>>),
+    OnDisk(Blocks),
+}
+
+/// Rolled-over data, as compressed blocks in an anonymous temporary file.
+struct Blocks {
+    file: File,
+    level: i32,
+    // The offset and compressed length within `file` of each block. Blocks
+    // that have never been written (e.g. after extending with `set_len`) are
+    // `None` and read as zeros.
+    index: Vec<Option<(u64, usize)>>,
+    // Where the next compressed block is appended.
+    end: u64,
+    // The logical length and position of the data.
+    len: u64,
+    pos: u64,
+    // The block last read or written, decompressed. Bytes past `len` are
+    // always zero, so that extending the data needn't clear them.
+    cached: Option<CachedBlock>,
+}
+
+struct CachedBlock {
+    block: usize,
+    data: Vec<u8>,
+    dirty: bool,
+}
+
+/// Create a new spooled temporary file that compresses its data with zstd
+/// once it has rolled over to disk.
+///
+/// The data stays in memory, uncompressed, until it exceeds `max_size`
+/// bytes. `level` is the zstd compression level: from 1 (fastest) to 22
+/// (smallest), or 0 for zstd's default (currently 3). See
+/// [`CompressedSpooledTempFile`] for how seeking performs.
+///
+/// Requires the `zstd` feature.
+///
+/// [`CompressedSpooledTempFile`]: struct.CompressedSpooledTempFile.html
+#[inline]
+pub fn spooled_tempfile_compressed(max_size: usize, level: i32) -> CompressedSpooledTempFile {
+    CompressedSpooledTempFile::new(max_size, level)
+}
+
+impl CompressedSpooledTempFile {
+    /// Create a new compressed spooled temporary file.
+    ///
+    /// See [`spooled_tempfile_compressed`](fn.spooled_tempfile_compressed.html).
+    pub fn new(max_size: usize, level: i32) -> CompressedSpooledTempFile {
+        CompressedSpooledTempFile {
+            max_size,
+            level,
+            inner: Inner::InMemory(Cursor::new(Vec::new())),
+        }
+    }
+
+    /// Returns true if the file has been rolled over to disk.
+    pub fn is_rolled(&self) -> bool {
+        match self.inner {
+            Inner::InMemory(_) => false,
+            Inner::OnDisk(_) => true,
+        }
+    }
+
+    /// Returns how many bytes the compressed data takes up on disk, or `None`
+    /// if the data is still in memory.
+    ///
+    /// Changes to the current block only count once they've been written
+    /// out, e.g. by [`flush`](#method.flush).
+    pub fn compressed_len(&self) -> Option<u64> {
+        match self.inner {
+            Inner::InMemory(_) => None,
+            Inner::OnDisk(ref blocks) => Some(blocks.end),
+        }
+    }
+
+    /// Rolls over to a file on disk, regardless of current size. Does nothing
+    /// if already rolled over.
+    pub fn roll(&mut self) -> io::Result<()> {
+        if let Inner::InMemory(ref cursor) = self.inner {
+            let mut blocks = Blocks::new(tempfile()?, self.level);
+            blocks.write_all(cursor.get_ref())?;
+            blocks.pos = cursor.position();
+            self.inner = Inner::OnDisk(blocks);
+        }
+        Ok(())
+    }
+
+    /// Truncates or extends the data, as `File::set_len` does. Extending
+    /// beyond `max_size` rolls the data over to disk.
+    pub fn set_len(&mut self, size: u64) -> io::Result<()> {
+        if size as usize > self.max_size {
+            self.roll()?;
+        }
+        match self.inner {
+            Inner::InMemory(ref mut cursor) => {
+                cursor.get_mut().resize(size as usize, 0);
+                Ok(())
+            }
+            Inner::OnDisk(ref mut blocks) => blocks.set_len(size),
+        }
+    }
+}
+
+impl Blocks {
+    fn new(file: File, level: i32) -> Blocks {
+        Blocks {
+            file,
+            level,
+            index: Vec::new(),
+            end: 0,
+            len: 0,
+            pos: 0,
+            cached: None,
+        }
+    }
+
+    /// Make `block` the cached block, writing out the previous one if it
+    /// has changes.
+    fn load(&mut self, block: usize) -> io::Result<&mut CachedBlock> {
+        if self.cached.as_ref().map(|c| c.block) != Some(block) {
+            self.store()?;
+            let data = match self.index.get(block) {
+                Some(&Some((offset, len))) => {
+                    let mut compressed = vec![0; len];
+                    self.file.seek(SeekFrom::Start(offset))?;
+                    self.file.read_exact(&mut compressed)?;
+                    let mut data = zstd::bulk::decompress(&compressed, BLOCK_SIZE)?;
+                    data.resize(BLOCK_SIZE, 0);
+                    data
+                }
+                _ => vec![0; BLOCK_SIZE],
+            };
+            self.cached = Some(CachedBlock {
+                block,
+                data,
+                dirty: false,
+            });
+        }
+        Ok(self.cached.as_mut().unwrap())
+    }
+
+    /// Compress the cached block and append it to the file, if it has changes.
+    fn store(&mut self) -> io::Result<()> {
+        let cached = match self.cached {
+            Some(ref mut cached) if cached.dirty => cached,
+            _ => return Ok(()),
+        };
+        let start = cached.block as u64 * BLOCK_SIZE as u64;
+        let used = self.len.saturating_sub(start).min(BLOCK_SIZE as u64) as usize;
+        let compressed = zstd::bulk::compress(&cached.data[..used], self.level)?;
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&compressed)?;
+        if self.index.len() <= cached.block {
+            self.index.resize(cached.block + 1, None);
+        }
+        self.index[cached.block] = Some((self.end, compressed.len()));
+        self.end += compressed.len() as u64;
+        cached.dirty = false;
+        Ok(())
+    }
+
+    fn set_len(&mut self, size: u64) -> io::Result<()> {
+        if size < self.len {
+            // Clear what's cut off of the last remaining block, so that it
+            // reads as zeros if the data is extended again.
+            let block = (size / BLOCK_SIZE as u64) as usize;
+            let offset = (size % BLOCK_SIZE as u64) as usize;
+            if offset > 0 {
+                let cached = self.load(block)?;
+                cached.data[offset..].iter_mut().for_each(|b| *b = 0);
+                cached.dirty = true;
+            }
+            let kept = block + (offset > 0) as usize;
+            self.index.truncate(kept);
+            if self.cached.as_ref().is_some_and(|c| c.block >= kept) {
+                self.cached = None;
+            }
+        }
+        self.len = size;
+        Ok(())
+    }
+}
+
+impl Read for Blocks {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let block = (self.pos / BLOCK_SIZE as u64) as usize;
+        let offset = (self.pos % BLOCK_SIZE as u64) as usize;
+        let n = buf
+            .len()
+            .min(BLOCK_SIZE - offset)
+            .min((self.len - self.pos) as usize);
+        let cached = self.load(block)?;
+        buf[..n].copy_from_slice(&cached.data[offset..offset + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for Blocks {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let block = (self.pos / BLOCK_SIZE as u64) as usize;
+        let offset = (self.pos % BLOCK_SIZE as u64) as usize;
+        let n = buf.len().min(BLOCK_SIZE - offset);
+        let cached = self.load(block)?;
+        cached.data[offset..offset + n].copy_from_slice(&buf[..n]);
+        cached.dirty = true;
+        self.pos += n as u64;
+        self.len = self.len.max(self.pos);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.store()?;
+        self.file.flush()
+    }
+}
+
+impl Seek for Blocks {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(offset) => {
+                self.pos = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(delta) => (self.len, delta),
+            SeekFrom::Current(delta) => (self.pos, delta),
+        };
+        match base.checked_add_signed(delta) {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for CompressedSpooledTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompressedSpooledTempFile")
+            .field("max_size", &self.max_size)
+            .field("level", &self.level)
+            .field("rolled", &self.is_rolled())
+            .finish()
+    }
+}
+
+impl Read for CompressedSpooledTempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner {
+            Inner::InMemory(ref mut cursor) => cursor.read(buf),
+            Inner::OnDisk(ref mut blocks) => blocks.read(buf),
+        }
+    }
+}
+
+impl Write for CompressedSpooledTempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // roll over to file if necessary
+        let mut rolling = false;
+        if let Inner::InMemory(ref mut cursor) = self.inner {
+            rolling = cursor.position() as usize + buf.len() > self.max_size;
+        }
+        if rolling {
+            self.roll()?;
+        }
+
+        match self.inner {
+            Inner::InMemory(ref mut cursor) => cursor.write(buf),
+            Inner::OnDisk(ref mut blocks) => blocks.write(buf),
+        }
+    }
+
+    /// Writes out the current block's changes, if it has any.
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner {
+            Inner::InMemory(ref mut cursor) => cursor.flush(),
+            Inner::OnDisk(ref mut blocks) => blocks.flush(),
+        }
+    }
+}
+
+impl Seek for CompressedSpooledTempFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self.inner {
+            Inner::InMemory(ref mut cursor) => cursor.seek(pos),
+            Inner::OnDisk(ref mut blocks) => blocks.seek(pos),
+        }
+    }
+}
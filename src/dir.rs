@@ -9,11 +9,17 @@
 // except according to those terms.
 
 use remove_dir_all::remove_dir_all;
+use std::ffi::{OsStr, OsString};
+use std::ops::Range;
 use std::path::{self, Path, PathBuf};
-use std::{fmt, fs, io};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, fs, io, mem, thread};
 
 use crate::error::IoResultExt;
-use crate::Builder;
+use crate::file;
+use crate::util;
+use crate::{Builder, Encoding, QuotaTempDir};
 
 /// Create a new temporary directory.
 ///
@@ -64,6 +70,32 @@ pub fn tempdir() -> io::Result<TempDir> {
     TempDir::new()
 }
 
+/// Create a new temporary directory in `std::env::temp_dir()`, using
+/// `rand_len` random characters for its name instead of the default 6.
+///
+/// This is [`tempdir`] with a different collision budget, for one-off calls
+/// that don't warrant a [`Builder`].
+///
+/// # Errors
+///
+/// If the directory can not be created, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io;
+/// # fn run() -> Result<(), io::Error> {
+/// let dir = tempfile::tempdir_with_rand(16)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`tempdir`]: fn.tempdir.html
+/// [`Builder`]: struct.Builder.html
+pub fn tempdir_with_rand(rand_len: usize) -> io::Result<TempDir> {
+    Builder::new().rand_bytes(rand_len).tempdir()
+}
+
 /// Create a new temporary directory.
 ///
 /// The `tempdir` function creates a directory in the file system
@@ -193,6 +225,11 @@ pub fn tempdir_in<P: AsRef<Path>>(dir: P) -> io::Result<TempDir> {
 /// [`std::process::exit()`]: http://doc.rust-lang.org/std/process/fn.exit.html
 pub struct TempDir {
     path: Option<PathBuf>,
+    parent_dirs: Vec<PathBuf>,
+    // Byte range of the random token within the directory name, if any.
+    rand_token: Range<usize>,
+    // See `Builder::warn_slow_cleanup`.
+    warn_slow_cleanup: Option<(Duration, SlowCleanupHook)>,
 }
 
 impl TempDir {
@@ -263,6 +300,186 @@ impl TempDir {
         Builder::new().tempdir_in(dir)
     }
 
+    /// Creates a new temporary directory pre-populated with the given files
+    /// and directories.
+    ///
+    /// Each entry is a path relative to the new directory, using `/` as the
+    /// separator, and optional file contents. A path ending in `/` creates a
+    /// directory; any other path creates a file containing the given bytes,
+    /// or an empty file if `None`. Missing parent directories are created
+    /// along the way, so `("a/b/c.txt", ...)` needs no separate `"a/b/"`
+    /// entry. Later entries overwrite earlier files with the same path.
+    ///
+    /// Everything is deleted along with the directory when the `TempDir` is
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// If an entry's path is absolute or contains `..`, or if a directory
+    /// entry is given contents, an error of kind `InvalidInput` is returned
+    /// before anything is created. If the directory or any entry can't be
+    /// created, `Err` is returned and everything created so far is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use tempfile::TempDir;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let tmp_dir = TempDir::scaffold(&[
+    ///     ("a/b.txt", Some(&b"hi"[..])),
+    ///     ("a/empty.txt", None),
+    ///     ("c/", None),
+    /// ])?;
+    /// assert_eq!(std::fs::read(tmp_dir.path().join("a/b.txt"))?, b"hi");
+    /// assert!(tmp_dir.path().join("c").is_dir());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn scaffold<C: AsRef<[u8]>>(entries: &[(&str, Option<C>)]) -> io::Result<TempDir> {
+        for &(path, ref contents) in entries {
+            let is_relative = Path::new(path)
+                .components()
+                .all(|c| matches!(c, path::Component::Normal(_) | path::Component::CurDir));
+            if !is_relative {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "scaffold entries must be relative paths without `..` components",
+                ))
+                .with_err_path(|| path);
+            }
+            if path.ends_with('/') && contents.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "scaffold directory entries can't have contents",
+                ))
+                .with_err_path(|| path);
+            }
+        }
+
+        let tmp_dir = TempDir::new()?;
+        for &(path, ref contents) in entries {
+            let full = tmp_dir.path().join(path);
+            if path.ends_with('/') {
+                fs::create_dir_all(&full).with_err_path(|| &full)?;
+                continue;
+            }
+            if let Some(parent) = full.parent() {
+                fs::create_dir_all(parent).with_err_path(|| parent)?;
+            }
+            let contents = contents.as_ref().map_or(&[][..], AsRef::as_ref);
+            fs::write(&full, contents).with_err_path(|| &full)?;
+        }
+        Ok(tmp_dir)
+    }
+
+    /// Create a new temporary directory and unpack the tar archive read from
+    /// `reader` into it.
+    ///
+    /// This is meant for test fixtures shipped as archives, e.g. with
+    /// `TempDir::from_tar(&include_bytes!("fixture.tar")[..])`. The archive is
+    /// read with the [`tar`] crate. Regular files and directories are
+    /// supported, in the ustar, GNU and pax formats, but the archive must not
+    /// be compressed. On Unix, the permission bits of
+    /// files are preserved. Those of directories are not quite: the owner
+    /// always gets read, write and search access (the archived mode is
+    /// combined with `0o700`), since otherwise the tree couldn't be filled in
+    /// or cleaned up. Ownership and timestamps are not restored.
+    ///
+    /// The whole extracted tree is deleted along with the directory when the
+    /// `TempDir` is dropped.
+    ///
+    /// Requires the `tar` feature.
+    ///
+    /// # Errors
+    ///
+    /// If the archive contains an entry whose path is absolute or contains
+    /// `..`, or an entry that isn't a file or directory (such as a symbolic
+    /// link), an error of kind `InvalidData` is returned. If the archive is
+    /// malformed, or the directory or any entry can't be created, `Err` is
+    /// returned. In all cases, everything extracted so far is removed.
+    ///
+    /// [`tar`]: https://docs.rs/tar
+    #[cfg(feature = "tar")]
+    pub fn from_tar<R: io::Read>(reader: R) -> io::Result<TempDir> {
+        let tmp_dir = TempDir::new()?;
+        crate::archive::extract_tar(reader, tmp_dir.path())?;
+        Ok(tmp_dir)
+    }
+
+    /// Create a new temporary directory and unpack the zip archive read from
+    /// `reader` into it.
+    ///
+    /// This is the zip counterpart of [`from_tar`](#method.from_tar), e.g.
+    /// for `TempDir::from_zip(io::Cursor::new(include_bytes!("fixture.zip")))`.
+    /// The archive is read with the [`zip`] crate. Regular files and
+    /// directories are supported, stored as is or deflate-compressed. On Unix,
+    /// the permission bits recorded by archivers on Unix are preserved for
+    /// files, while directories are always made accessible to their owner (as
+    /// with `from_tar`). Entries without recorded permissions get `0o644`
+    /// (files) or `0o755` (directories). Ownership and timestamps are not
+    /// restored.
+    ///
+    /// The whole extracted tree is deleted along with the directory when the
+    /// `TempDir` is dropped.
+    ///
+    /// Requires the `zip` feature.
+    ///
+    /// # Errors
+    ///
+    /// If the archive contains an entry whose path is absolute or contains
+    /// `..`, or a symbolic link, an error of kind `InvalidData` is returned.
+    /// If the archive is malformed or uses an unsupported compression method,
+    /// or the directory or any entry can't be created, `Err` is returned. In
+    /// all cases, everything extracted so far is removed.
+    ///
+    /// [`zip`]: https://docs.rs/zip
+    #[cfg(feature = "zip")]
+    pub fn from_zip<R: io::Read + io::Seek>(reader: R) -> io::Result<TempDir> {
+        let tmp_dir = TempDir::new()?;
+        crate::archive::extract_zip(reader, tmp_dir.path())?;
+        Ok(tmp_dir)
+    }
+
+    /// Create a new temporary directory that limits how much data can be
+    /// written to the files created in it to `max_bytes` in total.
+    ///
+    /// See [`QuotaTempDir`] for how the quota is accounted.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// use tempfile::TempDir;
+    ///
+    /// # fn run() -> Result<(), io::Error> {
+    /// let dir = TempDir::with_quota(16)?;
+    /// let mut file = dir.new_file()?;
+    /// file.write_all(b"0123456789")?;
+    ///
+    /// let mut other = dir.new_file()?;
+    /// let err = other.write_all(b"0123456789").unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+    /// # Ok(())
+    /// # }
+    /// # run().unwrap();
+    /// ```
+    ///
+    /// [`QuotaTempDir`]: struct.QuotaTempDir.html
+    pub fn with_quota(max_bytes: u64) -> io::Result<QuotaTempDir> {
+        TempDir::new().map(|dir| QuotaTempDir::new(dir, max_bytes))
+    }
+
     /// Accesses the [`Path`] to the temporary directory.
     ///
     /// [`Path`]: http://doc.rust-lang.org/std/path/struct.Path.html
@@ -295,6 +512,18 @@ impl TempDir {
         self.path.as_ref().unwrap()
     }
 
+    /// Get the random token that was generated for the directory name.
+    ///
+    /// This is the part of the name between the prefix and the suffix, e.g.
+    /// `AbC123` for `.tmpAbC123`, and is handy for correlating a temporary
+    /// directory with log lines. It's empty if the name wasn't randomly
+    /// generated (see [`Builder::exact_name`]).
+    ///
+    /// [`Builder::exact_name`]: struct.Builder.html#method.exact_name
+    pub fn rand_token(&self) -> &OsStr {
+        util::name_slice(self.path(), &self.rand_token)
+    }
+
     /// Persist the temporary directory to disk, returning the [`PathBuf`] where it is located.
     ///
     /// This consumes the [`TempDir`] without deleting directory on the filesystem, meaning that
@@ -326,6 +555,142 @@ impl TempDir {
         self.path.take().unwrap()
     }
 
+    /// Split the temporary directory into its path and a guard that deletes
+    /// the directory when dropped.
+    ///
+    /// This is useful when the path needs to be stored or passed around
+    /// separately from whatever controls the directory's lifetime. Only the
+    /// guard deletes the directory, so it's never removed twice. Use
+    /// [`TempDir::from_parts`] to turn the guard back into a `TempDir`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use tempfile::TempDir;
+    ///
+    /// # fn run() -> Result<(), io::Error> {
+    /// let (path, guard) = TempDir::new()?.into_parts();
+    /// assert_eq!(guard.path(), path);
+    ///
+    /// drop(guard);
+    /// assert!(!path.exists());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TempDir::from_parts`]: #method.from_parts
+    pub fn into_parts(self) -> (PathBuf, TempDirGuard) {
+        (self.path().to_path_buf(), TempDirGuard(self))
+    }
+
+    /// Reassemble a temporary directory from the guard returned by
+    /// [`TempDir::into_parts`].
+    ///
+    /// [`TempDir::into_parts`]: #method.into_parts
+    pub fn from_parts(guard: TempDirGuard) -> TempDir {
+        guard.0
+    }
+
+    /// Adopt an existing directory, deleting it (recursively) when the
+    /// returned `TempDir` is dropped.
+    ///
+    /// Responsibility for the directory, including everything in it, moves to
+    /// the `TempDir`, so make sure nothing else still expects it to exist.
+    /// The directory isn't checked or touched until then; it doesn't even
+    /// have to exist yet.
+    ///
+    /// In debug builds, this panics if `path` doesn't end in a normal
+    /// component (such as `/`, `.` or `..`), which is almost certainly a
+    /// mistake.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use std::fs;
+    /// use tempfile::TempDir;
+    ///
+    /// # fn run() -> Result<(), io::Error> {
+    /// # let parent = tempfile::tempdir()?;
+    /// let path = parent.path().join("build-output");
+    /// fs::create_dir(&path)?;
+    ///
+    /// let dir = TempDir::from_existing(path.clone());
+    /// drop(dir);
+    /// assert!(!path.exists());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_existing(path: PathBuf) -> TempDir {
+        debug_assert!(
+            matches!(
+                path.components().next_back(),
+                Some(path::Component::Normal(_))
+            ),
+            "refusing to adopt {:?} as a temporary directory",
+            path
+        );
+        TempDir {
+            path: Some(path),
+            parent_dirs: Vec::new(),
+            rand_token: 0..0,
+            warn_slow_cleanup: None,
+        }
+    }
+
+    /// Recursively visit every entry inside the temporary directory.
+    ///
+    /// `f` is called with the path of each file, directory, and symlink
+    /// below the temporary directory (but not the directory itself).
+    /// Directories are visited before their contents, and symlinks are
+    /// reported but never followed. Visiting stops at the first error,
+    /// whether it comes from `f` or from reading a directory.
+    ///
+    /// This is handy for logging, or for moving specific files out, before
+    /// the directory is cleaned up.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `f` or encountered while reading
+    /// the directory tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use tempfile::TempDir;
+    ///
+    /// # use std::io;
+    /// # fn run() -> Result<(), io::Error> {
+    /// let tmp_dir = TempDir::new()?;
+    /// File::create(tmp_dir.path().join("output.log"))?;
+    ///
+    /// tmp_dir.for_each_entry(|path| {
+    ///     println!("leftover: {}", path.display());
+    ///     Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_each_entry<F>(&self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&Path) -> io::Result<()>,
+    {
+        fn visit(dir: &Path, f: &mut dyn FnMut(&Path) -> io::Result<()>) -> io::Result<()> {
+            for entry in fs::read_dir(dir).with_err_path(|| dir)? {
+                let entry = entry.with_err_path(|| dir)?;
+                let path = entry.path();
+                f(&path)?;
+                if entry.file_type().with_err_path(|| &path)?.is_dir() {
+                    visit(&path, f)?;
+                }
+            }
+            Ok(())
+        }
+        visit(self.path(), &mut f)
+    }
+
     /// Closes and removes the temporary directory, returning a `Result`.
     ///
     /// Although `TempDir` removes the directory on drop, in the destructor
@@ -371,9 +736,172 @@ impl TempDir {
 
         // Prevent the Drop impl from removing the dir a second time.
         self.path = None;
+        util::remove_dirs(&self.parent_dirs);
 
         result
     }
+
+    /// Closes the temporary directory and removes it on a background thread.
+    ///
+    /// Removing a large tree can take a long time, which [`close`] and `Drop`
+    /// spend blocking the current thread. This spawns a new thread to do the
+    /// same work instead and returns right away; the returned handle yields
+    /// the result [`close`] would have returned. Joining the handle is
+    /// optional, and dropping it detaches the thread, which then finishes on
+    /// its own. Dropping a `TempDir` still removes it synchronously.
+    ///
+    /// Note that background threads are killed when the process exits (e.g.
+    /// when `main` returns): if the cleanup must complete, join the handle
+    /// before then, or the directory may be left partially deleted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system fails to create a thread, like
+    /// [`std::thread::spawn`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::TempDir;
+    ///
+    /// # use std::io;
+    /// # fn run() -> Result<(), io::Error> {
+    /// let tmp_dir = TempDir::new()?;
+    /// // ... fill it with lots of files ...
+    ///
+    /// let cleanup = tmp_dir.close_async();
+    /// // ... do other work ...
+    /// cleanup.join().expect("cleanup thread panicked")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`close`]: #method.close
+    /// [`std::thread::spawn`]: https://doc.rust-lang.org/std/thread/fn.spawn.html
+    pub fn close_async(mut self) -> thread::JoinHandle<io::Result<()>> {
+        // Taking the path prevents the Drop impl from removing the dir.
+        let path = self.path.take();
+        let parent_dirs = mem::take(&mut self.parent_dirs);
+        thread::spawn(move || {
+            let result = match path {
+                Some(ref path) => remove_dir_all(path).with_err_path(|| path),
+                None => Ok(()),
+            };
+            util::remove_dirs(&parent_dirs);
+            result
+        })
+    }
+
+    /// Move the temporary directory to `dest`, replacing whatever is there.
+    ///
+    /// This is the directory counterpart of [`NamedTempFile::persist`]: build
+    /// a new tree in a temporary directory, then swap it into place. The old
+    /// contents of `dest`, if any, are deleted afterwards.
+    ///
+    /// Directories can't be replaced atomically everywhere, so this does the
+    /// best the platform allows:
+    ///
+    /// * If `dest` doesn't exist, the directory is simply renamed, which is
+    ///   atomic.
+    /// * On Linux, an existing `dest` is swapped with the temporary directory
+    ///   in one step using `renameat2(RENAME_EXCHANGE)` where the kernel and
+    ///   filesystem support it, so other processes always see either the old
+    ///   or the new tree.
+    /// * Otherwise, the old `dest` is first renamed aside (to a hidden sibling)
+    ///   and the temporary directory is then renamed into place. Between the
+    ///   two renames, `dest` briefly doesn't exist. If the second rename
+    ///   fails, the old directory is moved back.
+    ///
+    /// Like files, directories can't be moved across filesystems.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can't be moved into place, `Err` is returned, `dest`
+    /// is left as it was (as far as possible), and the temporary directory is
+    /// deleted. If the new directory is in place but the old one can't be
+    /// deleted, that error is returned too.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use std::fs;
+    /// use tempfile::Builder;
+    ///
+    /// # fn run() -> Result<(), io::Error> {
+    /// let staging = Builder::new().prefix(".assets").tempdir_in("/srv/www")?;
+    /// fs::write(staging.path().join("index.html"), "<h1>v2</h1>")?;
+    /// staging.persist_replace("/srv/www/assets")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`NamedTempFile::persist`]: struct.NamedTempFile.html#method.persist
+    pub fn persist_replace<P: AsRef<Path>>(mut self, dest: P) -> io::Result<()> {
+        let dest = dest.as_ref();
+        let src = self.path().to_path_buf();
+
+        if fs::symlink_metadata(dest).is_err() {
+            fs::rename(&src, dest).with_err_path(|| dest)?;
+            self.path = None;
+            util::remove_dirs(&self.parent_dirs);
+            return Ok(());
+        }
+
+        if file::exchange(&src, dest).with_err_path(|| dest)? {
+            // The old tree now lives at our path; let `close` remove it.
+            return self.close();
+        }
+
+        let dir = dest.parent().unwrap_or_else(|| Path::new(""));
+        let mut prefix = OsString::from(".");
+        prefix.push(dest.file_name().unwrap_or_default());
+        prefix.push(".old");
+        let aside = util::create_helper(
+            dir,
+            &prefix,
+            OsStr::new(""),
+            crate::NUM_RAND_CHARS,
+            Encoding::default(),
+            false,
+            |aside| {
+                fs::rename(dest, &aside)
+                    .with_err_path_unless_exists(|| dest)
+                    .map(|_| aside)
+            },
+        )?;
+        if let Err(e) = fs::rename(&src, dest) {
+            let _ = fs::rename(&aside, dest);
+            return Err(e).with_err_path(|| dest);
+        }
+        self.path = None;
+        util::remove_dirs(&self.parent_dirs);
+
+        if fs::symlink_metadata(&aside).is_ok_and(|m| m.is_dir()) {
+            remove_dir_all(&aside)
+        } else {
+            fs::remove_file(&aside)
+        }
+        .with_err_path(|| &aside)
+    }
+
+    pub(crate) fn set_rand_token(mut self, rand_token: Range<usize>) -> TempDir {
+        self.rand_token = rand_token;
+        self
+    }
+
+    pub(crate) fn set_parent_dirs(mut self, parent_dirs: Vec<PathBuf>) -> TempDir {
+        self.parent_dirs = parent_dirs;
+        self
+    }
+
+    pub(crate) fn set_warn_slow_cleanup(
+        mut self,
+        warn: Option<(Duration, SlowCleanupHook)>,
+    ) -> TempDir {
+        self.warn_slow_cleanup = warn;
+        self
+    }
 }
 
 impl AsRef<Path> for TempDir {
@@ -394,13 +922,82 @@ impl Drop for TempDir {
     fn drop(&mut self) {
         // Path is `None` if `close()` or `into_path()` has been called.
         if let Some(ref p) = self.path {
-            let _ = remove_dir_all(p);
+            // Only read the clock when asked to: the common case is a small
+            // tree that's gone in microseconds.
+            let start = self.warn_slow_cleanup.as_ref().map(|_| Instant::now());
+            let _result = remove_dir_all(p);
+            trace_event!(path = %p.display(), result = ?_result, "removing temporary directory");
+            util::remove_dirs(&self.parent_dirs);
+            if let (Some((threshold, ref hook)), Some(start)) = (&self.warn_slow_cleanup, start) {
+                let elapsed = start.elapsed();
+                if elapsed >= *threshold {
+                    (hook.0)(p, elapsed);
+                }
+            }
         }
     }
 }
 
+/// The cleanup half of a [`TempDir`], as returned by [`TempDir::into_parts`].
+///
+/// The directory is deleted when the guard is dropped.
+///
+/// [`TempDir`]: struct.TempDir.html
+/// [`TempDir::into_parts`]: struct.TempDir.html#method.into_parts
+#[derive(Debug)]
+pub struct TempDirGuard(TempDir);
+
+impl TempDirGuard {
+    /// Get the path of the directory this guard cleans up.
+    pub fn path(&self) -> &Path {
+        self.0.path()
+    }
+
+    /// Remove the directory now, reporting any error.
+    pub fn close(self) -> io::Result<()> {
+        self.0.close()
+    }
+}
+
+/// The callback set with [`Builder::warn_slow_cleanup`], wrapped like
+/// `EnospcHook` so that the builder can keep deriving its traits.
+///
+/// [`Builder::warn_slow_cleanup`]: struct.Builder.html#method.warn_slow_cleanup
+#[derive(Clone)]
+pub(crate) struct SlowCleanupHook(Arc<SlowCleanupFn>);
+
+type SlowCleanupFn = dyn Fn(&Path, Duration) + Send + Sync;
+
+impl SlowCleanupHook {
+    pub(crate) fn new<F>(hook: F) -> SlowCleanupHook
+    where
+        F: Fn(&Path, Duration) + Send + Sync + 'static,
+    {
+        SlowCleanupHook(Arc::new(hook))
+    }
+}
+
+impl fmt::Debug for SlowCleanupHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SlowCleanupHook")
+    }
+}
+
+impl PartialEq for SlowCleanupHook {
+    fn eq(&self, other: &SlowCleanupHook) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SlowCleanupHook {}
+
 pub(crate) fn create(path: PathBuf) -> io::Result<TempDir> {
     fs::create_dir(&path)
-        .with_err_path(|| &path)
-        .map(|_| TempDir { path: Some(path) })
+        .with_err_path_unless_exists(|| &path)
+        .map(|_| TempDir {
+            path: Some(path),
+            parent_dirs: Vec::new(),
+            rand_token: 0..0,
+            warn_slow_cleanup: None,
+        })
 }
@@ -0,0 +1,35 @@
+//! Alphabets for the random part of temporary file names.
+
+/// The set of characters used for the random part of temporary file names.
+///
+/// See [`Builder::rand_encoding`]. Whatever the encoding, [`Builder::rand_bytes`]
+/// sets the number of random *characters*, not bytes of entropy: with 6
+/// characters, `Base62` gives about 35.7 bits of randomness, `Base32` 30 and
+/// `HexLower` 24. Use more characters with the smaller alphabets to keep the
+/// odds of a collision equally low.
+///
+/// [`Builder::rand_encoding`]: struct.Builder.html#method.rand_encoding
+/// [`Builder::rand_bytes`]: struct.Builder.html#method.rand_bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Encoding {
+    /// ASCII letters and digits (`A-Z`, `a-z`, `0-9`); about 5.95 bits per
+    /// character. This is the default.
+    #[default]
+    Base62,
+    /// Lowercase hexadecimal digits (`0-9`, `a-f`); 4 bits per character.
+    HexLower,
+    /// The RFC 4648 base32 alphabet in lowercase (`a-z`, `2-7`); 5 bits per
+    /// character. Names differ in more than case, so this is safe on
+    /// case-insensitive filesystems.
+    Base32,
+}
+
+impl Encoding {
+    pub(crate) fn alphabet(self) -> &'static [u8] {
+        match self {
+            Encoding::Base62 => b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+            Encoding::HexLower => b"0123456789abcdef",
+            Encoding::Base32 => b"abcdefghijklmnopqrstuvwxyz234567",
+        }
+    }
+}
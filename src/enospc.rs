@@ -0,0 +1,96 @@
+//! Retrying writes after running out of disk space.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, IoSlice, Write};
+use std::sync::Arc;
+
+use crate::file;
+
+/// The callback set with [`Builder::on_enospc`].
+///
+/// Wrapped so that the builder and temporary files can keep deriving or
+/// implementing `Clone`, `Debug` and `Eq`; two hooks are equal if they're the
+/// same callback.
+///
+/// [`Builder::on_enospc`]: struct.Builder.html#method.on_enospc
+#[derive(Clone)]
+pub(crate) struct EnospcHook(Arc<dyn Fn() -> io::Result<()> + Send + Sync>);
+
+impl EnospcHook {
+    pub(crate) fn new<F>(hook: F) -> EnospcHook
+    where
+        F: Fn() -> io::Result<()> + Send + Sync + 'static,
+    {
+        EnospcHook(Arc::new(hook))
+    }
+}
+
+impl fmt::Debug for EnospcHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EnospcHook")
+    }
+}
+
+impl PartialEq for EnospcHook {
+    fn eq(&self, other: &EnospcHook) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for EnospcHook {}
+
+/// A writer that calls a hook and retries once when a write fails because
+/// the disk is full.
+///
+/// Created by [`NamedTempFile::enospc_retrying_writer`]. Writes go straight
+/// to the underlying file; only when one fails with `ENOSPC`
+/// (`ERROR_DISK_FULL` on Windows) is the [`Builder::on_enospc`] hook invoked,
+/// e.g. to delete other temporary files, and the write attempted a second
+/// time. If no hook was set, errors are passed through unchanged.
+///
+/// [`NamedTempFile::enospc_retrying_writer`]: struct.NamedTempFile.html#method.enospc_retrying_writer
+/// [`Builder::on_enospc`]: struct.Builder.html#method.on_enospc
+pub struct EnospcRetryingWriter<'a> {
+    file: &'a File,
+    hook: Option<&'a EnospcHook>,
+}
+
+impl<'a> EnospcRetryingWriter<'a> {
+    pub(crate) fn new(file: &'a File, hook: Option<&'a EnospcHook>) -> EnospcRetryingWriter<'a> {
+        EnospcRetryingWriter { file, hook }
+    }
+
+    fn retry<T>(&mut self, mut op: impl FnMut(&mut &'a File) -> io::Result<T>) -> io::Result<T> {
+        match op(&mut self.file) {
+            Err(ref e) if file::is_enospc(e) && self.hook.is_some() => {
+                (self.hook.unwrap().0)()?;
+                op(&mut self.file)
+            }
+            res => res,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for EnospcRetryingWriter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnospcRetryingWriter")
+            .field("file", &self.file)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+impl<'a> Write for EnospcRetryingWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.retry(|file| file.write(buf))
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.retry(|file| file.write_vectored(bufs))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.retry(|file| file.flush())
+    }
+}
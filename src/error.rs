@@ -24,6 +24,21 @@ pub(crate) trait IoResultExt<T> {
     where
         F: FnOnce() -> P,
         P: Into<PathBuf>;
+
+    /// Like `with_err_path`, but leaves `AlreadyExists` errors untouched.
+    ///
+    /// Name collisions are retried (and the error discarded) by
+    /// `util::create_helper`, so attaching the path there would only cost
+    /// allocations; callers that do report such errors add the path themselves.
+    fn with_err_path_unless_exists<F, P>(self, path: F) -> Self
+    where
+        F: FnOnce() -> P,
+        P: Into<PathBuf>;
+}
+
+/// Attach `path` to `err`, keeping its kind.
+pub(crate) fn with_path(err: io::Error, path: PathBuf) -> io::Error {
+    io::Error::new(err.kind(), PathError { path, err })
 }
 
 impl<T> IoResultExt<T> for Result<T, io::Error> {
@@ -32,14 +47,17 @@ impl<T> IoResultExt<T> for Result<T, io::Error> {
         F: FnOnce() -> P,
         P: Into<PathBuf>,
     {
-        self.map_err(|e| {
-            io::Error::new(
-                e.kind(),
-                PathError {
-                    path: path().into(),
-                    err: e,
-                },
-            )
-        })
+        self.map_err(|e| with_path(e, path().into()))
+    }
+
+    fn with_err_path_unless_exists<F, P>(self, path: F) -> Self
+    where
+        F: FnOnce() -> P,
+        P: Into<PathBuf>,
+    {
+        match self {
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => self,
+            res => res.with_err_path(path),
+        }
     }
 }
@@ -1,7 +1,9 @@
-use std::fs::{File, OpenOptions};
+use std::fs::{File, OpenOptions, Permissions};
 use std::io;
 use std::path::Path;
 
+use crate::FsKind;
+
 fn not_supported<T>() -> io::Result<T> {
     Err(io::Error::new(
         io::ErrorKind::Other,
@@ -9,11 +11,92 @@ fn not_supported<T>() -> io::Result<T> {
     ))
 }
 
-pub fn create_named(_path: &Path, open_options: &mut OpenOptions) -> io::Result<File> {
+pub fn create_named(
+    _path: &Path,
+    open_options: &mut OpenOptions,
+    _permissions: Option<&Permissions>,
+) -> io::Result<File> {
     not_supported()
 }
 
-pub fn create(_dir: &Path) -> io::Result<File> {
+pub fn create_named_via_link(
+    path: &Path,
+    open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
+) -> io::Result<File> {
+    create_named(path, open_options, permissions)
+}
+
+pub fn set_inheritable(_file: &File, _inheritable: bool) -> io::Result<()> {
+    not_supported()
+}
+
+pub fn link_count(_file: &File) -> io::Result<u64> {
+    not_supported()
+}
+
+pub fn exchange(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn is_enospc(_e: &io::Error) -> bool {
+    false
+}
+
+pub fn is_cross_device(_e: &io::Error) -> bool {
+    false
+}
+
+pub fn lock(_file: &File, _exclusive: bool, _blocking: bool) -> io::Result<bool> {
+    not_supported()
+}
+
+pub fn unlock(_file: &File) -> io::Result<()> {
+    not_supported()
+}
+
+pub fn reflink(_source: &File, _dest: &File) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn reserve_extents(_file: &File, _len: u64) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn link_anonymous(_file: &File, _path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn is_same_file(_file: &File, _path: &Path) -> io::Result<bool> {
+    not_supported()
+}
+
+pub fn remove_file(path: &Path) -> io::Result<()> {
+    std::fs::remove_file(path)
+}
+
+pub fn is_unsafe_dir(_dir: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn describe_open_flags(
+    _append: bool,
+    _shared_append: bool,
+    _nfs_safe: bool,
+    _inheritable: bool,
+    _share_mode: Option<(bool, bool, bool)>,
+    _permissions: Option<&Permissions>,
+) -> String {
+    "creating files is not supported on this platform".to_string()
+}
+
+pub fn share_all(open_options: &mut OpenOptions) -> &mut OpenOptions {
+    open_options
+}
+
+pub fn set_share_mode(_open_options: &mut OpenOptions, _read: bool, _write: bool, _delete: bool) {}
+
+pub fn create(_dir: &Path, _random_len: usize) -> io::Result<File> {
     not_supported()
 }
 
@@ -21,6 +104,10 @@ pub fn reopen(_file: &File, _path: &Path) -> io::Result<File> {
     not_supported()
 }
 
+pub fn into_anonymous(_file: File, _path: &Path) -> io::Result<File> {
+    not_supported()
+}
+
 pub fn persist(_old_path: &Path, _new_path: &Path, _overwrite: bool) -> io::Result<()> {
     not_supported()
 }
@@ -28,3 +115,15 @@ pub fn persist(_old_path: &Path, _new_path: &Path, _overwrite: bool) -> io::Resu
 pub fn keep(path: &Path) -> io::Result<()> {
     not_supported()
 }
+
+pub fn fs_kind(_path: &Path) -> io::Result<FsKind> {
+    Ok(FsKind::Unknown)
+}
+
+pub fn free_space(_path: &Path) -> io::Result<u64> {
+    not_supported()
+}
+
+pub fn numa_node() -> Option<u32> {
+    None
+}
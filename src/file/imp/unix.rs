@@ -1,11 +1,13 @@
+use crate::util;
+use crate::FsKind;
 use std::env;
 use std::ffi::{CString, OsStr};
-use std::fs::{self, File, OpenOptions};
+use std::fs::{self, File, OpenOptions, Permissions};
 use std::io;
 use std::os::unix::ffi::OsStrExt;
-use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::path::Path;
-use crate::util;
 
 #[cfg(not(target_os = "redox"))]
 use libc::{c_char, c_int, link, rename, unlink};
@@ -20,6 +22,27 @@ pub fn cvt_err(result: c_int) -> io::Result<c_int> {
     }
 }
 
+/// How often a system call interrupted by a signal is restarted before `EINTR`
+/// is reported. Bounded so that a signal storm can't turn into a hang.
+#[cfg(not(target_os = "redox"))]
+const EINTR_RETRIES: usize = 64;
+
+/// Like `cvt_err`, but restarts `f` when it fails with `EINTR`, at most
+/// `EINTR_RETRIES` times. `f` must be safe to repeat after an interrupted
+/// call, which is the case for calls that either complete or fail as a whole.
+#[cfg(not(target_os = "redox"))]
+pub fn cvt_retry<F: FnMut() -> c_int>(mut f: F) -> io::Result<c_int> {
+    let mut retries = 0;
+    loop {
+        match cvt_err(f()) {
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted && retries < EINTR_RETRIES => {
+                retries += 1
+            }
+            res => return res,
+        }
+    }
+}
+
 #[cfg(target_os = "redox")]
 #[inline(always)]
 pub fn cvt_err(result: Result<usize, syscall::Error>) -> io::Result<usize> {
@@ -32,15 +55,567 @@ pub fn cstr(path: &Path) -> io::Result<CString> {
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contained a null"))
 }
 
-pub fn create_named(path: &Path, open_options: &mut OpenOptions) -> io::Result<File> {
+// Note: std opens every file with `O_CLOEXEC`, so temporary files never leak
+// into child processes unless `set_inheritable` is called.
+pub fn create_named(
+    path: &Path,
+    open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
+) -> io::Result<File> {
     open_options
         .read(true)
         .write(true)
         .create_new(true)
-        .mode(0o600)
+        .mode(permissions.map(|p| p.mode()).unwrap_or(0o600))
         .open(path)
 }
 
+/// Describe the flags `create_named` and friends open a file with, for
+/// `Builder::debug_open_flags`.
+pub fn describe_open_flags(
+    append: bool,
+    shared_append: bool,
+    nfs_safe: bool,
+    inheritable: bool,
+    _share_mode: Option<(bool, bool, bool)>,
+    permissions: Option<&Permissions>,
+) -> String {
+    let append = append || shared_append;
+    let flags = format!(
+        "O_RDWR | O_CREAT | O_EXCL{} | O_CLOEXEC, mode {:04o}",
+        if append { " | O_APPEND" } else { "" },
+        permissions.map(|p| p.mode()).unwrap_or(0o600) & 0o7777,
+    );
+    let mut description = if nfs_safe {
+        format!(
+            "open(private name, {}), then link(2) to the final name and check st_nlink",
+            flags
+        )
+    } else {
+        format!("open({})", flags)
+    };
+    if shared_append {
+        description.push_str(", or open(O_RDWR | O_APPEND | O_CLOEXEC) if it already exists");
+    }
+    if inheritable {
+        description.push_str(", then fcntl(F_SETFD) to clear FD_CLOEXEC");
+    }
+    description
+}
+
+/// Exclusively create `path` in a way that also works on NFSv2, where
+/// `O_EXCL` isn't atomic: create a uniquely named private file next to it,
+/// hard-link it to `path`, and check that the private file's link count went
+/// up to 2. The link count is checked even if `link` reports an error, since
+/// the reply to a successful `link` can get lost and the retried request then
+/// fails with `EEXIST`.
+pub fn create_named_via_link(
+    path: &Path,
+    open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
+) -> io::Result<File> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let (private, file) = util::create_helper(
+        dir,
+        OsStr::new(".tmplink"),
+        OsStr::new(""),
+        crate::NUM_RAND_CHARS,
+        crate::Encoding::default(),
+        false,
+        |private| {
+            let file = create_named(&private, &mut open_options.clone(), permissions)?;
+            Ok((private, file))
+        },
+    )?;
+    let linked = fs::hard_link(&private, path);
+    let nlink = fs::metadata(&private).map(|m| m.nlink());
+    let _ = fs::remove_file(&private);
+    match (linked, nlink) {
+        (_, Ok(2)) => Ok(file),
+        (Err(e), _) => Err(e),
+        (Ok(()), nlink) => {
+            // The link at `path` is ours but unconfirmed; don't leave it behind
+            // when the caller retries under another name.
+            let _ = fs::remove_file(path);
+            Err(nlink.err().unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "exclusive creation could not be confirmed by the link count",
+                )
+            }))
+        }
+    }
+}
+
+#[cfg(not(target_os = "redox"))]
+pub fn set_inheritable(file: &File, inheritable: bool) -> io::Result<()> {
+    use libc::{fcntl, FD_CLOEXEC, F_GETFD, F_SETFD};
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    unsafe {
+        let flags = cvt_err(fcntl(fd, F_GETFD))?;
+        let flags = if inheritable {
+            flags & !FD_CLOEXEC
+        } else {
+            flags | FD_CLOEXEC
+        };
+        cvt_err(fcntl(fd, F_SETFD, flags))?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "redox")]
+pub fn set_inheritable(_file: &File, _inheritable: bool) -> io::Result<()> {
+    // XXX implement when possible
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+/// Duplicate `file`'s descriptor without `FD_CLOEXEC`.
+#[cfg(not(target_os = "redox"))]
+pub fn dup_inheritable(file: &File) -> io::Result<RawFd> {
+    // Unlike `F_DUPFD_CLOEXEC`, `F_DUPFD` always clears the flag on the copy.
+    cvt_err(unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD, 0) })
+}
+
+#[cfg(target_os = "redox")]
+pub fn dup_inheritable(_file: &File) -> io::Result<RawFd> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_xattr(file: &File, name: &OsStr, value: &[u8]) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let name = xattr_name(name)?;
+    cvt_err(unsafe {
+        libc::fsetxattr(
+            file.as_raw_fd(),
+            name.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn set_xattr(file: &File, name: &OsStr, value: &[u8]) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let name = xattr_name(name)?;
+    cvt_err(unsafe {
+        libc::fsetxattr(
+            file.as_raw_fd(),
+            name.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+            0,
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios"
+))]
+fn xattr_name(name: &OsStr) -> io::Result<CString> {
+    CString::new(name.as_bytes()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "extended attribute name contained a null",
+        )
+    })
+}
+
+#[cfg(target_os = "redox")]
+pub fn set_xattr(_file: &File, _name: &OsStr, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "redox"
+)))]
+pub fn set_xattr(_file: &File, _name: &OsStr, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::from_raw_os_error(libc::ENOTSUP))
+}
+
+pub fn link_count(file: &File) -> io::Result<u64> {
+    file.metadata().map(|m| m.nlink())
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+fn to_off_t(offset: u64) -> io::Result<libc::off_t> {
+    use std::convert::TryFrom;
+
+    libc::off_t::try_from(offset)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "offset too large"))
+}
+
+/// Decide whether a failed `sendfile` call should be retried, or the loop
+/// should stop and report what has been sent so far.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+fn sendfile_retry(sent: usize) -> io::Result<bool> {
+    let e = io::Error::last_os_error();
+    match e.kind() {
+        io::ErrorKind::Interrupted => Ok(true),
+        io::ErrorKind::WouldBlock if sent > 0 => Ok(false),
+        _ => Err(e),
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn sendfile(file: &File, socket: RawFd, offset: u64, len: usize) -> io::Result<usize> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut offset = to_off_t(offset)?;
+    let mut sent = 0;
+    while sent < len {
+        // Advances `offset` by the number of bytes sent.
+        let n = unsafe { libc::sendfile(socket, file.as_raw_fd(), &mut offset, len - sent) };
+        if n == -1 {
+            if sendfile_retry(sent)? {
+                continue;
+            }
+            break;
+        }
+        if n == 0 {
+            // End of file.
+            break;
+        }
+        sent += n as usize;
+    }
+    Ok(sent)
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+pub fn sendfile(file: &File, socket: RawFd, offset: u64, len: usize) -> io::Result<usize> {
+    use std::os::unix::io::AsRawFd;
+
+    let offset = to_off_t(offset)?;
+    let mut sent = 0;
+    // The loop condition matters: a length of zero means "until the end of
+    // the file" to the BSD `sendfile`.
+    while sent < len {
+        let start = offset + sent as libc::off_t;
+        // In: how much to send (macOS only). Out: how much was sent.
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let mut n = (len - sent) as libc::off_t;
+        #[cfg(target_os = "freebsd")]
+        let mut n: libc::off_t = 0;
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let result = unsafe {
+            libc::sendfile(
+                file.as_raw_fd(),
+                socket,
+                start,
+                &mut n,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        #[cfg(target_os = "freebsd")]
+        let result = unsafe {
+            libc::sendfile(
+                file.as_raw_fd(),
+                socket,
+                start,
+                len - sent,
+                std::ptr::null_mut(),
+                &mut n,
+                0,
+            )
+        };
+        // Partial progress is reported even when the call fails.
+        sent += n as usize;
+        if result == -1 {
+            if sendfile_retry(sent)? {
+                continue;
+            }
+            break;
+        }
+        if n == 0 {
+            break;
+        }
+    }
+    Ok(sent)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+pub fn sendfile(file: &File, socket: RawFd, offset: u64, len: usize) -> io::Result<usize> {
+    use std::io::Write;
+    use std::mem::ManuallyDrop;
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::FromRawFd;
+
+    // Don't close the caller's socket when we're done with it.
+    let mut out = ManuallyDrop::new(unsafe { File::from_raw_fd(socket) });
+    let mut buf = vec![0; len.min(64 * 1024)];
+    let mut sent = 0;
+    'outer: while sent < len {
+        let want = buf.len().min(len - sent);
+        let n = match file.read_at(&mut buf[..want], offset + sent as u64) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        // Count partial writes so that, like `sendfile`, a socket that would
+        // block after some progress reports what was sent instead of losing it.
+        let mut written = 0;
+        while written < n {
+            match out.write(&buf[written..n]) {
+                Ok(0) => break 'outer,
+                Ok(m) => {
+                    written += m;
+                    sent += m;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock && sent > 0 => break 'outer,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(sent)
+}
+
+/// Atomically swap two existing paths, returning `Ok(false)` if the
+/// platform or filesystem can't do that.
+#[cfg(target_os = "linux")]
+pub fn exchange(a: &Path, b: &Path) -> io::Result<bool> {
+    let a = cstr(a)?;
+    let b = cstr(b)?;
+    let result = cvt_retry(|| unsafe {
+        // The raw syscall returns `-1` or `0`, which fits in a `c_int`.
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        ) as c_int
+    });
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) => match e.raw_os_error() {
+            // Old kernel, or a filesystem without support for `RENAME_EXCHANGE`.
+            Some(libc::ENOSYS) | Some(libc::EINVAL) | Some(libc::EOPNOTSUPP) => Ok(false),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Make `dest` a copy-on-write clone of `source`, returning `Ok(false)` if the
+/// filesystem can't do that.
+#[cfg(target_os = "linux")]
+pub fn reflink(source: &File, dest: &File) -> io::Result<bool> {
+    match cvt_retry(|| unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) })
+    {
+        Ok(_) => Ok(true),
+        Err(e) => match e.raw_os_error() {
+            // No reflink support, or the files are on different filesystems.
+            Some(libc::EOPNOTSUPP)
+            | Some(libc::ENOTTY)
+            | Some(libc::EINVAL)
+            | Some(libc::EXDEV) => Ok(false),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Allocate disk space for the first `len` bytes of `file` without changing
+/// its size, returning `Ok(false)` if the filesystem can't do that.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn reserve_extents(file: &File, len: u64) -> io::Result<bool> {
+    use std::convert::TryFrom;
+
+    if len == 0 {
+        return Ok(true);
+    }
+    let len = libc::off_t::try_from(len)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "reservation too large"))?;
+    match cvt_retry(|| unsafe {
+        libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len)
+    }) {
+        Ok(_) => Ok(true),
+        Err(e) => match e.raw_os_error() {
+            Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => Ok(false),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Allocate disk space for the first `len` bytes of `file` without changing
+/// its size, preferably contiguously, returning `Ok(false)` if the filesystem
+/// can't do that.
+#[cfg(target_os = "macos")]
+pub fn reserve_extents(file: &File, len: u64) -> io::Result<bool> {
+    use std::convert::TryFrom;
+
+    if len == 0 {
+        return Ok(true);
+    }
+    let len = libc::off_t::try_from(len)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "reservation too large"))?;
+    let mut store = libc::fstore_t {
+        fst_flags: libc::F_ALLOCATECONTIG | libc::F_ALLOCATEALL,
+        fst_posmode: libc::F_PEOFPOSMODE,
+        fst_offset: 0,
+        fst_length: len,
+        fst_bytesalloc: 0,
+    };
+    let mut result =
+        cvt_retry(|| unsafe { libc::fcntl(file.as_raw_fd(), libc::F_PREALLOCATE, &mut store) });
+    if result.is_err() {
+        // No contiguous run that long is free; settle for any extents.
+        store.fst_flags = libc::F_ALLOCATEALL;
+        result =
+            cvt_retry(|| unsafe { libc::fcntl(file.as_raw_fd(), libc::F_PREALLOCATE, &mut store) });
+    }
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) => match e.raw_os_error() {
+            Some(libc::EOPNOTSUPP) | Some(libc::ENOTSUP) | Some(libc::EINVAL) => Ok(false),
+            _ => Err(e),
+        },
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+pub fn reserve_extents(_file: &File, _len: u64) -> io::Result<bool> {
+    Ok(false)
+}
+
+// macOS's `clonefile` and `fclonefileat` only clone into a destination that
+// doesn't exist yet, so they can't fill the already created temporary file.
+#[cfg(not(target_os = "linux"))]
+pub fn reflink(_source: &File, _dest: &File) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn exchange(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Give `file`, which has no name (e.g. one opened with `O_TMPFILE`), the
+/// name `path`, returning `Ok(false)` if it can't be linked.
+#[cfg(target_os = "linux")]
+pub fn link_anonymous(file: &File, path: &Path) -> io::Result<bool> {
+    let source = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd())).unwrap();
+    let target = cstr(path)?;
+    match cvt_retry(|| unsafe {
+        libc::linkat(
+            libc::AT_FDCWD,
+            source.as_ptr(),
+            libc::AT_FDCWD,
+            target.as_ptr(),
+            libc::AT_SYMLINK_FOLLOW,
+        )
+    }) {
+        Ok(_) => Ok(true),
+        Err(e) => match e.raw_os_error() {
+            // Not linkable (e.g. `O_TMPFILE | O_EXCL`, or no `/proc`), on
+            // another filesystem (e.g. a memfd), or not allowed.
+            Some(libc::ENOENT) | Some(libc::EXDEV) | Some(libc::EPERM) | Some(libc::EACCES) => {
+                Ok(false)
+            }
+            _ => Err(e),
+        },
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn link_anonymous(_file: &File, _path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(target_os = "linux")]
+pub fn mount_tmpfs(path: &Path, size: u64) -> io::Result<()> {
+    let target = cstr(path)?;
+    let options = CString::new(format!("size={},mode=0700", size)).unwrap();
+    cvt_err(unsafe {
+        libc::mount(
+            b"tempfile\0".as_ptr() as *const c_char,
+            target.as_ptr(),
+            b"tmpfs\0".as_ptr() as *const c_char,
+            libc::MS_NOSUID | libc::MS_NODEV,
+            options.as_ptr() as *const libc::c_void,
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+pub fn unmount(path: &Path) -> io::Result<()> {
+    let target = cstr(path)?;
+    cvt_err(unsafe { libc::umount2(target.as_ptr(), libc::MNT_DETACH) })?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "redox"))]
+pub fn is_enospc(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::ENOSPC)
+}
+
+#[cfg(target_os = "redox")]
+pub fn is_enospc(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(syscall::ENOSPC)
+}
+
+#[cfg(not(target_os = "redox"))]
+pub fn is_cross_device(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::EXDEV)
+}
+
+#[cfg(target_os = "redox")]
+pub fn is_cross_device(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(syscall::EXDEV)
+}
+
+pub fn open_parent_dir(path: &Path) -> io::Result<File> {
+    File::open(path.parent().unwrap_or_else(|| Path::new(".")))
+}
+
+pub fn is_unsafe_dir(dir: &Path) -> io::Result<bool> {
+    let mode = fs::metadata(dir)?.mode();
+    Ok(mode & 0o002 != 0 && mode & 0o1000 == 0)
+}
+
+pub fn share_all(open_options: &mut OpenOptions) -> &mut OpenOptions {
+    // Files can always be opened by other processes.
+    open_options
+}
+
+pub fn set_share_mode(_open_options: &mut OpenOptions, _read: bool, _write: bool, _delete: bool) {
+    // Files can always be opened by other processes.
+}
+
 fn create_unlinked(path: &Path) -> io::Result<File> {
     let tmp;
     // shadow this to decrease the lifetime. It can't live longer than `tmp`.
@@ -51,7 +626,7 @@ fn create_unlinked(path: &Path) -> io::Result<File> {
         path = &tmp;
     }
 
-    let f = create_named(path, &mut OpenOptions::new())?;
+    let f = create_named(path, &mut OpenOptions::new(), None)?;
     // don't care whether the path has already been unlinked,
     // but perhaps there are some IO error conditions we should send up?
     let _ = fs::remove_file(path);
@@ -59,7 +634,7 @@ fn create_unlinked(path: &Path) -> io::Result<File> {
 }
 
 #[cfg(target_os = "linux")]
-pub fn create(dir: &Path) -> io::Result<File> {
+pub fn create(dir: &Path, random_len: usize) -> io::Result<File> {
     use libc::{EISDIR, ENOENT, EOPNOTSUPP, O_EXCL, O_TMPFILE};
     OpenOptions::new()
         .read(true)
@@ -69,27 +644,40 @@ pub fn create(dir: &Path) -> io::Result<File> {
         .or_else(|e| {
             match e.raw_os_error() {
                 // These are the three "not supported" error codes for O_TMPFILE.
-                Some(EOPNOTSUPP) | Some(EISDIR) | Some(ENOENT) => create_unix(dir),
+                Some(EOPNOTSUPP) | Some(EISDIR) | Some(ENOENT) => create_unix(dir, random_len),
                 _ => Err(e),
             }
         })
 }
 
 #[cfg(not(target_os = "linux"))]
-pub fn create(dir: &Path) -> io::Result<File> {
-    create_unix(dir)
+pub fn create(dir: &Path, random_len: usize) -> io::Result<File> {
+    create_unix(dir, random_len)
 }
 
-fn create_unix(dir: &Path) -> io::Result<File> {
+fn create_unix(dir: &Path, random_len: usize) -> io::Result<File> {
     util::create_helper(
         dir,
         OsStr::new(".tmp"),
         OsStr::new(""),
-        crate::NUM_RAND_CHARS,
+        random_len,
+        crate::Encoding::default(),
+        false,
         |path| create_unlinked(&path),
     )
 }
 
+/// Whether `path` (still) refers to `file`.
+pub fn is_same_file(file: &File, path: &Path) -> io::Result<bool> {
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let own = file.metadata()?;
+    Ok(own.dev() == meta.dev() && own.ino() == meta.ino())
+}
+
 pub fn reopen(file: &File, path: &Path) -> io::Result<File> {
     let new_file = OpenOptions::new().read(true).write(true).open(path)?;
     let old_meta = file.metadata()?;
@@ -103,24 +691,33 @@ pub fn reopen(file: &File, path: &Path) -> io::Result<File> {
     Ok(new_file)
 }
 
+pub fn into_anonymous(file: File, path: &Path) -> io::Result<File> {
+    fs::remove_file(path)?;
+    Ok(file)
+}
+
 #[cfg(not(target_os = "redox"))]
 pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<()> {
     unsafe {
         let old_path = cstr(old_path)?;
         let new_path = cstr(new_path)?;
         if overwrite {
-            cvt_err(rename(
-                old_path.as_ptr() as *const c_char,
-                new_path.as_ptr() as *const c_char,
-            ))?;
+            cvt_retry(|| {
+                rename(
+                    old_path.as_ptr() as *const c_char,
+                    new_path.as_ptr() as *const c_char,
+                )
+            })?;
         } else {
-            cvt_err(link(
-                old_path.as_ptr() as *const c_char,
-                new_path.as_ptr() as *const c_char,
-            ))?;
+            cvt_retry(|| {
+                link(
+                    old_path.as_ptr() as *const c_char,
+                    new_path.as_ptr() as *const c_char,
+                )
+            })?;
             // Ignore unlink errors. Can we do better?
             // On recent linux, we can use renameat2 to do this atomically.
-            let _ = unlink(old_path.as_ptr() as *const c_char);
+            let _ = cvt_retry(|| unlink(old_path.as_ptr() as *const c_char));
         }
         Ok(())
     }
@@ -132,6 +729,182 @@ pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<
     Err(io::Error::from_raw_os_error(syscall::ENOSYS))
 }
 
+#[cfg(not(target_os = "redox"))]
+pub fn create_at(dir: &File, name: &Path, append: bool, mode: u32) -> io::Result<File> {
+    let name = cstr(name)?;
+    let mut flags = libc::O_RDWR | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC;
+    if append {
+        flags |= libc::O_APPEND;
+    }
+    let fd = cvt_retry(|| unsafe {
+        libc::openat(dir.as_raw_fd(), name.as_ptr(), flags, mode as libc::c_uint)
+    })?;
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(not(target_os = "redox"))]
+pub fn unlink_at(dir: &File, name: &Path) -> io::Result<()> {
+    let name = cstr(name)?;
+    cvt_retry(|| unsafe { libc::unlinkat(dir.as_raw_fd(), name.as_ptr(), 0) })?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "redox"))]
+pub fn rename_at(dir: &File, old_name: &Path, new_name: &Path) -> io::Result<()> {
+    let old_name = cstr(old_name)?;
+    let new_name = cstr(new_name)?;
+    cvt_retry(|| unsafe {
+        libc::renameat(
+            dir.as_raw_fd(),
+            old_name.as_ptr(),
+            dir.as_raw_fd(),
+            new_name.as_ptr(),
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(target_os = "redox")]
+pub fn create_at(_dir: &File, _name: &Path, _append: bool, _mode: u32) -> io::Result<File> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+#[cfg(target_os = "redox")]
+pub fn unlink_at(_dir: &File, _name: &Path) -> io::Result<()> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+#[cfg(target_os = "redox")]
+pub fn rename_at(_dir: &File, _old_name: &Path, _new_name: &Path) -> io::Result<()> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+/// Place an advisory `flock` lock on `file`, returning `Ok(false)` if
+/// `blocking` isn't set and another handle holds a conflicting lock.
+#[cfg(not(target_os = "redox"))]
+pub fn lock(file: &File, exclusive: bool, blocking: bool) -> io::Result<bool> {
+    let mut operation = if exclusive {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_SH
+    };
+    if !blocking {
+        operation |= libc::LOCK_NB;
+    }
+    match cvt_retry(|| unsafe { libc::flock(file.as_raw_fd(), operation) }) {
+        Ok(_) => Ok(true),
+        Err(ref e) if e.raw_os_error() == Some(libc::EWOULDBLOCK) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(target_os = "redox"))]
+pub fn unlock(file: &File) -> io::Result<()> {
+    cvt_retry(|| unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) })?;
+    Ok(())
+}
+
+#[cfg(target_os = "redox")]
+pub fn lock(_file: &File, _exclusive: bool, _blocking: bool) -> io::Result<bool> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+#[cfg(target_os = "redox")]
+pub fn unlock(_file: &File) -> io::Result<()> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+pub fn remove_file(path: &Path) -> io::Result<()> {
+    fs::remove_file(path)
+}
+
 pub fn keep(_: &Path) -> io::Result<()> {
     Ok(())
 }
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn fs_kind(path: &Path) -> io::Result<FsKind> {
+    let path = cstr(path)?;
+    let mut buf = unsafe { std::mem::zeroed::<libc::statfs>() };
+    cvt_retry(|| unsafe { libc::statfs(path.as_ptr(), &mut buf) })?;
+    // The width and signedness of `f_type` varies by architecture, but all
+    // magic numbers fit in 32 bits.
+    #[allow(clippy::unnecessary_cast)]
+    let kind = match buf.f_type as u32 {
+        0x0102_1994 | 0x8584_58f6 => FsKind::Tmpfs,
+        0xef53 => FsKind::Ext,
+        0x9123_683e => FsKind::Btrfs,
+        0x5846_5342 => FsKind::Xfs,
+        0x2fc1_2fc1 => FsKind::Zfs,
+        0x794c_7630 => FsKind::Overlay,
+        0x6969 => FsKind::Nfs,
+        0x517b | 0xff53_4d42 | 0xfe53_4d42 => FsKind::Smb,
+        0x5346_544e => FsKind::Ntfs,
+        0x4d44 | 0x2011_bab0 => FsKind::Fat,
+        _ => FsKind::Unknown,
+    };
+    Ok(kind)
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+pub fn fs_kind(path: &Path) -> io::Result<FsKind> {
+    let path = cstr(path)?;
+    let mut buf = unsafe { std::mem::zeroed::<libc::statfs>() };
+    cvt_retry(|| unsafe { libc::statfs(path.as_ptr(), &mut buf) })?;
+    let name = unsafe { std::ffi::CStr::from_ptr(buf.f_fstypename.as_ptr()) };
+    Ok(FsKind::from_name(&name.to_string_lossy()))
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+pub fn fs_kind(_path: &Path) -> io::Result<FsKind> {
+    Ok(FsKind::Unknown)
+}
+
+/// The number of bytes available to unprivileged users on the filesystem
+/// containing `path`.
+#[cfg(not(target_os = "redox"))]
+pub fn free_space(path: &Path) -> io::Result<u64> {
+    let path = cstr(path)?;
+    let mut buf = unsafe { std::mem::zeroed::<libc::statvfs>() };
+    cvt_retry(|| unsafe { libc::statvfs(path.as_ptr(), &mut buf) })?;
+    // The field types vary by platform.
+    #[allow(clippy::unnecessary_cast, clippy::useless_conversion)]
+    Ok((buf.f_bavail as u64).saturating_mul(buf.f_frsize as u64))
+}
+
+#[cfg(target_os = "redox")]
+pub fn free_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::from_raw_os_error(syscall::ENOSYS))
+}
+
+/// The NUMA node of the CPU the calling thread is running on.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn numa_node() -> Option<u32> {
+    let mut cpu: libc::c_uint = 0;
+    let mut node: libc::c_uint = 0;
+    // The third argument is an unused cache pointer, kept for compatibility.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_getcpu,
+            &mut cpu as *mut libc::c_uint,
+            &mut node as *mut libc::c_uint,
+            std::ptr::null_mut::<libc::c_void>(),
+        )
+    };
+    if ret == 0 {
+        Some(node)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn numa_node() -> Option<u32> {
+    None
+}
@@ -1,26 +1,44 @@
 use std::ffi::OsStr;
-use std::fs::{File, OpenOptions};
+use std::fs::{File, OpenOptions, Permissions};
 use std::os::windows::ffi::OsStrExt;
 use std::os::windows::fs::OpenOptionsExt;
 use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
 use std::path::Path;
-use std::{io, iter};
+use std::time::Duration;
+use std::{fs, io, iter, mem, ptr, thread};
 
-use winapi::um::fileapi::SetFileAttributesW;
-use winapi::um::handleapi::INVALID_HANDLE_VALUE;
-use winapi::um::winbase::{MoveFileExW, ReOpenFile};
-use winapi::um::winbase::{FILE_FLAG_DELETE_ON_CLOSE, MOVEFILE_REPLACE_EXISTING};
+use winapi::shared::ntdef::ULARGE_INTEGER;
+use winapi::um::fileapi::{
+    GetDiskFreeSpaceExW, GetVolumeInformationW, GetVolumePathNameW, SetFileAttributesW,
+};
+use winapi::um::fileapi::{
+    SetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_ALLOCATION_INFO,
+};
+use winapi::um::handleapi::{SetHandleInformation, INVALID_HANDLE_VALUE};
+use winapi::um::minwinbase::FileAllocationInfo;
+use winapi::um::winbase::{MoveFileExW, MoveFileTransactedW, ReOpenFile};
+use winapi::um::winbase::{
+    FILE_FLAG_DELETE_ON_CLOSE, HANDLE_FLAG_INHERIT, MOVEFILE_REPLACE_EXISTING,
+};
+use winapi::um::winnt::{DELETE, FILE_GENERIC_READ, FILE_GENERIC_WRITE, HANDLE};
 use winapi::um::winnt::{FILE_ATTRIBUTE_NORMAL, FILE_ATTRIBUTE_TEMPORARY};
-use winapi::um::winnt::{FILE_GENERIC_READ, FILE_GENERIC_WRITE, HANDLE};
 use winapi::um::winnt::{FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE};
 
 use crate::util;
+use crate::FsKind;
+
+/// How many times `remove_file` tries to delete a file.
+const REMOVE_ATTEMPTS: u32 = 5;
 
 fn to_utf16(s: &Path) -> Vec<u16> {
     s.as_os_str().encode_wide().chain(iter::once(0)).collect()
 }
 
-pub fn create_named(path: &Path, open_options: &mut OpenOptions) -> io::Result<File> {
+pub fn create_named(
+    path: &Path,
+    open_options: &mut OpenOptions,
+    _permissions: Option<&Permissions>,
+) -> io::Result<File> {
     open_options
         .create_new(true)
         .read(true)
@@ -29,12 +47,258 @@ pub fn create_named(path: &Path, open_options: &mut OpenOptions) -> io::Result<F
         .open(path)
 }
 
-pub fn create(dir: &Path) -> io::Result<File> {
+/// Describe the flags `create_named` and friends open a file with, for
+/// `Builder::debug_open_flags`.
+pub fn describe_open_flags(
+    append: bool,
+    shared_append: bool,
+    _nfs_safe: bool,
+    inheritable: bool,
+    share_mode: Option<(bool, bool, bool)>,
+    permissions: Option<&Permissions>,
+) -> String {
+    let share = match share_mode {
+        None => "FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE".to_owned(),
+        Some((read, write, delete)) => {
+            let names: Vec<&str> = [
+                (read, "FILE_SHARE_READ"),
+                (write, "FILE_SHARE_WRITE"),
+                (delete, "FILE_SHARE_DELETE"),
+            ]
+            .iter()
+            .filter(|&&(set, _)| set)
+            .map(|&(_, name)| name)
+            .collect();
+            if names.is_empty() {
+                "0".to_owned()
+            } else {
+                names.join(" | ")
+            }
+        }
+    };
+    let access = if append || shared_append {
+        "GENERIC_READ | (FILE_GENERIC_WRITE & !FILE_WRITE_DATA)"
+    } else {
+        "GENERIC_READ | GENERIC_WRITE"
+    };
+    let mut description = format!(
+        "CreateFileW({}, {}, CREATE_NEW, FILE_ATTRIBUTE_TEMPORARY, bInheritHandle = FALSE)",
+        access, share
+    );
+    if shared_append {
+        description.push_str(", or OPEN_EXISTING if it already exists");
+    }
+    if inheritable {
+        description.push_str(", then SetHandleInformation(HANDLE_FLAG_INHERIT)");
+    }
+    if permissions.is_some() {
+        description.push_str("; permissions are ignored");
+    }
+    description
+}
+
+pub fn create_named_via_link(
+    path: &Path,
+    open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
+) -> io::Result<File> {
+    create_named(path, open_options, permissions)
+}
+
+// Note: std creates handles as non-inheritable, so temporary files never leak
+// into child processes unless `set_inheritable` is called.
+pub fn set_inheritable(file: &File, inheritable: bool) -> io::Result<()> {
+    let flags = if inheritable { HANDLE_FLAG_INHERIT } else { 0 };
+    unsafe {
+        if SetHandleInformation(file.as_raw_handle() as HANDLE, HANDLE_FLAG_INHERIT, flags) == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn file_info(file: &File) -> io::Result<BY_HANDLE_FILE_INFORMATION> {
+    use winapi::um::fileapi::GetFileInformationByHandle;
+
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+        if GetFileInformationByHandle(file.as_raw_handle() as HANDLE, &mut info) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(info)
+    }
+}
+
+pub fn link_count(file: &File) -> io::Result<u64> {
+    file_info(file).map(|info| u64::from(info.nNumberOfLinks))
+}
+
+/// Whether `path` (still) refers to `file`.
+pub fn is_same_file(file: &File, path: &Path) -> io::Result<bool> {
+    let other = match share_all(OpenOptions::new().read(true)).open(path) {
+        Ok(other) => other,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let (a, b) = (file_info(file)?, file_info(&other)?);
+    Ok(a.dwVolumeSerialNumber == b.dwVolumeSerialNumber
+        && a.nFileIndexHigh == b.nFileIndexHigh
+        && a.nFileIndexLow == b.nFileIndexLow)
+}
+
+pub fn exchange(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Delete `path`, retrying a few times with a growing delay if another
+/// process briefly has it open.
+pub fn remove_file(path: &Path) -> io::Result<()> {
+    use winapi::shared::winerror::{ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION};
+
+    let mut delay = Duration::from_millis(10);
+    for _ in 1..REMOVE_ATTEMPTS {
+        match fs::remove_file(path) {
+            Err(ref e)
+                if e.raw_os_error().is_some_and(|code| {
+                    code as u32 == ERROR_SHARING_VIOLATION || code as u32 == ERROR_ACCESS_DENIED
+                }) =>
+            {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            res => return res,
+        }
+    }
+    fs::remove_file(path)
+}
+
+pub fn is_cross_device(e: &io::Error) -> bool {
+    use winapi::shared::winerror::ERROR_NOT_SAME_DEVICE;
+
+    e.raw_os_error() == Some(ERROR_NOT_SAME_DEVICE as i32)
+}
+
+pub fn is_enospc(e: &io::Error) -> bool {
+    use winapi::shared::winerror::{ERROR_DISK_FULL, ERROR_HANDLE_DISK_FULL};
+
+    match e.raw_os_error() {
+        Some(code) => code as u32 == ERROR_DISK_FULL || code as u32 == ERROR_HANDLE_DISK_FULL,
+        None => false,
+    }
+}
+
+/// Lock the whole of `file` with `LockFileEx`, returning `Ok(false)` if
+/// `blocking` isn't set and another handle holds a conflicting lock.
+pub fn lock(file: &File, exclusive: bool, blocking: bool) -> io::Result<bool> {
+    use winapi::shared::winerror::ERROR_LOCK_VIOLATION;
+    use winapi::um::fileapi::LockFileEx;
+    use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY};
+
+    let mut flags = 0;
+    if exclusive {
+        flags |= LOCKFILE_EXCLUSIVE_LOCK;
+    }
+    if !blocking {
+        flags |= LOCKFILE_FAIL_IMMEDIATELY;
+    }
+    unsafe {
+        let mut overlapped = mem::zeroed();
+        let handle = file.as_raw_handle() as HANDLE;
+        if LockFileEx(handle, flags, 0, !0, !0, &mut overlapped) != 0 {
+            return Ok(true);
+        }
+    }
+    let e = io::Error::last_os_error();
+    match e.raw_os_error() {
+        Some(code) if code as u32 == ERROR_LOCK_VIOLATION => Ok(false),
+        _ => Err(e),
+    }
+}
+
+pub fn unlock(file: &File) -> io::Result<()> {
+    use winapi::um::fileapi::UnlockFileEx;
+
+    unsafe {
+        let mut overlapped = mem::zeroed();
+        let handle = file.as_raw_handle() as HANDLE;
+        if UnlockFileEx(handle, 0, !0, !0, &mut overlapped) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+pub fn reflink(_source: &File, _dest: &File) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Allocate disk space for the first `len` bytes of `file` without changing
+/// its size, returning `Ok(false)` if the filesystem can't do that.
+pub fn reserve_extents(file: &File, len: u64) -> io::Result<bool> {
+    use winapi::shared::winerror::{ERROR_INVALID_FUNCTION, ERROR_NOT_SUPPORTED};
+
+    if len == 0 {
+        return Ok(true);
+    }
+    let mut info = unsafe { mem::zeroed::<FILE_ALLOCATION_INFO>() };
+    unsafe { *info.AllocationSize.QuadPart_mut() = len as i64 };
+    if unsafe {
+        SetFileInformationByHandle(
+            file.as_raw_handle() as HANDLE,
+            FileAllocationInfo,
+            &mut info as *mut FILE_ALLOCATION_INFO as *mut _,
+            mem::size_of::<FILE_ALLOCATION_INFO>() as u32,
+        )
+    } == 0
+    {
+        let e = io::Error::last_os_error();
+        return match e.raw_os_error().map(|code| code as u32) {
+            Some(ERROR_INVALID_FUNCTION) | Some(ERROR_NOT_SUPPORTED) => Ok(false),
+            _ => Err(e),
+        };
+    }
+    Ok(true)
+}
+
+pub fn link_anonymous(_file: &File, _path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn is_unsafe_dir(_dir: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+pub fn share_all(open_options: &mut OpenOptions) -> &mut OpenOptions {
+    open_options.share_mode(FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE)
+}
+
+fn share_flags(read: bool, write: bool, delete: bool) -> u32 {
+    let mut flags = 0;
+    if read {
+        flags |= FILE_SHARE_READ;
+    }
+    if write {
+        flags |= FILE_SHARE_WRITE;
+    }
+    if delete {
+        flags |= FILE_SHARE_DELETE;
+    }
+    flags
+}
+
+pub fn set_share_mode(open_options: &mut OpenOptions, read: bool, write: bool, delete: bool) {
+    open_options.share_mode(share_flags(read, write, delete));
+}
+
+pub fn create(dir: &Path, random_len: usize) -> io::Result<File> {
     util::create_helper(
         dir,
         OsStr::new(".tmp"),
         OsStr::new(""),
-        crate::NUM_RAND_CHARS,
+        random_len,
+        crate::Encoding::default(),
+        false,
         |path| {
             OpenOptions::new()
                 .create_new(true)
@@ -64,6 +328,24 @@ pub fn reopen(file: &File, _path: &Path) -> io::Result<File> {
     }
 }
 
+pub fn into_anonymous(file: File, _path: &Path) -> io::Result<File> {
+    // Open files can't be unlinked on Windows. Instead, get a new handle that
+    // deletes the file when closed and drop the old one.
+    unsafe {
+        let handle = ReOpenFile(
+            file.as_raw_handle() as HANDLE,
+            FILE_GENERIC_READ | FILE_GENERIC_WRITE | DELETE,
+            FILE_SHARE_DELETE | FILE_SHARE_READ | FILE_SHARE_WRITE,
+            FILE_FLAG_DELETE_ON_CLOSE,
+        );
+        if handle == INVALID_HANDLE_VALUE {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(FromRawHandle::from_raw_handle(handle as RawHandle))
+        }
+    }
+}
+
 pub fn keep(path: &Path) -> io::Result<()> {
     unsafe {
         let path_w = to_utf16(path);
@@ -106,3 +388,100 @@ pub fn persist(old_path: &Path, new_path: &Path, overwrite: bool) -> io::Result<
         }
     }
 }
+
+pub fn fs_kind(path: &Path) -> io::Result<FsKind> {
+    const MAX_PATH: usize = 261;
+
+    let path = to_utf16(path);
+    let mut root = vec![0u16; path.len().max(MAX_PATH)];
+    let mut name = [0u16; MAX_PATH];
+    unsafe {
+        if GetVolumePathNameW(path.as_ptr(), root.as_mut_ptr(), root.len() as u32) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if GetVolumeInformationW(
+            root.as_ptr(),
+            ptr::null_mut(),
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            name.as_mut_ptr(),
+            name.len() as u32,
+        ) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    Ok(FsKind::from_name(&String::from_utf16_lossy(&name[..len])))
+}
+
+/// The number of bytes available to the current user on the volume
+/// containing `path`.
+pub fn free_space(path: &Path) -> io::Result<u64> {
+    let path = to_utf16(path);
+    let mut available = unsafe { mem::zeroed::<ULARGE_INTEGER>() };
+    if unsafe {
+        GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { *available.QuadPart() })
+}
+
+pub fn persist_transacted(
+    old_path: &Path,
+    new_path: &Path,
+    transaction: RawHandle,
+) -> io::Result<()> {
+    use winapi::shared::winerror::{
+        ERROR_INVALID_FUNCTION, ERROR_NOT_SUPPORTED, ERROR_RM_NOT_ACTIVE,
+        ERROR_TRANSACTIONS_UNSUPPORTED_REMOTE,
+    };
+
+    unsafe {
+        let old_path_w = to_utf16(old_path);
+        let new_path_w = to_utf16(new_path);
+
+        // See `persist`.
+        if SetFileAttributesW(old_path_w.as_ptr(), FILE_ATTRIBUTE_NORMAL) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if MoveFileTransactedW(
+            old_path_w.as_ptr(),
+            new_path_w.as_ptr(),
+            None,
+            ptr::null_mut(),
+            MOVEFILE_REPLACE_EXISTING,
+            transaction as HANDLE,
+        ) != 0
+        {
+            return Ok(());
+        }
+
+        let e = io::Error::last_os_error();
+        match e.raw_os_error().map(|code| code as u32) {
+            // Transactions aren't available here; settle for a regular rename.
+            Some(ERROR_INVALID_FUNCTION)
+            | Some(ERROR_NOT_SUPPORTED)
+            | Some(ERROR_RM_NOT_ACTIVE)
+            | Some(ERROR_TRANSACTIONS_UNSUPPORTED_REMOTE) => persist(old_path, new_path, true),
+            _ => {
+                let _ = SetFileAttributesW(old_path_w.as_ptr(), FILE_ATTRIBUTE_TEMPORARY);
+                Err(e)
+            }
+        }
+    }
+}
+
+pub fn numa_node() -> Option<u32> {
+    None
+}
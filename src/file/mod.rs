@@ -1,18 +1,35 @@
 use std::env;
 use std::error;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
 use std::fmt;
-use std::fs::{self, File, OpenOptions};
-use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{self, BufRead, BufWriter, Read, Seek, SeekFrom, Write};
 use std::mem;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::path::{Path, PathBuf};
 
+use crate::enospc::{EnospcHook, EnospcRetryingWriter};
 use crate::error::IoResultExt;
+use crate::lock::FileLock;
+use crate::rewind::RewindReader;
+use crate::split::{self, TempReader, TempWriter};
+use crate::util;
 use crate::Builder;
 
 mod imp;
 
+#[cfg(unix)]
+pub(crate) use self::imp::set_xattr;
+#[cfg(unix)]
+pub(crate) use self::imp::{create_at, dup_inheritable, open_parent_dir, rename_at, unlink_at};
+pub(crate) use self::imp::{
+    describe_open_flags, exchange, free_space, fs_kind, is_enospc, is_same_file, is_unsafe_dir,
+    lock, numa_node, reflink, remove_file, reserve_extents, set_inheritable, set_share_mode,
+    share_all, unlock,
+};
+#[cfg(target_os = "linux")]
+pub(crate) use self::imp::{mount_tmpfs, unmount};
+
 /// Create a new temporary file.
 ///
 /// The file will be created in the location returned by [`std::env::temp_dir()`].
@@ -52,7 +69,7 @@ mod imp;
 ///
 /// [`std::env::temp_dir()`]: https://doc.rust-lang.org/std/env/fn.temp_dir.html
 pub fn tempfile() -> io::Result<File> {
-    tempfile_in(&env::temp_dir())
+    tempfile_in(crate::default_temp_dir())
 }
 
 /// Create a new temporary file in the specified directory.
@@ -93,7 +110,136 @@ pub fn tempfile() -> io::Result<File> {
 ///
 /// [`std::env::temp_dir()`]: https://doc.rust-lang.org/std/env/fn.temp_dir.html
 pub fn tempfile_in<P: AsRef<Path>>(dir: P) -> io::Result<File> {
-    imp::create(dir.as_ref())
+    imp::create(dir.as_ref(), crate::NUM_RAND_CHARS)
+}
+
+/// Create a new temporary file in [`std::env::temp_dir()`], using
+/// `rand_len` random characters for its name instead of the default 6.
+///
+/// This is [`tempfile()`] with a different collision budget, for one-off
+/// calls that don't warrant a [`Builder`]. Where the file is created without
+/// any name at all (with `O_TMPFILE` on Linux), `rand_len` has no effect.
+///
+/// # Security
+///
+/// See [`tempfile()`].
+///
+/// # Errors
+///
+/// If the file can not be created, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Write};
+///
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// let mut file = tempfile::tempfile_with_rand(16)?;
+/// writeln!(file, "Brian was here. Briefly.")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`tempfile()`]: fn.tempfile.html
+/// [`Builder`]: struct.Builder.html
+/// [`std::env::temp_dir()`]: https://doc.rust-lang.org/std/env/fn.temp_dir.html
+pub fn tempfile_with_rand(rand_len: usize) -> io::Result<File> {
+    imp::create(crate::default_temp_dir(), rand_len)
+}
+
+/// Buffer everything read from `reader` in a new named temporary file, e.g.
+/// to be able to seek in a stream.
+///
+/// The file is created in `std::env::temp_dir()` like with
+/// [`NamedTempFile::new`], filled with `io::copy`, and rewound, so it's
+/// ready to be read from the start.
+///
+/// # Errors
+///
+/// If the file can't be created, or reading from `reader` or writing to the
+/// file fails, `Err` is returned and the file is deleted.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Read, Seek, SeekFrom};
+///
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// let stream = io::repeat(b'x').take(4);
+/// let mut file = tempfile::from_reader(stream)?;
+/// file.seek(SeekFrom::Start(2))?;
+/// let mut rest = String::new();
+/// file.read_to_string(&mut rest)?;
+/// assert_eq!(rest, "xx");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`NamedTempFile::new`]: struct.NamedTempFile.html#method.new
+pub fn from_reader<R: Read>(mut reader: R) -> io::Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    io::copy(&mut reader, &mut file)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Create a new temporary file, along with a `/proc` path referring to it.
+///
+/// This is [`tempfile()`] for debugging: the file is just as anonymous, but
+/// the returned `/proc/self/fd/N` path can be logged, shown to a developer
+/// or passed to a tool that insists on a path. Opening it opens the
+/// temporary file (the kernel resolves the magic link even though the file has
+/// no name), but only from within this process and only while the file is
+/// open.
+///
+/// Linux only.
+///
+/// # Stability
+///
+/// The path merely names a file descriptor: it's only meaningful in this
+/// process, and once the `File` is closed it may refer to whatever unrelated
+/// file later reuses the descriptor number. Don't store it or rely on it
+/// outside of diagnostics.
+///
+/// # Errors
+///
+/// If the file can not be created, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{self, Write};
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// let (mut file, path) = tempfile::tempfile_with_proc_path()?;
+/// writeln!(file, "Brian was here. Briefly.")?;
+/// eprintln!("scratch data is in {}", path.display());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`tempfile()`]: fn.tempfile.html
+#[cfg(target_os = "linux")]
+pub fn tempfile_with_proc_path() -> io::Result<(File, PathBuf)> {
+    use std::os::unix::io::AsRawFd;
+
+    let file = tempfile()?;
+    let path = PathBuf::from(format!("/proc/self/fd/{}", file.as_raw_fd()));
+    Ok((file, path))
 }
 
 /// Error returned when persisting a temporary file path fails.
@@ -137,8 +283,51 @@ impl error::Error for PathPersistError {
 /// for example.
 ///
 /// When dropped, the temporary file is deleted.
+///
+/// `TempPath` dereferences to a [`Path`], so `Path` methods can be called on it
+/// directly and it can be passed wherever a `&Path` is expected. It also
+/// implements `AsRef<Path>` for functions that take `impl AsRef<Path>`.
+///
+/// ```
+/// # use std::io;
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// use std::path::Path;
+/// use tempfile::Builder;
+///
+/// fn takes_path(_path: &Path) {}
+///
+/// let path = Builder::new().suffix(".txt").tempfile()?.into_temp_path();
+/// assert_eq!(path.extension().unwrap(), "txt");
+/// takes_path(&path);
+/// std::fs::write(&path, b"contents")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Path`]: https://doc.rust-lang.org/std/path/struct.Path.html
 pub struct TempPath {
     path: PathBuf,
+    // A boxed slice rather than a `Vec`, to keep `PersistError` small.
+    parent_dirs: Box<[PathBuf]>,
+    // Set once the file has been deleted, persisted or kept, after which
+    // neither `close` nor `Drop` touch the path again: something else may have
+    // been created there in the meantime. Handles that joined a file shared
+    // with `shared_append` start out disarmed, as they don't own the file.
+    disarmed: bool,
+    // Byte range of the random token within the file name, if any.
+    rand_token: Range<usize>,
+    // See `Builder::keep_dir_handle`. Windows has no use for it, and another
+    // handle there would make `PersistError` noticeably larger.
+    #[cfg(unix)]
+    dir_handle: Option<File>,
+    // Where the path is recorded for `cleanup_on_signals`, if it is.
+    #[cfg(unix)]
+    signal_slot: Option<usize>,
 }
 
 impl TempPath {
@@ -146,6 +335,13 @@ impl TempPath {
     ///
     /// Use this if you want to detect errors in deleting the file.
     ///
+    /// On Windows, another program (typically a virus scanner or the search
+    /// indexer) briefly opening the file makes deleting it fail with a
+    /// sharing violation or "access denied". Deletion is therefore attempted
+    /// up to 5 times, waiting 10 ms after the first failure and twice as long
+    /// after each further one (150 ms in total), both here and when the path
+    /// is dropped.
+    ///
     /// # Errors
     ///
     /// If the file cannot be deleted, `Err` is returned.
@@ -176,12 +372,32 @@ impl TempPath {
     /// # }
     /// ```
     pub fn close(mut self) -> io::Result<()> {
-        let result = fs::remove_file(&self.path).with_err_path(|| &self.path);
-        self.path = PathBuf::new();
-        mem::forget(self);
+        let result = if !self.disarmed {
+            imp::remove_file(&self.path).with_err_path(|| &self.path)
+        } else {
+            Ok(())
+        };
+        self.defuse(true);
         result
     }
 
+    /// Query metadata about the file at the temporary path.
+    ///
+    /// Unlike [`NamedTempFile::metadata`], this looks the path up again with
+    /// `fs::metadata` (following symlinks). If the file was deleted or replaced
+    /// by something else, for example by a temporary file cleaner, this fails
+    /// or describes the new file instead.
+    ///
+    /// # Errors
+    ///
+    /// If nothing exists at the path or its metadata can't be queried, `Err`
+    /// is returned.
+    ///
+    /// [`NamedTempFile::metadata`]: struct.NamedTempFile.html#method.metadata
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        fs::metadata(&self.path).with_err_path(|| &self.path)
+    }
+
     /// Persist the temporary file at the target path.
     ///
     /// If a file exists at the target path, persist will atomically replace it.
@@ -225,21 +441,16 @@ impl TempPath {
     /// ```
     ///
     /// [`PathPersistError`]: struct.PathPersistError.html
-    pub fn persist<P: AsRef<Path>>(mut self, new_path: P) -> Result<(), PathPersistError> {
-        match imp::persist(&self.path, new_path.as_ref(), true) {
-            Ok(_) => {
-                // Don't drop `self`. We don't want to try deleting the old
-                // temporary file path. (It'll fail, but the failure is never
-                // seen.)
-                self.path = PathBuf::new();
-                mem::forget(self);
-                Ok(())
-            }
-            Err(e) => Err(PathPersistError {
-                error: e,
-                path: self,
-            }),
-        }
+    pub fn persist<P: AsRef<Path>>(self, new_path: P) -> Result<(), PathPersistError> {
+        let result = imp::persist(&self.path, new_path.as_ref(), true);
+        trace_event!(
+            from = %self.path.display(),
+            to = %new_path.as_ref().display(),
+            overwrite = true,
+            result = ?result,
+            "persisting temporary file"
+        );
+        self.finish_persist(result)
     }
 
     /// Persist the temporary file at the target path if and only if no file exists there.
@@ -284,24 +495,16 @@ impl TempPath {
     /// ```
     ///
     /// [`PathPersistError`]: struct.PathPersistError.html
-    pub fn persist_noclobber<P: AsRef<Path>>(
-        mut self,
-        new_path: P,
-    ) -> Result<(), PathPersistError> {
-        match imp::persist(&self.path, new_path.as_ref(), false) {
-            Ok(_) => {
-                // Don't drop `self`. We don't want to try deleting the old
-                // temporary file path. (It'll fail, but the failure is never
-                // seen.)
-                self.path = PathBuf::new();
-                mem::forget(self);
-                Ok(())
-            }
-            Err(e) => Err(PathPersistError {
-                error: e,
-                path: self,
-            }),
-        }
+    pub fn persist_noclobber<P: AsRef<Path>>(self, new_path: P) -> Result<(), PathPersistError> {
+        let result = imp::persist(&self.path, new_path.as_ref(), false);
+        trace_event!(
+            from = %self.path.display(),
+            to = %new_path.as_ref().display(),
+            overwrite = false,
+            result = ?result,
+            "persisting temporary file"
+        );
+        self.finish_persist(result)
     }
 
     /// Keep the temporary file from being deleted. This function will turn the
@@ -338,11 +541,10 @@ impl TempPath {
     pub fn keep(mut self) -> Result<PathBuf, PathPersistError> {
         match imp::keep(&self.path) {
             Ok(_) => {
-                // Don't drop `self`. We don't want to try deleting the old
+                // Disarm `self`. We don't want to try deleting the old
                 // temporary file path. (It'll fail, but the failure is never
                 // seen.)
-                let path = mem::replace(&mut self.path, PathBuf::new());
-                mem::forget(self);
+                let path = self.defuse(false);
                 Ok(path)
             }
             Err(e) => Err(PathPersistError {
@@ -351,6 +553,86 @@ impl TempPath {
             }),
         }
     }
+
+    pub(crate) fn set_parent_dirs(&mut self, parent_dirs: Vec<PathBuf>) {
+        self.parent_dirs = parent_dirs.into_boxed_slice();
+    }
+
+    /// Get the random token that was generated for the file name.
+    ///
+    /// This is the part of the name between the prefix and the suffix, e.g.
+    /// `AbC123` for `.tmpAbC123.txt`, and is handy for correlating a
+    /// temporary file with log lines. It's empty if the name wasn't randomly
+    /// generated (see [`Builder::exact_name`]).
+    ///
+    /// [`Builder::exact_name`]: struct.Builder.html#method.exact_name
+    pub fn rand_token(&self) -> &OsStr {
+        util::name_slice(&self.path, &self.rand_token)
+    }
+
+    /// Get the handle to the file's parent directory, if it was created with
+    /// [`Builder::keep_dir_handle`].
+    ///
+    /// [`Builder::keep_dir_handle`]: struct.Builder.html#method.keep_dir_handle
+    pub fn parent_dir(&self) -> Option<&File> {
+        #[cfg(unix)]
+        return self.dir_handle.as_ref();
+        #[cfg(not(unix))]
+        None
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn set_dir_handle(&mut self, dir_handle: File) {
+        self.dir_handle = Some(dir_handle);
+    }
+
+    /// Disarm `self` after the file has been moved away (we don't want to try
+    /// deleting the old temporary file path; it'll fail, but the failure is
+    /// never seen), or hand it back on failure.
+    fn finish_persist(mut self, result: io::Result<()>) -> Result<(), PathPersistError> {
+        match result {
+            Ok(()) => {
+                self.defuse(true);
+                Ok(())
+            }
+            Err(error) => Err(PathPersistError { error, path: self }),
+        }
+    }
+
+    /// Disarm the path so that dropping it doesn't try to delete anything,
+    /// returning the old path. If `remove_parent_dirs` is set, the directories
+    /// created for a nested prefix are removed now.
+    fn defuse(&mut self, remove_parent_dirs: bool) -> PathBuf {
+        self.disarmed = true;
+        self.unregister_signal_cleanup();
+        let parent_dirs = mem::take(&mut self.parent_dirs);
+        if remove_parent_dirs {
+            util::remove_dirs(&parent_dirs);
+        }
+        mem::replace(&mut self.path, PathBuf::new())
+    }
+
+    /// An empty, disarmed stand-in left behind by `NamedTempFile::take_parts`.
+    fn taken() -> TempPath {
+        TempPath {
+            path: PathBuf::new(),
+            parent_dirs: Box::default(),
+            disarmed: true,
+            rand_token: 0..0,
+            #[cfg(unix)]
+            dir_handle: None,
+            #[cfg(unix)]
+            signal_slot: None,
+        }
+    }
+
+    /// Stop deleting the path on signals (see `cleanup_on_signals`).
+    fn unregister_signal_cleanup(&mut self) {
+        #[cfg(unix)]
+        if let Some(slot) = self.signal_slot.take() {
+            crate::signals::unregister(slot);
+        }
+    }
 }
 
 impl fmt::Debug for TempPath {
@@ -361,7 +643,12 @@ impl fmt::Debug for TempPath {
 
 impl Drop for TempPath {
     fn drop(&mut self) {
-        let _ = fs::remove_file(&self.path);
+        self.unregister_signal_cleanup();
+        if !self.disarmed {
+            let _result = imp::remove_file(&self.path);
+            trace_event!(path = %self.path.display(), result = ?_result, "removing temporary file");
+            util::remove_dirs(&self.parent_dirs);
+        }
     }
 }
 
@@ -468,7 +755,11 @@ impl AsRef<OsStr> for TempPath {
 /// [`std::process::exit()`]: http://doc.rust-lang.org/std/process/fn.exit.html
 pub struct NamedTempFile {
     path: TempPath,
-    file: File,
+    // Only `None` after `take_parts`, which the destructor then has to skip.
+    file: Option<File>,
+    sync_on_drop: bool,
+    wipe_on_drop: bool,
+    on_enospc: Option<EnospcHook>,
 }
 
 impl fmt::Debug for NamedTempFile {
@@ -519,6 +810,66 @@ impl error::Error for PersistError {
     }
 }
 
+/// Error returned by [`NamedTempFile::persist_durable`].
+///
+/// [`NamedTempFile::persist_durable`]: struct.NamedTempFile.html#method.persist_durable
+#[derive(Debug)]
+pub enum DurablePersistError {
+    /// The file couldn't be moved, and is handed back still temporary.
+    Persist(PersistError),
+    /// The file was moved to `path`, but its directory couldn't be
+    /// synchronized afterwards, so the rename may not survive a crash.
+    ///
+    /// The file is no longer temporary: it stays at `path` whatever happens
+    /// to this error.
+    Sync {
+        /// The underlying IO error.
+        error: io::Error,
+        /// Where the file was persisted to.
+        path: PathBuf,
+        /// The persisted file.
+        file: File,
+    },
+}
+
+impl From<PersistError> for DurablePersistError {
+    #[inline]
+    fn from(error: PersistError) -> DurablePersistError {
+        DurablePersistError::Persist(error)
+    }
+}
+
+impl From<DurablePersistError> for io::Error {
+    fn from(error: DurablePersistError) -> io::Error {
+        match error {
+            DurablePersistError::Persist(error) => error.into(),
+            DurablePersistError::Sync { error, path, .. } => crate::error::with_path(error, path),
+        }
+    }
+}
+
+impl fmt::Display for DurablePersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DurablePersistError::Persist(error) => error.fmt(f),
+            DurablePersistError::Sync { error, path, .. } => write!(
+                f,
+                "persisted temporary file to {:?}, but failed to sync its directory: {}",
+                path, error
+            ),
+        }
+    }
+}
+
+impl error::Error for DurablePersistError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DurablePersistError::Persist(error) => Some(error),
+            DurablePersistError::Sync { error, .. } => Some(error),
+        }
+    }
+}
+
 impl NamedTempFile {
     /// Create a new named temporary file.
     ///
@@ -590,49 +941,98 @@ impl NamedTempFile {
         Builder::new().tempfile_in(dir)
     }
 
-    /// Get the temporary file's path.
+    /// Adopt an open file as a new named temporary file in `dir`.
+    ///
+    /// This gives a file from elsewhere, e.g. a `memfd` or a file opened with
+    /// `O_TMPFILE`, a freshly generated name in `dir` (like the ones
+    /// [`NamedTempFile::new_in`] picks) that is deleted when the returned
+    /// `NamedTempFile` is dropped.
+    ///
+    /// On Linux, a file that has no name and lives on the same filesystem as
+    /// `dir` (an `O_TMPFILE` file) is linked into `dir` in place, so the
+    /// returned `NamedTempFile` wraps the very same handle and nothing is
+    /// copied. Everything else, including memfds, files on other filesystems,
+    /// files that already have a name, and every file on other platforms, is
+    /// copied: a new file is created in `dir` and `file`'s whole contents
+    /// are copied into it, which takes time (and space) proportional to its
+    /// size. The copy is independent of `file`, which is closed afterwards:
+    /// writes to one no longer show up in the other, and an existing name is
+    /// left alone.
+    ///
+    /// Either way, the cursor ends up at the position `file` had.
     ///
-    /// # Security
+    /// # Errors
     ///
-    /// Referring to a temporary file's path may not be secure in all cases.
-    /// Please read the security section on the top level documentation of this
-    /// type for details.
+    /// If the file can't be linked or copied, e.g. because it isn't seekable
+    /// (like a pipe), `Err` is returned.
     ///
     /// # Examples
     ///
-    /// ```no_run
-    /// # use std::io::{self, Write};
-    /// use tempfile::NamedTempFile;
-    ///
+    /// ```
+    /// # use std::io::{self, Read, Write};
     /// # fn main() {
     /// #     if let Err(_) = run() {
     /// #         ::std::process::exit(1);
     /// #     }
     /// # }
-    /// # fn run() -> Result<(), ::std::io::Error> {
-    /// let file = NamedTempFile::new()?;
+    /// # fn run() -> Result<(), io::Error> {
+    /// use tempfile::NamedTempFile;
     ///
-    /// println!("{:?}", file.path());
+    /// let mut anonymous = tempfile::tempfile()?;
+    /// anonymous.write_all(b"materialized")?;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// let named = NamedTempFile::adopt(anonymous, dir.path())?;
+    /// assert_eq!(std::fs::read(named.path())?, b"materialized");
     /// # Ok(())
     /// # }
     /// ```
-    #[inline]
-    pub fn path(&self) -> &Path {
-        &self.path
+    ///
+    /// [`NamedTempFile::new_in`]: #method.new_in
+    pub fn adopt<P: AsRef<Path>>(file: File, dir: P) -> io::Result<NamedTempFile> {
+        let dir = dir.as_ref();
+        if cfg!(target_os = "linux") && imp::link_count(&file).with_err_path(|| dir)? == 0 {
+            let linked = util::create_helper(
+                dir,
+                OsStr::new(".tmp"),
+                OsStr::new(""),
+                crate::NUM_RAND_CHARS,
+                crate::Encoding::default(),
+                false,
+                |path| Ok(imp::link_anonymous(&file, &path)?.then_some(path)),
+            )?;
+            if let Some(path) = linked {
+                let rand_token = 4..4 + crate::NUM_RAND_CHARS;
+                return Ok(create_named_with(path, |_| Ok(file))?.set_rand_token(rand_token));
+            }
+        }
+        let mut source = file;
+        let pos = source.stream_position().with_err_path(|| dir)?;
+        source.seek(SeekFrom::Start(0)).with_err_path(|| dir)?;
+        let mut adopted = NamedTempFile::new_in(dir)?;
+        fill_from(&source, adopted.as_file()).with_err_path(|| adopted.path())?;
+        adopted.seek(SeekFrom::Start(pos))?;
+        Ok(adopted)
     }
 
-    /// Close and remove the temporary file.
+    /// Create a new named temporary file next to `target`, ready to be
+    /// persisted over it.
     ///
-    /// Use this if you want to detect errors in deleting the file.
+    /// The file is created in `target`'s directory, so that [`persist`] is a
+    /// same-filesystem rename, and is named like a hidden sibling keeping the
+    /// extension: `config.toml` gets a temporary file such as
+    /// `.config.toml.AbC123.toml`.
     ///
     /// # Errors
     ///
-    /// If the file cannot be deleted, `Err` is returned.
+    /// If `target` has no parent directory or file name (e.g. `/` or `..`),
+    /// an [`io::ErrorKind::InvalidInput`] error is returned. Otherwise, errors
+    /// are the same as for [`NamedTempFile::new_in`].
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use std::io;
+    /// # use std::io::{self, Write};
     /// use tempfile::NamedTempFile;
     ///
     /// # fn main() {
@@ -640,48 +1040,73 @@ impl NamedTempFile {
     /// #         ::std::process::exit(1);
     /// #     }
     /// # }
-    /// # fn run() -> Result<(), io::Error> {
-    /// let file = NamedTempFile::new()?;
-    ///
-    /// // By closing the `NamedTempFile` explicitly, we can check that it has
-    /// // been deleted successfully. If we don't close it explicitly,
-    /// // the file will still be deleted when `file` goes out
-    /// // of scope, but we won't know whether deleting the file
-    /// // succeeded.
-    /// file.close()?;
+    /// # fn run() -> Result<(), ::std::io::Error> {
+    /// let mut file = NamedTempFile::for_target("/etc/app/config.toml")?;
+    /// writeln!(file, "answer = 42")?;
+    /// file.persist("/etc/app/config.toml")?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn close(self) -> io::Result<()> {
-        let NamedTempFile { path, .. } = self;
-        path.close()
+    ///
+    /// [`persist`]: #method.persist
+    /// [`NamedTempFile::new_in`]: #method.new_in
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn for_target<P: AsRef<Path>>(target: P) -> io::Result<NamedTempFile> {
+        let target = target.as_ref();
+        let (dir, name) = match (target.parent(), target.file_name()) {
+            (Some(dir), Some(name)) => (dir, name),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "target path has no parent directory or file name",
+                ))
+                .with_err_path(|| target)
+            }
+        };
+        let mut prefix = OsString::from(".");
+        prefix.push(name);
+        prefix.push(".");
+        let mut suffix = OsString::new();
+        if let Some(extension) = target.extension() {
+            suffix.push(".");
+            suffix.push(extension);
+        }
+        Builder::new()
+            .prefix(&prefix)
+            .suffix(&suffix)
+            .tempfile_in(dir)
     }
 
-    /// Persist the temporary file at the target path.
-    ///
-    /// If a file exists at the target path, persist will atomically replace it.
-    /// If this method fails, it will return `self` in the resulting
-    /// [`PersistError`].
-    ///
-    /// Note: Temporary files cannot be persisted across filesystems. Also
-    /// neither the file contents nor the containing directory are
-    /// synchronized, so the update may not yet have reached the disk when
-    /// `persist` returns.
-    ///
-    /// # Security
-    ///
-    /// This method persists the temporary file using its path and may not be
-    /// secure in the in all cases. Please read the security section on the top
-    /// level documentation of this type for details.
+    /// Start editing `path` atomically: create a temporary file next to it
+    /// holding a copy of its contents, ready to be persisted back over it.
+    ///
+    /// This is the read-modify-write cycle on top of
+    /// [`NamedTempFile::for_target`]: modify the returned file, then
+    /// [`persist`] it to `path` to replace the original in one step, or drop
+    /// it to abandon the edit. Readers of `path` see either the old contents
+    /// or the new, never a mix.
+    ///
+    /// The whole file is copied up front, which takes time and space
+    /// proportional to its size (unless the filesystem supports reflinks, see
+    /// [`Builder::reflink_from`]). Its permissions are copied as well, so that
+    /// persisting keeps them. If `path` doesn't exist, the temporary file
+    /// starts out empty, and persisting creates it. Either way, the cursor is
+    /// at the start.
+    ///
+    /// Concurrent edits aren't coordinated: if two edits of the same file
+    /// overlap, the one persisted last wins and the other's changes are
+    /// lost. Use a lock (e.g. [`NamedTempFile::lock_exclusive`] on a separate
+    /// lock file) if that matters.
     ///
     /// # Errors
     ///
-    /// If the file cannot be moved to the new location, `Err` is returned.
+    /// Errors from [`NamedTempFile::for_target`] are returned, as are errors
+    /// reading `path` other than it not existing.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use std::io::{self, Write};
+    /// # use std::io::{self, Seek, SeekFrom, Write};
     /// use tempfile::NamedTempFile;
     ///
     /// # fn main() {
@@ -690,37 +1115,872 @@ impl NamedTempFile {
     /// #     }
     /// # }
     /// # fn run() -> Result<(), io::Error> {
-    /// let file = NamedTempFile::new()?;
-    ///
-    /// let mut persisted_file = file.persist("./saved_file.txt")?;
-    /// writeln!(persisted_file, "Brian was here. Briefly.")?;
+    /// let mut file = NamedTempFile::edit_in_place("counter.log")?;
+    /// file.seek(SeekFrom::End(0))?;
+    /// writeln!(file, "one more")?;
+    /// file.persist("counter.log")?;
     /// # Ok(())
     /// # }
     /// ```
     ///
-    /// [`PersistError`]: struct.PersistError.html
-    pub fn persist<P: AsRef<Path>>(self, new_path: P) -> Result<File, PersistError> {
-        let NamedTempFile { path, file } = self;
-        match path.persist(new_path) {
-            Ok(_) => Ok(file),
-            Err(err) => {
-                let PathPersistError { error, path } = err;
-                Err(PersistError {
-                    file: NamedTempFile { path, file },
-                    error,
-                })
-            }
-        }
+    /// [`NamedTempFile::for_target`]: #method.for_target
+    /// [`persist`]: #method.persist
+    /// [`Builder::reflink_from`]: struct.Builder.html#method.reflink_from
+    /// [`NamedTempFile::lock_exclusive`]: #method.lock_exclusive
+    pub fn edit_in_place<P: AsRef<Path>>(path: P) -> io::Result<NamedTempFile> {
+        let path = path.as_ref();
+        let file = NamedTempFile::for_target(path)?;
+        let original = match File::open(path) {
+            Ok(original) => original,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(file),
+            Err(e) => return Err(e).with_err_path(|| path),
+        };
+        let permissions = original.metadata().with_err_path(|| path)?.permissions();
+        fill_from(&original, file.as_file()).with_err_path(|| file.path())?;
+        file.as_file()
+            .set_permissions(permissions)
+            .with_err_path(|| file.path())?;
+        Ok(file)
     }
 
-    /// Persist the temporary file at the target path if and only if no file exists there.
-    ///
-    /// If a file exists at the target path, fail. If this method fails, it will
-    /// return `self` in the resulting PersistError.
-    ///
-    /// Note: Temporary files cannot be persisted across filesystems. Also Note:
-    /// This method is not atomic. It can leave the original link to the
-    /// temporary file behind.
+    /// Get the temporary file's path.
+    ///
+    /// # Security
+    ///
+    /// Referring to a temporary file's path may not be secure in all cases.
+    /// Please read the security section on the top level documentation of this
+    /// type for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), ::std::io::Error> {
+    /// let file = NamedTempFile::new()?;
+    ///
+    /// println!("{:?}", file.path());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get the random token that was generated for the file name.
+    ///
+    /// See [`TempPath::rand_token`].
+    ///
+    /// [`TempPath::rand_token`]: struct.TempPath.html#method.rand_token
+    pub fn rand_token(&self) -> &OsStr {
+        self.path.rand_token()
+    }
+
+    /// Get the handle to the file's parent directory, if it was created with
+    /// [`Builder::keep_dir_handle`].
+    ///
+    /// [`Builder::keep_dir_handle`]: struct.Builder.html#method.keep_dir_handle
+    pub fn parent_dir(&self) -> Option<&File> {
+        self.path.parent_dir()
+    }
+
+    /// Query metadata about the temporary file through its open handle.
+    ///
+    /// This is equivalent to `as_file().metadata()`, but errors mention the
+    /// file's path. Because it goes through the handle, it still describes
+    /// this file even if the path has since been removed or replaced by
+    /// something else; see [`TempPath::metadata`] for the path-based variant.
+    ///
+    /// # Errors
+    ///
+    /// If the metadata can't be queried, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), ::std::io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// write!(file, "abcde")?;
+    /// assert_eq!(file.metadata()?.len(), 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TempPath::metadata`]: struct.TempPath.html#method.metadata
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        self.as_file().metadata().with_err_path(|| self.path())
+    }
+
+    /// Send up to `len` bytes of the file, starting at `offset`, to `socket`
+    /// without copying them through user space (Unix only).
+    ///
+    /// This loops over the platform's zero-copy primitive until `len` bytes
+    /// have been sent, the end of the file is reached, or `socket` would
+    /// block, retrying on `EINTR`. It returns the number of bytes sent. The
+    /// file's own cursor isn't moved.
+    ///
+    /// * On Linux, this uses `sendfile(2)`, which accepts any kind of output
+    ///   file descriptor, not just sockets.
+    /// * On macOS, iOS and FreeBSD, it uses the BSD `sendfile(2)`, which
+    ///   requires `socket` to be a stream socket.
+    /// * Elsewhere, the data is read with `pread` and written to `socket` with
+    ///   an ordinary copy through a buffer.
+    ///
+    /// # Errors
+    ///
+    /// If nothing could be sent, the underlying error is returned; in
+    /// particular, a non-blocking socket that isn't ready yields
+    /// [`io::ErrorKind::WouldBlock`]. If some data was sent before the socket
+    /// would block, the partial count is returned instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use std::net::TcpStream;
+    /// use std::os::unix::io::AsRawFd;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), ::std::io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// write!(file, "HTTP/1.0 200 OK\r\n\r\nhello")?;
+    ///
+    /// let client = TcpStream::connect("127.0.0.1:8080")?;
+    /// let len = file.metadata()?.len() as usize;
+    /// file.sendfile_to(client.as_raw_fd(), 0, len)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`io::ErrorKind::WouldBlock`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.WouldBlock
+    #[cfg(unix)]
+    pub fn sendfile_to(
+        &self,
+        socket: std::os::unix::io::RawFd,
+        offset: u64,
+        len: usize,
+    ) -> io::Result<usize> {
+        imp::sendfile(self.as_file(), socket, offset, len).with_err_path(|| self.path())
+    }
+
+    /// Copy the full contents of the temporary file to `dest`, leaving the
+    /// temporary file itself (including its cursor) untouched.
+    ///
+    /// Unlike [`persist`], this neither consumes nor moves the temporary file,
+    /// so it can be kept around and copied again later. `dest` is created or
+    /// truncated. The copy goes through [`std::fs::copy`], which uses
+    /// `copy_file_range(2)` (or `sendfile(2)`) on Linux, `fcopyfile(3)` on
+    /// macOS and `CopyFileExW` on Windows when available, falling back to an
+    /// ordinary copy through a buffer. Like `std::fs::copy`, it also copies the
+    /// temporary file's permissions, which are usually restrictive.
+    ///
+    /// Returns the number of bytes copied.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be copied, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// writeln!(file, "partial results")?;
+    /// file.copy_to("./snapshot.txt")?;
+    /// writeln!(file, "more results")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`persist`]: #method.persist
+    /// [`std::fs::copy`]: https://doc.rust-lang.org/std/fs/fn.copy.html
+    pub fn copy_to<P: AsRef<Path>>(&self, dest: P) -> io::Result<u64> {
+        let dest = dest.as_ref();
+        // `fs::copy` opens the file afresh, so our cursor isn't moved.
+        fs::copy(self.path(), dest).with_err_path(|| dest)
+    }
+
+    /// Make sure the temporary file isn't a hard link to some other file
+    /// before relying on it (e.g. before persisting it).
+    ///
+    /// A freshly created temporary file has exactly one link, but a file that
+    /// was put together from an externally provided path or handle might not,
+    /// in which case writing to it would also modify the other file. This
+    /// checks the link count of the open file (`st_nlink` on Unix,
+    /// `nNumberOfLinks` from `GetFileInformationByHandle` on Windows).
+    ///
+    /// The file is only borrowed, so it's still there to deal with if the
+    /// check fails: e.g. break the alias with [`into_unique_link`], or
+    /// [`keep`] it.
+    ///
+    /// # Errors
+    ///
+    /// If the file has several links, an error of kind
+    /// [`io::ErrorKind::AlreadyExists`] is returned. Errors querying the link
+    /// count are also returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), ::std::io::Error> {
+    /// let file = NamedTempFile::new()?;
+    /// file.ensure_unique_link()?;
+    /// file.persist("./saved_file.txt")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`into_unique_link`]: #method.into_unique_link
+    /// [`keep`]: #method.keep
+    /// [`io::ErrorKind::AlreadyExists`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.AlreadyExists
+    pub fn ensure_unique_link(&self) -> io::Result<()> {
+        let links = imp::link_count(self.as_file()).with_err_path(|| self.path())?;
+        if links > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("temporary file has {} hard links", links),
+            ))
+            .with_err_path(|| self.path());
+        }
+        Ok(())
+    }
+
+    /// Break any hard link aliasing of the temporary file (see
+    /// [`ensure_unique_link`]) by copying it.
+    ///
+    /// If the file has a single link, it's returned as is. Otherwise, the
+    /// contents are copied to a new temporary file in the same directory,
+    /// which is returned with the same cursor position and drop-time
+    /// settings; the old temporary name is removed, leaving the other file's
+    /// links alone. The shared contents are never wiped (see
+    /// [`Builder::wipe_on_drop`]).
+    ///
+    /// # Errors
+    ///
+    /// Errors querying the link count or copying the file are returned. As
+    /// with a dropped `NamedTempFile`, the old temporary name is removed
+    /// then, but the other links are left alone.
+    ///
+    /// [`ensure_unique_link`]: #method.ensure_unique_link
+    /// [`Builder::wipe_on_drop`]: struct.Builder.html#method.wipe_on_drop
+    pub fn into_unique_link(mut self) -> io::Result<NamedTempFile> {
+        let links = imp::link_count(self.as_file()).with_err_path(|| self.path())?;
+        if links <= 1 {
+            return Ok(self);
+        }
+        // The contents are shared with the other links, so wiping them when
+        // this handle is dropped would destroy someone else's data.
+        let wipe_on_drop = mem::replace(&mut self.wipe_on_drop, false);
+        let dir = self.path().parent().unwrap_or_else(|| Path::new(""));
+        let mut fresh = NamedTempFile::new_in(dir)?
+            .set_sync_on_drop(self.sync_on_drop)
+            .set_wipe_on_drop(wipe_on_drop)
+            .set_on_enospc(self.on_enospc.clone());
+        let pos = self.stream_position()?;
+        self.seek(SeekFrom::Start(0))?;
+        io::copy(&mut self, &mut fresh)?;
+        fresh.seek(SeekFrom::Start(pos))?;
+        Ok(fresh)
+    }
+
+    /// Close and remove the temporary file.
+    ///
+    /// Use this if you want to detect errors in deleting the file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be deleted, or if it was created with
+    /// [`Builder::wipe_on_drop`] and can't be wiped, `Err` is returned. The
+    /// file is deleted even if wiping it fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let file = NamedTempFile::new()?;
+    ///
+    /// // By closing the `NamedTempFile` explicitly, we can check that it has
+    /// // been deleted successfully. If we don't close it explicitly,
+    /// // the file will still be deleted when `file` goes out
+    /// // of scope, but we won't know whether deleting the file
+    /// // succeeded.
+    /// file.close()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::wipe_on_drop`]: struct.Builder.html#method.wipe_on_drop
+    pub fn close(self) -> io::Result<()> {
+        let wiped = if self.wipe_on_drop && !self.path.disarmed {
+            self.wipe()
+        } else {
+            Ok(())
+        };
+        let (path, _) = self.take_parts();
+        let closed = path.close();
+        wiped.and(closed)
+    }
+
+    /// Close and remove the temporary file, and wait until it's really gone.
+    ///
+    /// On Unix, this is the same as [`close`]: the file's name is removed
+    /// right away. On Windows, a file that's still open elsewhere (e.g., by a
+    /// virus scanner or a handle from [`reopen`]) is only marked for deletion
+    /// and stays visible until the last handle is closed. This method closes
+    /// its own handle, deletes the file, and then checks every 10 ms, for up
+    /// to a second, whether the file has disappeared. This makes assertions
+    /// like `assert!(!path.exists())` reliable in tests.
+    ///
+    /// Files that this handle doesn't own (see [`Builder::shared_append`])
+    /// aren't deleted or waited for.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be deleted, `Err` is returned. If it's still there
+    /// after a second, an [`io::ErrorKind::TimedOut`] error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let file = NamedTempFile::new()?;
+    /// let path = file.path().to_path_buf();
+    /// file.ensure_deleted()?;
+    /// assert!(!path.exists());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`close`]: #method.close
+    /// [`reopen`]: #method.reopen
+    /// [`Builder::shared_append`]: struct.Builder.html#method.shared_append
+    /// [`io::ErrorKind::TimedOut`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.TimedOut
+    pub fn ensure_deleted(self) -> io::Result<()> {
+        let (path, file) = self.take_parts();
+        drop(file);
+        #[cfg(windows)]
+        let owned = (!path.disarmed).then(|| path.to_path_buf());
+        path.close()?;
+        #[cfg(windows)]
+        if let Some(path) = owned {
+            for _ in 0..100 {
+                match fs::symlink_metadata(&path) {
+                    Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+                    // Files pending deletion can't be queried anymore.
+                    _ => std::thread::sleep(std::time::Duration::from_millis(10)),
+                }
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "temporary file still exists after deleting it",
+            ))
+            .with_err_path(|| path);
+        }
+        Ok(())
+    }
+
+    /// Persist the temporary file at the target path.
+    ///
+    /// If a file exists at the target path, persist will atomically replace it.
+    /// If this method fails, it will return `self` in the resulting
+    /// [`PersistError`].
+    ///
+    /// Note: Temporary files cannot be persisted across filesystems. Also
+    /// neither the file contents nor the containing directory are
+    /// synchronized, so the update may not yet have reached the disk when
+    /// `persist` returns.
+    ///
+    /// # Security
+    ///
+    /// This method persists the temporary file using its path and may not be
+    /// secure in the in all cases. Please read the security section on the top
+    /// level documentation of this type for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let file = NamedTempFile::new()?;
+    ///
+    /// let mut persisted_file = file.persist("./saved_file.txt")?;
+    /// writeln!(persisted_file, "Brian was here. Briefly.")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`PersistError`]: struct.PersistError.html
+    pub fn persist<P: AsRef<Path>>(self, new_path: P) -> Result<File, PersistError> {
+        let sync_on_drop = self.sync_on_drop;
+        let wipe_on_drop = self.wipe_on_drop;
+        let on_enospc = self.on_enospc.clone();
+        let (path, file) = self.take_parts();
+        match path.persist(new_path) {
+            Ok(_) => Ok(file),
+            Err(err) => {
+                let PathPersistError { error, path } = err;
+                Err(PersistError {
+                    file: NamedTempFile {
+                        path,
+                        file: Some(file),
+                        sync_on_drop,
+                        wipe_on_drop,
+                        on_enospc,
+                    },
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Persist the temporary file at the target path, then synchronize the
+    /// target's directory so that the rename survives a crash.
+    ///
+    /// This is [`persist`] followed by an `fsync` of the directory, through
+    /// the handle retained with [`Builder::keep_dir_handle`] if there is one,
+    /// or by opening `new_path`'s parent otherwise. The retained handle is
+    /// that of the temporary file's directory, so only rely on it when
+    /// persisting within that directory. The file's contents aren't
+    /// synchronized; call `as_file().sync_all()` first for that.
+    ///
+    /// On Windows, directories can't be synchronized, so this is the same as
+    /// [`persist`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be moved (or, without a retained handle, the target
+    /// directory can't be opened), [`DurablePersistError::Persist`] is
+    /// returned, holding `self` as with [`persist`]. If the file was moved
+    /// but the directory couldn't be synchronized,
+    /// [`DurablePersistError::Sync`] is returned: by then the file is at
+    /// `new_path`, and the error holds its handle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// # let dir = tempfile::tempdir()?;
+    /// let mut file = Builder::new().keep_dir_handle(true).tempfile_in(&dir)?;
+    /// writeln!(file, "durable")?;
+    /// file.as_file().sync_all()?;
+    /// file.persist_durable(dir.path().join("saved.txt"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`persist`]: #method.persist
+    /// [`Builder::keep_dir_handle`]: struct.Builder.html#method.keep_dir_handle
+    /// [`DurablePersistError::Persist`]: enum.DurablePersistError.html#variant.Persist
+    /// [`DurablePersistError::Sync`]: enum.DurablePersistError.html#variant.Sync
+    pub fn persist_durable<P: AsRef<Path>>(self, new_path: P) -> Result<File, DurablePersistError> {
+        let new_path = new_path.as_ref();
+        let dir = match self.target_dir_handle(new_path) {
+            Ok(dir) => dir,
+            Err(error) => return Err(PersistError { error, file: self }.into()),
+        };
+        let file = self.persist(new_path)?;
+        if let Some(dir) = dir {
+            if let Err(error) = dir.sync_all() {
+                return Err(DurablePersistError::Sync {
+                    error,
+                    path: new_path.to_path_buf(),
+                    file,
+                });
+            }
+        }
+        Ok(file)
+    }
+
+    /// A handle to sync after persisting to `new_path`, for
+    /// `persist_durable`.
+    #[cfg(unix)]
+    fn target_dir_handle(&self, new_path: &Path) -> io::Result<Option<File>> {
+        if let Some(dir) = self.parent_dir() {
+            return dir.try_clone().map(Some);
+        }
+        let parent = match new_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent).with_err_path(|| parent).map(Some)
+    }
+
+    #[cfg(not(unix))]
+    fn target_dir_handle(&self, _new_path: &Path) -> io::Result<Option<File>> {
+        Ok(None)
+    }
+
+    /// Persist the temporary file at the target path, creating its parent
+    /// directories first if needed.
+    ///
+    /// Every missing ancestor of `new_path` is created, outermost first, with
+    /// the permission bits `dir_mode` on Unix (subject to the umask; ignored
+    /// on Windows). Directories that already exist are left alone. If
+    /// persisting fails, the directories created by this call are removed
+    /// again (as long as they're still empty).
+    ///
+    /// The file is then renamed into place, which atomically replaces any
+    /// existing file, like [`persist`]. If the temporary file lives on another
+    /// filesystem, where renaming isn't possible, its contents (and
+    /// permissions) are first copied into a new temporary file next to
+    /// `new_path`, which is renamed into place instead; the original
+    /// temporary file is then deleted. The returned handle refers to the
+    /// persisted file, with the same cursor position either way.
+    ///
+    /// # Security
+    ///
+    /// See [`persist`].
+    ///
+    /// # Errors
+    ///
+    /// If a directory can't be created, or the file can't be copied or moved
+    /// to the new location, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// writeln!(file, "Brian was here. Briefly.")?;
+    /// file.persist_creating_dirs("./out/2021/saved_file.txt", 0o755)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`persist`]: #method.persist
+    pub fn persist_creating_dirs<P: AsRef<Path>>(
+        self,
+        new_path: P,
+        dir_mode: u32,
+    ) -> Result<File, PersistError> {
+        let new_path = new_path.as_ref();
+        let created = match util::create_parent_dirs(new_path, dir_mode) {
+            Ok(created) => created,
+            Err(error) => return Err(PersistError { error, file: self }),
+        };
+        let result = match self.persist(new_path) {
+            Err(PersistError { error, file }) if imp::is_cross_device(&error) => {
+                file.relocate_and_persist(new_path)
+            }
+            res => res,
+        };
+        if result.is_err() {
+            util::remove_dirs(&created);
+        }
+        result
+    }
+
+    /// Copy the file into a new temporary file next to `new_path` and persist
+    /// that instead, for when renaming across filesystems isn't possible.
+    fn relocate_and_persist(self, new_path: &Path) -> Result<File, PersistError> {
+        let copy = (|| {
+            let dir = match new_path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let mut copy = NamedTempFile::new_in(dir)?;
+            let mut source = self.reopen()?;
+            io::copy(&mut source, copy.as_file_mut()).with_err_path(|| copy.path())?;
+            let permissions = self.metadata()?.permissions();
+            copy.as_file()
+                .set_permissions(permissions)
+                .with_err_path(|| copy.path())?;
+            let position = self
+                .as_file()
+                .stream_position()
+                .with_err_path(|| self.path())?;
+            copy.seek(SeekFrom::Start(position))?;
+            Ok(copy)
+        })();
+        match copy {
+            Ok(copy) => match copy.persist(new_path) {
+                // Dropping `self` deletes the original temporary file.
+                Ok(file) => Ok(file),
+                Err(err) => Err(PersistError {
+                    error: err.error,
+                    file: self,
+                }),
+            },
+            Err(error) => Err(PersistError { error, file: self }),
+        }
+    }
+
+    /// Persist the temporary file at the target path as part of a Windows
+    /// kernel transaction (Transactional NTFS).
+    ///
+    /// `transaction` is a handle returned by `CreateTransaction`; the rename
+    /// only becomes visible to others once the transaction is committed, which
+    /// makes it possible to replace several files atomically. Otherwise, this
+    /// behaves like [`persist`], replacing any existing file.
+    ///
+    /// Microsoft has deprecated Transactional NTFS and it isn't available on
+    /// every volume (e.g. ReFS or network shares). If the system reports that
+    /// transactions aren't supported, this falls back to a plain [`persist`],
+    /// which takes effect immediately and is *not* rolled back with the
+    /// transaction.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location, `Err` is returned. The
+    /// transaction may also refuse the rename (with
+    /// `ERROR_TRANSACTIONAL_CONFLICT`) if the files involved are in use outside
+    /// of it.
+    ///
+    /// [`persist`]: #method.persist
+    #[cfg(windows)]
+    pub fn persist_transacted<P: AsRef<Path>>(
+        self,
+        new_path: P,
+        transaction: std::os::windows::io::RawHandle,
+    ) -> Result<File, PersistError> {
+        let sync_on_drop = self.sync_on_drop;
+        let wipe_on_drop = self.wipe_on_drop;
+        let on_enospc = self.on_enospc.clone();
+        let (mut path, file) = self.take_parts();
+        match imp::persist_transacted(&path, new_path.as_ref(), transaction) {
+            Ok(()) => {
+                path.defuse(true);
+                Ok(file)
+            }
+            Err(error) => Err(PersistError {
+                file: NamedTempFile {
+                    path,
+                    file: Some(file),
+                    sync_on_drop,
+                    wipe_on_drop,
+                    on_enospc,
+                },
+                error,
+            }),
+        }
+    }
+
+    /// Persist the temporary file at the target path like [`persist`], and
+    /// keep a handle to the file it replaced, if any.
+    ///
+    /// Before renaming, the existing file at `new_path` is opened for reading.
+    /// After the swap, it's gone from the filesystem namespace, but the
+    /// returned handle still refers to it, so its old contents can be read
+    /// (e.g. to roll back) until the handle is closed, at which point its
+    /// storage is freed. If nothing exists at `new_path`, the second element
+    /// is `None`.
+    ///
+    /// On Windows, the old file is opened with `FILE_SHARE_DELETE` so that it
+    /// can still be replaced; whether its handle stays readable afterwards
+    /// depends on the filesystem.
+    ///
+    /// # Security
+    ///
+    /// Like [`persist`], this method uses the temporary file's path. Please
+    /// read the security section on the top level documentation of this type
+    /// for details.
+    ///
+    /// # Errors
+    ///
+    /// If the existing file can't be opened or the temporary file can't be
+    /// moved to the new location, `Err` is returned with `self`, and nothing
+    /// has been replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Read, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # let dir = tempfile::tempdir()?;
+    /// # let config = dir.path().join("config.toml");
+    /// # std::fs::write(&config, "old = true")?;
+    /// let mut file = NamedTempFile::new_in(&dir)?;
+    /// write!(file, "new = true")?;
+    ///
+    /// let (_new, old) = file.persist_swapping(&config)?;
+    /// let mut previous = String::new();
+    /// old.unwrap().read_to_string(&mut previous)?;
+    /// assert_eq!(previous, "old = true");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`persist`]: #method.persist
+    pub fn persist_swapping<P: AsRef<Path>>(
+        self,
+        new_path: P,
+    ) -> Result<(File, Option<File>), PersistError> {
+        let new_path = new_path.as_ref();
+        let old = match share_all(OpenOptions::new().read(true)).open(new_path) {
+            Ok(old) => Ok(Some(old)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        };
+        match old.with_err_path(|| new_path) {
+            Ok(old) => self.persist(new_path).map(|file| (file, old)),
+            Err(error) => Err(PersistError { error, file: self }),
+        }
+    }
+
+    /// Persist the temporary file at the target path if and only if no file exists there.
+    ///
+    /// If a file exists at the target path, fail. If this method fails, it will
+    /// return `self` in the resulting PersistError.
+    ///
+    /// Note: Temporary files cannot be persisted across filesystems. Also Note:
+    /// This method is not atomic. It can leave the original link to the
+    /// temporary file behind.
+    ///
+    /// # Security
+    ///
+    /// This method persists the temporary file using its path and may not be
+    /// secure in the in all cases. Please read the security section on the top
+    /// level documentation of this type for details.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be moved to the new location or a file already exists there,
+    /// `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let file = NamedTempFile::new()?;
+    ///
+    /// let mut persisted_file = file.persist_noclobber("./saved_file.txt")?;
+    /// writeln!(persisted_file, "Brian was here. Briefly.")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn persist_noclobber<P: AsRef<Path>>(self, new_path: P) -> Result<File, PersistError> {
+        let sync_on_drop = self.sync_on_drop;
+        let wipe_on_drop = self.wipe_on_drop;
+        let on_enospc = self.on_enospc.clone();
+        let (path, file) = self.take_parts();
+        match path.persist_noclobber(new_path) {
+            Ok(_) => Ok(file),
+            Err(err) => {
+                let PathPersistError { error, path } = err;
+                Err(PersistError {
+                    file: NamedTempFile {
+                        path,
+                        file: Some(file),
+                        sync_on_drop,
+                        wipe_on_drop,
+                        on_enospc,
+                    },
+                    error,
+                })
+            }
+        }
+    }
+
+    /// Flush the temporary file and then persist it at the target path.
+    ///
+    /// This behaves exactly like [`persist`], except that any buffered writes
+    /// are flushed before the file is renamed. If `sync` is `true`, the file's
+    /// contents and metadata are also synchronized to disk with
+    /// [`File::sync_all`] before the rename.
+    ///
+    /// If flushing, syncing, or renaming fails, `self` is returned in the
+    /// resulting [`PersistError`] and the file is left at its temporary path.
+    ///
+    /// # Durability
+    ///
+    /// A flush only hands buffered data to the OS; after a crash or power
+    /// loss, the persisted file may be empty or incomplete. Passing
+    /// `sync = true` guarantees the _contents_ reach the disk before the file
+    /// becomes visible at `new_path`, but the rename itself (the containing
+    /// directory) is still not synchronized.
     ///
     /// # Security
     ///
@@ -728,15 +1988,342 @@ impl NamedTempFile {
     /// secure in the in all cases. Please read the security section on the top
     /// level documentation of this type for details.
     ///
-    /// # Errors
+    /// # Errors
+    ///
+    /// If the file cannot be flushed, synced, or moved to the new location,
+    /// `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// writeln!(file, "Brian was here. Briefly.")?;
+    ///
+    /// let persisted_file = file.persist_flushed("./saved_file.txt", true)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`persist`]: #method.persist
+    /// [`File::sync_all`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.sync_all
+    /// [`PersistError`]: struct.PersistError.html
+    pub fn persist_flushed<P: AsRef<Path>>(
+        mut self,
+        new_path: P,
+        sync: bool,
+    ) -> Result<File, PersistError> {
+        let mut result = self.flush();
+        if sync {
+            result = result.and_then(|_| self.as_file().sync_all().with_err_path(|| self.path()));
+        }
+        match result {
+            Ok(()) => self.persist(new_path),
+            Err(error) => Err(PersistError { error, file: self }),
+        }
+    }
+
+    /// Keep the temporary file from being deleted. This function will turn the
+    /// temporary file into a non-temporary file without moving it.
+    ///
+    ///
+    /// # Errors
+    ///
+    /// On some platforms (e.g., Windows), we need to mark the file as
+    /// non-temporary. This operation could fail.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// writeln!(file, "Brian was here. Briefly.")?;
+    ///
+    /// let (file, path) = file.keep()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`PathPersistError`]: struct.PathPersistError.html
+    pub fn keep(self) -> Result<(File, PathBuf), PersistError> {
+        let sync_on_drop = self.sync_on_drop;
+        let wipe_on_drop = self.wipe_on_drop;
+        let on_enospc = self.on_enospc.clone();
+        let (path, file) = self.take_parts();
+        match path.keep() {
+            Ok(path) => Ok((file, path)),
+            Err(PathPersistError { error, path }) => Err(PersistError {
+                file: NamedTempFile {
+                    path,
+                    file: Some(file),
+                    sync_on_drop,
+                    wipe_on_drop,
+                    on_enospc,
+                },
+                error,
+            }),
+        }
+    }
+
+    /// Securely reopen the temporary file.
+    ///
+    /// This function is useful when you need multiple independent handles to
+    /// the same file. It's perfectly fine to drop the original `NamedTempFile`
+    /// while holding on to `File`s returned by this function; the `File`s will
+    /// remain usable. However, they may not be nameable.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be reopened, `Err` is returned.
+    ///
+    /// # Security
+    ///
+    /// Unlike `File::open(my_temp_file.path())`, `NamedTempFile::reopen()`
+    /// guarantees that the re-opened file is the _same_ file, even in the
+    /// presence of pathological temporary file cleaners.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let file = NamedTempFile::new()?;
+    ///
+    /// let another_handle = file.reopen()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reopen(&self) -> io::Result<File> {
+        imp::reopen(self.as_file(), NamedTempFile::path(self))
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Open a second, read-only handle to the temporary file through its file
+    /// descriptor rather than its path.
+    ///
+    /// On Linux, this opens `/proc/self/fd/N`, which yields a fresh open file
+    /// with its own cursor, like [`reopen`], but without looking up the
+    /// file's path: it can't be fooled by the path being replaced, and it
+    /// keeps working after the file has been unlinked. It requires `/proc` to
+    /// be mounted.
+    ///
+    /// On other platforms, this falls back to `File::try_clone`, whose handle
+    /// *shares* the cursor (and write access) with this `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be opened (e.g., because `/proc` isn't available),
+    /// `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Read, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// write!(file, "hello")?;
+    ///
+    /// let mut reader = file.reopen_via_fd()?;
+    /// # if cfg!(target_os = "linux") {
+    /// let mut contents = String::new();
+    /// reader.read_to_string(&mut contents)?;
+    /// assert_eq!(contents, "hello");
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`reopen`]: #method.reopen
+    pub fn reopen_via_fd(&self) -> io::Result<File> {
+        #[cfg(target_os = "linux")]
+        let reopened = {
+            use std::os::unix::io::AsRawFd;
+            File::open(format!("/proc/self/fd/{}", self.as_file().as_raw_fd()))
+        };
+        #[cfg(not(target_os = "linux"))]
+        let reopened = self.as_file().try_clone();
+        reopened.with_err_path(|| self.path())
+    }
+
+    /// Read the entire contents of the temporary file into a `Vec<u8>`.
+    ///
+    /// The file is read through a separate handle (see [`reopen`]), so the
+    /// cursor of this `NamedTempFile` is left where it is and writing can
+    /// continue afterwards.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be reopened or read, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// file.write_all(b"abc")?;
+    /// assert_eq!(file.read_to_vec()?, b"abc");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`reopen`]: #method.reopen
+    pub fn read_to_vec(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.reopen()?
+            .read_to_end(&mut buf)
+            .with_err_path(|| NamedTempFile::path(self))?;
+        Ok(buf)
+    }
+
+    /// Read the entire contents of the temporary file into a `String`.
+    ///
+    /// Like [`read_to_vec`], this doesn't move the file's cursor. (It isn't
+    /// called `read_to_string` so as not to shadow [`Read::read_to_string`].)
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be reopened or read, or its contents aren't valid
+    /// UTF-8, `Err` is returned. In the latter case, the error kind is
+    /// `InvalidData`.
+    ///
+    /// [`read_to_vec`]: #method.read_to_vec
+    /// [`Read::read_to_string`]: https://doc.rust-lang.org/std/io/trait.Read.html#method.read_to_string
+    pub fn read_text(&self) -> io::Result<String> {
+        let mut buf = String::new();
+        self.reopen()?
+            .read_to_string(&mut buf)
+            .with_err_path(|| NamedTempFile::path(self))?;
+        Ok(buf)
+    }
+
+    /// Read the temporary file as a list of lines.
+    ///
+    /// Lines are split as by [`BufRead::lines`]: the trailing `\n` or `\r\n`
+    /// is removed and a final line without one is still returned. Like
+    /// [`read_to_vec`], this doesn't move the file's cursor.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`read_text`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// writeln!(file, "first")?;
+    /// writeln!(file, "second")?;
+    /// assert_eq!(file.read_lines()?, ["first", "second"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`BufRead::lines`]: https://doc.rust-lang.org/std/io/trait.BufRead.html#method.lines
+    /// [`read_to_vec`]: #method.read_to_vec
+    /// [`read_text`]: #method.read_text
+    pub fn read_lines(&self) -> io::Result<Vec<String>> {
+        io::BufReader::new(self.reopen()?)
+            .lines()
+            .collect::<io::Result<_>>()
+            .with_err_path(|| NamedTempFile::path(self))
+    }
+
+    /// Get a writer that recovers from the disk filling up.
+    ///
+    /// Writes through the returned writer behave like writes to the file,
+    /// except that when one fails because the disk is full, the hook set with
+    /// [`Builder::on_enospc`] is called and, if it succeeds, the write is
+    /// retried once. This can't be done for writes to the `File` itself, so
+    /// use this writer wherever running out of space is expected.
     ///
-    /// If the file cannot be moved to the new location or a file already exists there,
-    /// `Err` is returned.
+    /// If the file wasn't created with an `on_enospc` hook, the writer simply
+    /// forwards to the file.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use std::io::{self, Write};
+    /// use tempfile::Builder;
+    ///
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = Builder::new()
+    ///     .on_enospc(|| {
+    ///         // Free up some space, e.g. by evicting cached temporary files.
+    ///         Ok(())
+    ///     })
+    ///     .tempfile()?;
+    /// file.enospc_retrying_writer().write_all(b"lots of data")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::on_enospc`]: struct.Builder.html#method.on_enospc
+    pub fn enospc_retrying_writer(&mut self) -> EnospcRetryingWriter<'_> {
+        EnospcRetryingWriter::new(self.as_file(), self.on_enospc.as_ref())
+    }
+
+    /// Duplicate the temporary file's descriptor into one that child
+    /// processes inherit, and return it.
+    ///
+    /// This is meant for low-level process spawning, e.g. with
+    /// `posix_spawn_file_actions_adddup2`, which needs a valid descriptor
+    /// that doesn't have `FD_CLOEXEC` set. The new descriptor refers to the
+    /// same open file (sharing its cursor), and the `NamedTempFile` itself is
+    /// unaffected.
+    ///
+    /// The caller owns the returned descriptor and must close it (e.g. with
+    /// `libc::close`, or by wrapping it in a `File` with
+    /// `FromRawFd::from_raw_fd`); it's leaked otherwise. Until it's closed,
+    /// every process spawned in the meantime inherits it, not just the
+    /// intended one.
+    ///
+    /// # Errors
+    ///
+    /// If the descriptor can't be duplicated (e.g., because the process ran
+    /// out of descriptors), `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use std::fs::File;
+    /// use std::os::unix::io::FromRawFd;
     /// use tempfile::NamedTempFile;
     ///
     /// # fn main() {
@@ -746,34 +2333,125 @@ impl NamedTempFile {
     /// # }
     /// # fn run() -> Result<(), io::Error> {
     /// let file = NamedTempFile::new()?;
+    /// let fd = file.as_inheritable_fd()?;
+    /// // ... hand `fd` to `posix_spawn_file_actions_adddup2` ...
+    /// drop(unsafe { File::from_raw_fd(fd) });
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn as_inheritable_fd(&self) -> io::Result<std::os::unix::io::RawFd> {
+        dup_inheritable(self.as_file()).with_err_path(|| self.path())
+    }
+
+    /// Take an exclusive lock on the temporary file, waiting until no other
+    /// handle holds a lock on it.
     ///
-    /// let mut persisted_file = file.persist_noclobber("./saved_file.txt")?;
-    /// writeln!(persisted_file, "Brian was here. Briefly.")?;
+    /// The lock is released when the returned guard is dropped. Use this to
+    /// coordinate a producer and consumers of the same file: while one handle
+    /// holds an exclusive lock, no other handle can take an exclusive or
+    /// shared lock (see [`lock_shared`]).
+    ///
+    /// The lock is taken with `flock` on Unix and `LockFileEx` on Windows. On
+    /// Unix, it's purely advisory: it only affects others that lock the file
+    /// too, and reads and writes are never blocked. On Windows, it's
+    /// mandatory: while it's held, other handles can't read from or write to
+    /// the file either (but the handle holding the lock can).
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be locked (e.g., because the platform doesn't
+    /// support locking), `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let file = NamedTempFile::new()?;
+    /// let lock = file.lock_exclusive()?;
+    /// writeln!(file.as_file(), "produced while locked")?;
+    /// drop(lock);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn persist_noclobber<P: AsRef<Path>>(self, new_path: P) -> Result<File, PersistError> {
-        let NamedTempFile { path, file } = self;
-        match path.persist_noclobber(new_path) {
-            Ok(_) => Ok(file),
-            Err(err) => {
-                let PathPersistError { error, path } = err;
-                Err(PersistError {
-                    file: NamedTempFile { path, file },
-                    error,
-                })
-            }
-        }
+    ///
+    /// [`lock_shared`]: #method.lock_shared
+    pub fn lock_exclusive(&self) -> io::Result<FileLock<'_>> {
+        FileLock::acquire(self.as_file(), self.path(), true, true)
     }
 
-    /// Keep the temporary file from being deleted. This function will turn the
-    /// temporary file into a non-temporary file without moving it.
+    /// Take a shared lock on the temporary file, waiting until no other handle
+    /// holds an exclusive lock on it.
     ///
+    /// Any number of handles can hold a shared lock at the same time. See
+    /// [`lock_exclusive`] for the semantics on each platform.
     ///
     /// # Errors
     ///
-    /// On some platforms (e.g., Windows), we need to mark the file as
-    /// non-temporary. This operation could fail.
+    /// If the file can't be locked, `Err` is returned.
+    ///
+    /// [`lock_exclusive`]: #method.lock_exclusive
+    pub fn lock_shared(&self) -> io::Result<FileLock<'_>> {
+        FileLock::acquire(self.as_file(), self.path(), false, true)
+    }
+
+    /// Like [`lock_exclusive`], but fails with an [`io::ErrorKind::WouldBlock`]
+    /// error instead of waiting if another handle holds a lock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let file = NamedTempFile::new()?;
+    /// match file.try_lock_exclusive() {
+    ///     Ok(_lock) => println!("got the lock"),
+    ///     Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => println!("busy"),
+    ///     Err(e) => return Err(e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`lock_exclusive`]: #method.lock_exclusive
+    /// [`io::ErrorKind::WouldBlock`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.WouldBlock
+    pub fn try_lock_exclusive(&self) -> io::Result<FileLock<'_>> {
+        FileLock::acquire(self.as_file(), self.path(), true, false)
+    }
+
+    /// Like [`lock_shared`], but fails with an [`io::ErrorKind::WouldBlock`]
+    /// error instead of waiting if another handle holds an exclusive lock.
+    ///
+    /// [`lock_shared`]: #method.lock_shared
+    /// [`io::ErrorKind::WouldBlock`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.WouldBlock
+    pub fn try_lock_shared(&self) -> io::Result<FileLock<'_>> {
+        FileLock::acquire(self.as_file(), self.path(), false, false)
+    }
+
+    /// Truncate the temporary file to zero length and seek back to the start.
+    ///
+    /// This is useful when the same temporary file is reused many times (e.g.,
+    /// in a loop), as it's much cheaper than creating a new one each time.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be truncated or the cursor cannot be moved, `Err`
+    /// is returned.
     ///
     /// # Examples
     ///
@@ -788,46 +2466,92 @@ impl NamedTempFile {
     /// # }
     /// # fn run() -> Result<(), io::Error> {
     /// let mut file = NamedTempFile::new()?;
-    /// writeln!(file, "Brian was here. Briefly.")?;
     ///
-    /// let (file, path) = file.keep()?;
+    /// for i in 0..10 {
+    ///     file.reset()?;
+    ///     writeln!(file, "iteration {}", i)?;
+    /// }
     /// # Ok(())
     /// # }
     /// ```
-    ///
-    /// [`PathPersistError`]: struct.PathPersistError.html
-    pub fn keep(self) -> Result<(File, PathBuf), PersistError> {
-        let (file, path) = (self.file, self.path);
-        match path.keep() {
-            Ok(path) => Ok((file, path)),
-            Err(PathPersistError { error, path }) => Err(PersistError {
-                file: NamedTempFile { path, file },
-                error,
-            }),
-        }
+    pub fn reset(&mut self) -> io::Result<()> {
+        self.as_file().set_len(0).with_err_path(|| self.path())?;
+        self.as_file_mut()
+            .seek(SeekFrom::Start(0))
+            .with_err_path(|| self.path())?;
+        Ok(())
     }
 
-    /// Securely reopen the temporary file.
+    /// Read the file from the start, and put the cursor back afterwards.
     ///
-    /// This function is useful when you need multiple independent handles to
-    /// the same file. It's perfectly fine to drop the original `NamedTempFile`
-    /// while holding on to `File`s returned by this function; the `File`s will
-    /// remain usable. However, they may not be nameable.
+    /// The returned [`RewindReader`] seeks to the start of the file, reads
+    /// from there, and seeks back to the current position when it's dropped.
+    /// This avoids the common mistake of forgetting to restore the cursor
+    /// before writing more data. The reader holds a mutable borrow of `self`
+    /// for as long as it's alive, so the file can't be used in any other way
+    /// in the meantime.
     ///
     /// # Errors
     ///
-    /// If the file cannot be reopened, `Err` is returned.
+    /// If the current position can't be queried or the file can't be
+    /// rewound, `Err` is returned.
     ///
-    /// # Security
+    /// # Examples
     ///
-    /// Unlike `File::open(my_temp_file.path())`, `NamedTempFile::reopen()`
-    /// guarantees that the re-opened file is the _same_ file, even in the
-    /// presence of pathological temporary file cleaners.
+    /// ```
+    /// # use std::io::{self, Read, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = NamedTempFile::new()?;
+    /// write!(file, "header")?;
+    ///
+    /// let mut contents = String::new();
+    /// file.rewind_reader()?.read_to_string(&mut contents)?;
+    /// assert_eq!(contents, "header");
+    ///
+    /// // Writing continues at the end.
+    /// write!(file, ", body")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`RewindReader`]: struct.RewindReader.html
+    pub fn rewind_reader(&mut self) -> io::Result<RewindReader<'_>> {
+        RewindReader::new(self)
+    }
+
+    /// Wrap the temporary file in a [`BufWriter`].
+    ///
+    /// Remember to call `flush` (or `into_inner`) on the returned writer to
+    /// detect write errors; dropping a `BufWriter` silently ignores them.
+    ///
+    /// [`BufWriter`]: https://doc.rust-lang.org/std/io/struct.BufWriter.html
+    pub fn buf_writer(self) -> BufWriter<NamedTempFile> {
+        BufWriter::new(self)
+    }
+
+    /// Fill the temporary file through a [`BufWriter`], then flush it and
+    /// seek back to the start.
+    ///
+    /// The closure is handed a buffered writer for the file. Once it returns,
+    /// the buffer is flushed (surfacing any write errors) and the file is
+    /// rewound so it's ready to be read.
+    ///
+    /// # Errors
+    ///
+    /// If the closure returns an error, or the file cannot be flushed or
+    /// rewound, `Err` is returned and the temporary file is deleted.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// # use std::io;
+    /// # use std::io::{self, Read, Write};
     /// use tempfile::NamedTempFile;
     ///
     /// # fn main() {
@@ -836,48 +2560,265 @@ impl NamedTempFile {
     /// #     }
     /// # }
     /// # fn run() -> Result<(), io::Error> {
-    /// let file = NamedTempFile::new()?;
-    ///
-    /// let another_handle = file.reopen()?;
+    /// let mut file = NamedTempFile::new()?.with_buffered_contents(|w| {
+    ///     for i in 0..100 {
+    ///         writeln!(w, "line {}", i)?;
+    ///     }
+    ///     Ok(())
+    /// })?;
+    ///
+    /// let mut contents = String::new();
+    /// file.read_to_string(&mut contents)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn reopen(&self) -> io::Result<File> {
-        imp::reopen(self.as_file(), NamedTempFile::path(self))
-            .with_err_path(|| NamedTempFile::path(self))
+    ///
+    /// [`BufWriter`]: https://doc.rust-lang.org/std/io/struct.BufWriter.html
+    pub fn with_buffered_contents<F>(mut self, f: F) -> io::Result<NamedTempFile>
+    where
+        F: FnOnce(&mut BufWriter<&mut NamedTempFile>) -> io::Result<()>,
+    {
+        {
+            let mut writer = BufWriter::new(&mut self);
+            f(&mut writer)?;
+            writer.flush()?;
+        }
+        self.seek(SeekFrom::Start(0))?;
+        Ok(self)
     }
 
     /// Get a reference to the underlying file.
     pub fn as_file(&self) -> &File {
-        &self.file
+        self.file.as_ref().expect("file taken")
     }
 
     /// Get a mutable reference to the underlying file.
     pub fn as_file_mut(&mut self) -> &mut File {
-        &mut self.file
+        self.file.as_mut().expect("file taken")
     }
 
     /// Convert the temporary file into a `std::fs::File`.
     ///
-    /// The inner file will be deleted.
+    /// The inner file will be deleted. The drop-time settings,
+    /// [`Builder::sync_on_drop`] and [`Builder::wipe_on_drop`], are
+    /// discarded: the contents stay readable through the returned `File`.
+    ///
+    /// [`Builder::sync_on_drop`]: struct.Builder.html#method.sync_on_drop
+    /// [`Builder::wipe_on_drop`]: struct.Builder.html#method.wipe_on_drop
     pub fn into_file(self) -> File {
-        self.file
+        self.take_parts().1
+    }
+
+    /// Convert the temporary file into an anonymous `std::fs::File`, dropping
+    /// its name once it's no longer needed.
+    ///
+    /// Unlike [`into_file`], which deletes the file by path and relies on the
+    /// OS to keep the open file alive, this makes the returned `File` itself
+    /// responsible for the storage: it's freed once the last handle to the
+    /// file is closed, just like a file created with [`tempfile()`]. The
+    /// cleanup mechanism differs by platform:
+    ///
+    /// * On UNIX, the path is unlinked immediately and the open file remains
+    ///   usable without a name.
+    /// * On Windows, open files can't be unlinked, so the file keeps its path
+    ///   until the returned handle is closed. The file is reopened with
+    ///   `FILE_FLAG_DELETE_ON_CLOSE` so the OS deletes it at that point.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be unlinked or reopened, `Err` is returned and the
+    /// temporary file is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut named = NamedTempFile::new()?;
+    /// writeln!(named, "Brian was here. Briefly.")?;
+    ///
+    /// let mut file = named.into_anonymous()?;
+    /// writeln!(file, "Still here.")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`into_file`]: #method.into_file
+    /// [`tempfile()`]: fn.tempfile.html
+    #[doc(alias = "forget_name")]
+    pub fn into_anonymous(self) -> io::Result<File> {
+        let (mut path, file) = self.take_parts();
+        let file = imp::into_anonymous(file, &path).with_err_path(|| path.to_path_buf())?;
+        // The OS is now responsible for the file; don't try deleting it by path.
+        path.defuse(true);
+        Ok(file)
     }
 
     /// Closes the file, leaving only the temporary file path.
     ///
     /// This is useful when another process must be able to open the temporary
     /// file.
+    ///
+    /// The drop-time settings, [`Builder::sync_on_drop`] and
+    /// [`Builder::wipe_on_drop`], are discarded: dropping the `TempPath`
+    /// just deletes the file.
+    ///
+    /// [`Builder::sync_on_drop`]: struct.Builder.html#method.sync_on_drop
+    /// [`Builder::wipe_on_drop`]: struct.Builder.html#method.wipe_on_drop
     pub fn into_temp_path(self) -> TempPath {
-        self.path
+        self.take_parts().0
     }
 
     /// Converts the named temporary file into its constituent parts.
     ///
     /// Note: When the path is dropped, the file is deleted but the file handle
-    /// is still usable.
+    /// is still usable. The drop-time settings, [`Builder::sync_on_drop`] and
+    /// [`Builder::wipe_on_drop`], are discarded, as are
+    /// [`Builder::on_enospc`] hooks.
+    ///
+    /// [`Builder::sync_on_drop`]: struct.Builder.html#method.sync_on_drop
+    /// [`Builder::wipe_on_drop`]: struct.Builder.html#method.wipe_on_drop
+    /// [`Builder::on_enospc`]: struct.Builder.html#method.on_enospc
     pub fn into_parts(self) -> (File, TempPath) {
-        (self.file, self.path)
+        let (path, file) = self.take_parts();
+        (file, path)
+    }
+
+    /// Split the named temporary file into a writing half and a reading half,
+    /// each with its own cursor, e.g. for a producer and a consumer in the
+    /// same process.
+    ///
+    /// The [`TempWriter`] keeps this file's own handle, cursor included. The
+    /// [`TempReader`] is a second handle, opened like [`reopen`] and starting
+    /// at offset 0, so reading never moves the writer's position or vice
+    /// versa. (A `File::try_clone` wouldn't do: cloned handles share one
+    /// cursor.) Both refer to the same file, so whatever the writer has
+    /// written can be read right away; reading past what has been written so
+    /// far returns end-of-file, not an error or a wait.
+    ///
+    /// The halves share ownership of the path: the file is deleted when the
+    /// last of the two is dropped, whichever that is. There's no way to
+    /// persist the file after splitting it. The [`Builder::sync_on_drop`] and
+    /// [`Builder::wipe_on_drop`] settings don't carry over to the halves.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be reopened, `Err` is returned (and the file is
+    /// deleted).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Read, Write};
+    /// use tempfile::NamedTempFile;
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let (mut writer, mut reader) = NamedTempFile::new()?.split()?;
+    /// writer.write_all(b"first ")?;
+    ///
+    /// let mut buf = String::new();
+    /// reader.read_to_string(&mut buf)?;
+    /// assert_eq!(buf, "first ");
+    ///
+    /// writer.write_all(b"second")?;
+    /// reader.read_to_string(&mut buf)?;
+    /// assert_eq!(buf, "first second");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TempWriter`]: struct.TempWriter.html
+    /// [`TempReader`]: struct.TempReader.html
+    /// [`reopen`]: #method.reopen
+    /// [`Builder::sync_on_drop`]: struct.Builder.html#method.sync_on_drop
+    /// [`Builder::wipe_on_drop`]: struct.Builder.html#method.wipe_on_drop
+    pub fn split(self) -> io::Result<(TempWriter, TempReader)> {
+        let reader = self.reopen()?;
+        let (path, writer) = self.take_parts();
+        Ok(split::split(writer, reader, path))
+    }
+
+    pub(crate) fn set_sync_on_drop(mut self, sync_on_drop: bool) -> NamedTempFile {
+        self.sync_on_drop = sync_on_drop;
+        self
+    }
+
+    pub(crate) fn set_wipe_on_drop(mut self, wipe_on_drop: bool) -> NamedTempFile {
+        self.wipe_on_drop = wipe_on_drop;
+        self
+    }
+
+    pub(crate) fn set_on_enospc(mut self, on_enospc: Option<EnospcHook>) -> NamedTempFile {
+        self.on_enospc = on_enospc;
+        self
+    }
+
+    pub(crate) fn set_parent_dirs(mut self, parent_dirs: Vec<PathBuf>) -> NamedTempFile {
+        self.path.set_parent_dirs(parent_dirs);
+        self
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn set_dir_handle(mut self, dir_handle: File) -> NamedTempFile {
+        self.path.set_dir_handle(dir_handle);
+        self
+    }
+
+    pub(crate) fn set_rand_token(mut self, rand_token: Range<usize>) -> NamedTempFile {
+        self.path.rand_token = rand_token;
+        self
+    }
+
+    /// Overwrite the file's contents with zeros, for `Builder::wipe_on_drop`.
+    fn wipe(&self) -> io::Result<()> {
+        // A fresh handle, since writes through ours may be in append mode.
+        let mut file = imp::reopen(self.as_file(), self.path()).with_err_path(|| self.path())?;
+        let len = file.metadata().with_err_path(|| self.path())?.len();
+        io::copy(&mut io::repeat(0).take(len), &mut file).with_err_path(|| self.path())?;
+        file.sync_data().with_err_path(|| self.path())
+    }
+
+    /// Leave deleting the file to someone else.
+    pub(crate) fn disarm(mut self) -> NamedTempFile {
+        self.path.disarmed = true;
+        self.path.unregister_signal_cleanup();
+        self
+    }
+
+    /// Split the named temporary file into its parts, skipping the drop-time
+    /// sync and wipe.
+    fn take_parts(mut self) -> (TempPath, File) {
+        let file = self.file.take().expect("file taken");
+        (mem::replace(&mut self.path, TempPath::taken()), file)
+    }
+}
+
+impl Drop for NamedTempFile {
+    fn drop(&mut self) {
+        // Errors can't be reported from here, and panicking could abort the
+        // process if we're already unwinding.
+        let file = match self.file {
+            Some(ref file) => file,
+            None => return,
+        };
+        if self.wipe_on_drop && !self.path.disarmed {
+            let _ = self.wipe();
+        }
+        if self.sync_on_drop {
+            let _ = file.sync_all();
+        }
     }
 }
 
@@ -887,7 +2828,7 @@ impl Read for NamedTempFile {
     }
 }
 
-impl<'a> Read for &'a NamedTempFile {
+impl Read for &NamedTempFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.as_file().read(buf).with_err_path(|| self.path())
     }
@@ -903,7 +2844,7 @@ impl Write for NamedTempFile {
     }
 }
 
-impl<'a> Write for &'a NamedTempFile {
+impl Write for &NamedTempFile {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.as_file().write(buf).with_err_path(|| self.path())
     }
@@ -919,7 +2860,7 @@ impl Seek for NamedTempFile {
     }
 }
 
-impl<'a> Seek for &'a NamedTempFile {
+impl Seek for &NamedTempFile {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         self.as_file().seek(pos).with_err_path(|| self.path())
     }
@@ -942,18 +2883,123 @@ impl std::os::windows::io::AsRawHandle for NamedTempFile {
 }
 
 pub(crate) fn create_named(
-    mut path: PathBuf,
+    path: PathBuf,
+    open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
+) -> io::Result<NamedTempFile> {
+    create_named_with(path, |path| {
+        imp::create_named(path, open_options, permissions)
+    })
+}
+
+/// Like `create_named`, but confirms exclusive creation through the link count
+/// on Unix, for NFS (see `Builder::nfs_safe`).
+pub(crate) fn create_named_nfs_safe(
+    path: PathBuf,
     open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
 ) -> io::Result<NamedTempFile> {
+    create_named_with(path, |path| {
+        imp::create_named_via_link(path, open_options, permissions)
+    })
+}
+
+fn create_named_with<F>(mut path: PathBuf, create: F) -> io::Result<NamedTempFile>
+where
+    F: FnOnce(&Path) -> io::Result<File>,
+{
     // Make the path absolute. Otherwise, changing directories could cause us to
     // delete the wrong file.
     if !path.is_absolute() {
         path = env::current_dir()?.join(path)
     }
-    imp::create_named(&path, open_options)
-        .with_err_path(|| path.clone())
-        .map(|file| NamedTempFile {
-            path: TempPath { path },
-            file,
-        })
+    match create(&path) {
+        Ok(file) => Ok(NamedTempFile {
+            path: TempPath {
+                #[cfg(unix)]
+                signal_slot: crate::signals::register(&path),
+                path,
+                parent_dirs: Box::default(),
+                disarmed: false,
+                rand_token: 0..0,
+                #[cfg(unix)]
+                dir_handle: None,
+            },
+            file: Some(file),
+            sync_on_drop: false,
+            wipe_on_drop: false,
+            on_enospc: None,
+        }),
+        Err(e) => Err(e).with_err_path_unless_exists(|| path),
+    }
+}
+
+/// Fill the empty file `dest` with the contents of `source`, as a reflink if
+/// possible, and rewind it.
+pub(crate) fn fill_from(mut source: &File, mut dest: &File) -> io::Result<()> {
+    if !reflink(source, dest)? {
+        io::copy(&mut source, &mut dest)?;
+        dest.seek(SeekFrom::Start(0))?;
+    }
+    Ok(())
+}
+
+/// Open the temporary file at `path` in append mode, creating it if it doesn't
+/// exist yet. Only the handle that actually created the file deletes it.
+pub(crate) fn create_shared_append(
+    path: PathBuf,
+    open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
+) -> io::Result<NamedTempFile> {
+    open_options.append(true);
+    create_shared(path, open_options, permissions)
+}
+
+/// Open the temporary file at `path`, creating it if it doesn't exist yet.
+/// Only the handle that actually created the file deletes it.
+pub(crate) fn create_shared(
+    mut path: PathBuf,
+    open_options: &mut OpenOptions,
+    permissions: Option<&Permissions>,
+) -> io::Result<NamedTempFile> {
+    if !path.is_absolute() {
+        path = env::current_dir()?.join(path)
+    }
+    let mut join_options = open_options.clone();
+    join_options.read(true).write(true);
+    for _ in 0..crate::NUM_RETRIES {
+        match create_named(path.clone(), open_options, permissions) {
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            res => return res,
+        }
+        // Someone else created it; join them. If the creator deleted it in the
+        // meantime, try creating it again.
+        match join_options.open(&path) {
+            Ok(file) => {
+                return Ok(NamedTempFile {
+                    path: TempPath {
+                        path,
+                        parent_dirs: Box::default(),
+                        disarmed: true,
+                        rand_token: 0..0,
+                        #[cfg(unix)]
+                        dir_handle: None,
+                        #[cfg(unix)]
+                        signal_slot: None,
+                    },
+                    file: Some(file),
+                    sync_on_drop: false,
+                    wipe_on_drop: false,
+                    on_enospc: None,
+                })
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_err_path(|| path),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "shared file kept disappearing while opening it",
+    ))
+    .with_err_path(|| path)
 }
@@ -0,0 +1,137 @@
+//! Detection of the filesystem backing the temporary directory.
+
+use std::io;
+
+use crate::error::IoResultExt;
+use crate::file;
+
+/// The kind of filesystem a path lives on, as reported by [`temp_fs_kind`].
+///
+/// Filesystems that aren't listed here, or that can't be identified on the
+/// current platform, are reported as `Unknown`.
+///
+/// [`temp_fs_kind`]: fn.temp_fs_kind.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FsKind {
+    /// An in-memory filesystem (`tmpfs` or `ramfs`).
+    Tmpfs,
+    /// ext2, ext3 or ext4 (these share one identifier on Linux).
+    Ext,
+    /// Btrfs.
+    Btrfs,
+    /// XFS.
+    Xfs,
+    /// ZFS.
+    Zfs,
+    /// An overlay or union filesystem, as commonly used by containers.
+    Overlay,
+    /// NFS.
+    Nfs,
+    /// SMB/CIFS network shares.
+    Smb,
+    /// Apple's APFS.
+    Apfs,
+    /// Apple's HFS+.
+    Hfs,
+    /// NTFS.
+    Ntfs,
+    /// Microsoft's ReFS.
+    Refs,
+    /// FAT12/16/32 or exFAT.
+    Fat,
+    /// A filesystem that couldn't be identified.
+    Unknown,
+}
+
+impl FsKind {
+    /// Returns `true` for network filesystems (NFS and SMB), where renames
+    /// and locking tend to be less reliable.
+    pub fn is_network(self) -> bool {
+        matches!(self, FsKind::Nfs | FsKind::Smb)
+    }
+
+    /// Returns `true` for filesystems that ignore case in file names by
+    /// default (APFS, HFS+, NTFS, ReFS, FAT and SMB shares).
+    ///
+    /// APFS and HFS+ volumes can also be formatted case-sensitively, and NTFS
+    /// supports case-sensitive directories, so this errs on the side of
+    /// reporting case-insensitivity.
+    pub fn is_case_insensitive(self) -> bool {
+        matches!(
+            self,
+            FsKind::Apfs | FsKind::Hfs | FsKind::Ntfs | FsKind::Refs | FsKind::Fat | FsKind::Smb
+        )
+    }
+
+    /// Map a filesystem type name, as reported by BSD `statfs` or Windows'
+    /// `GetVolumeInformation`, to a kind.
+    #[cfg(any(windows, target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    pub(crate) fn from_name(name: &str) -> FsKind {
+        match &*name.to_ascii_lowercase() {
+            "tmpfs" | "ramfs" => FsKind::Tmpfs,
+            "ext2" | "ext3" | "ext4" => FsKind::Ext,
+            "btrfs" => FsKind::Btrfs,
+            "xfs" => FsKind::Xfs,
+            "zfs" => FsKind::Zfs,
+            "overlay" | "unionfs" => FsKind::Overlay,
+            "nfs" => FsKind::Nfs,
+            "smbfs" | "cifs" => FsKind::Smb,
+            "apfs" => FsKind::Apfs,
+            "hfs" => FsKind::Hfs,
+            "ntfs" => FsKind::Ntfs,
+            "refs" => FsKind::Refs,
+            "msdos" | "msdosfs" | "fat" | "fat32" | "exfat" => FsKind::Fat,
+            _ => FsKind::Unknown,
+        }
+    }
+}
+
+/// Identify the filesystem backing `std::env::temp_dir()`.
+///
+/// This is a diagnostic helper: filesystems differ in whether they support
+/// `O_TMPFILE`, how atomic renames are and how expensive `fsync` is, so
+/// callers may want to adjust their strategy accordingly.
+///
+/// # Platform support
+///
+/// * On Linux and Android, the filesystem is identified by the magic number
+///   reported by `statfs(2)`.
+/// * On macOS, iOS and FreeBSD, it is identified by the type name reported
+///   by `statfs(2)`.
+/// * On Windows, it is identified by the name reported by
+///   `GetVolumeInformationW` for the volume containing the directory.
+/// * On other platforms, [`FsKind::Unknown`] is always returned.
+///
+/// Filesystems that can't be identified are reported as [`FsKind::Unknown`]
+/// rather than as an error.
+///
+/// # Errors
+///
+/// If the temporary directory can't be queried (for example, because it
+/// doesn't exist), `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io;
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// use tempfile::FsKind;
+///
+/// if tempfile::temp_fs_kind()? == FsKind::Tmpfs {
+///     // No point in calling `fsync` on an in-memory filesystem.
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`FsKind::Unknown`]: enum.FsKind.html#variant.Unknown
+pub fn temp_fs_kind() -> io::Result<FsKind> {
+    let dir = crate::default_temp_dir();
+    file::fs_kind(dir).with_err_path(|| dir)
+}
@@ -0,0 +1,127 @@
+//! A named temporary file that hashes its contents as they are written.
+
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::NamedTempFile;
+
+/// A [`NamedTempFile`] that computes the SHA-256 digest of everything written
+/// to it, so that it can be persisted under a content-addressed name without
+/// reading it back.
+///
+/// Only bytes written through this wrapper are hashed; writes made through
+/// other handles to the same file (or after seeking) are not accounted for.
+///
+/// Requires the `sha2` feature.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{self, Write};
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// use tempfile::HashingNamedTempFile;
+///
+/// let store = tempfile::tempdir()?;
+/// let mut file = HashingNamedTempFile::new_in(&store)?;
+/// file.write_all(b"Hello, world!")?;
+/// let (path, digest) = file.finalize_and_persist(&store)?;
+/// assert_eq!(digest[0], 0x31);
+/// assert!(path.starts_with(store.path()));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+pub struct HashingNamedTempFile {
+    file: NamedTempFile,
+    hasher: Sha256,
+}
+
+impl HashingNamedTempFile {
+    /// Create a new hashing temporary file in `std::env::temp_dir()`.
+    ///
+    /// See [`NamedTempFile::new`](struct.NamedTempFile.html#method.new).
+    pub fn new() -> io::Result<HashingNamedTempFile> {
+        NamedTempFile::new().map(HashingNamedTempFile::from)
+    }
+
+    /// Create a new hashing temporary file in the specified directory.
+    ///
+    /// See [`NamedTempFile::new_in`](struct.NamedTempFile.html#method.new_in).
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<HashingNamedTempFile> {
+        NamedTempFile::new_in(dir).map(HashingNamedTempFile::from)
+    }
+
+    /// Get the temporary file's current path.
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Get a reference to the underlying named temporary file.
+    ///
+    /// Writing through it bypasses the hash.
+    pub fn as_named_temp_file(&self) -> &NamedTempFile {
+        &self.file
+    }
+
+    /// Finish hashing and persist the file into `dir`, named after the
+    /// lowercase hex encoding of its SHA-256 digest.
+    ///
+    /// The file is flushed first. If a file with that name already exists, it
+    /// is replaced; since it is named after its contents, it should be
+    /// identical.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be flushed or persisted, `Err` is returned and the
+    /// temporary file is deleted.
+    pub fn finalize_and_persist<P: AsRef<Path>>(
+        mut self,
+        dir: P,
+    ) -> io::Result<(PathBuf, [u8; 32])> {
+        self.file.flush()?;
+        let digest: [u8; 32] = self.hasher.finalize().into();
+        let mut name = String::with_capacity(digest.len() * 2);
+        for byte in &digest {
+            let _ = write!(name, "{:02x}", byte);
+        }
+        let path = dir.as_ref().join(name);
+        self.file.persist(&path)?;
+        Ok((path, digest))
+    }
+}
+
+impl fmt::Debug for HashingNamedTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HashingNamedTempFile({:?})", self.path())
+    }
+}
+
+impl From<NamedTempFile> for HashingNamedTempFile {
+    /// Start hashing writes to `file`. Existing contents are not hashed.
+    fn from(file: NamedTempFile) -> HashingNamedTempFile {
+        HashingNamedTempFile {
+            file,
+            hasher: Sha256::new(),
+        }
+    }
+}
+
+impl Write for HashingNamedTempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -0,0 +1,96 @@
+//! A named temporary file inside its own temporary directory.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::{NamedTempFile, TempDir};
+
+/// A named temporary file that lives alone in a private temporary directory.
+///
+/// Created by [`Builder::isolated`]. Listing the parent directory only reveals
+/// a randomly named directory, and on Unix that directory is only accessible
+/// to the current user (mode `0700`). When dropped, both the file and the
+/// directory (along with anything else placed in it) are deleted.
+///
+/// [`Builder::isolated`]: struct.Builder.html#method.isolated
+pub struct IsolatedTempFile {
+    // Declared first so that the file is closed before the directory is removed.
+    file: NamedTempFile,
+    dir: TempDir,
+}
+
+impl IsolatedTempFile {
+    pub(crate) fn new(file: NamedTempFile, dir: TempDir) -> IsolatedTempFile {
+        IsolatedTempFile { file, dir }
+    }
+
+    /// Get the temporary file's path.
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Get the path of the directory containing the temporary file.
+    pub fn dir_path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &File {
+        self.file.as_file()
+    }
+
+    /// Get a mutable reference to the underlying file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.file.as_file_mut()
+    }
+
+    /// Close and remove the temporary file and its directory.
+    ///
+    /// Use this if you want to detect errors in deleting them.
+    ///
+    /// # Errors
+    ///
+    /// If the file or the directory cannot be deleted, `Err` is returned.
+    pub fn close(self) -> io::Result<()> {
+        let IsolatedTempFile { file, dir } = self;
+        let result = file.close();
+        let dir_result = dir.close();
+        result.and(dir_result)
+    }
+}
+
+impl fmt::Debug for IsolatedTempFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "IsolatedTempFile({:?})", self.path())
+    }
+}
+
+impl AsRef<Path> for IsolatedTempFile {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl Read for IsolatedTempFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Write for IsolatedTempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for IsolatedTempFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
@@ -0,0 +1,65 @@
+//! Temporary files whose name is derived from a key.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs::OpenOptions;
+use std::hash::Hasher;
+use std::io;
+
+use crate::file::{self, NamedTempFile};
+
+/// Open the temporary file for `key`, creating it if it doesn't exist yet.
+///
+/// This is meant for memoization-style caches: within a run of the program,
+/// the same key always maps to the same path in [`std::env::temp_dir()`],
+/// named `.tmp` followed by a hash of the key. If the file already exists
+/// (because an earlier or concurrent caller created it), it's opened instead
+/// of failing, so all callers using the same key share one file. Different
+/// keys can, very rarely, hash to the same name.
+///
+/// The name is removed when the `NamedTempFile` that created the file is
+/// dropped or closed; the other handles don't delete anything. On Unix, they
+/// can keep using the file after that, and its storage is freed once the last
+/// handle is closed; on Windows, the deletion is delayed until then. Later
+/// callers then create a fresh file.
+///
+/// The hash isn't guaranteed to be stable across builds of the program, so
+/// don't rely on the path staying the same between versions.
+///
+/// # Security
+///
+/// The name is predictable, so another user on the system can create the
+/// file first and have callers open it. Only use this in a directory no one
+/// else can write to.
+///
+/// # Errors
+///
+/// If the file can be neither created nor opened, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{self, Read, Seek, SeekFrom, Write};
+/// # fn run() -> Result<(), io::Error> {
+/// let mut first = tempfile::keyed("thumbnail:42")?;
+/// let mut second = tempfile::keyed("thumbnail:42")?;
+/// assert_eq!(first.path(), second.path());
+///
+/// first.write_all(b"cached")?;
+/// let mut contents = String::new();
+/// second.seek(SeekFrom::Start(0))?;
+/// second.read_to_string(&mut contents)?;
+/// assert_eq!(contents, "cached");
+/// # Ok(())
+/// # }
+/// # run().unwrap();
+/// ```
+///
+/// [`std::env::temp_dir()`]: https://doc.rust-lang.org/std/env/fn.temp_dir.html
+pub fn keyed(key: &str) -> io::Result<NamedTempFile> {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(key.as_bytes());
+    let path = crate::default_temp_dir().join(format!(".tmp{:016x}", hasher.finish()));
+    let mut open_options = OpenOptions::new();
+    file::share_all(&mut open_options);
+    file::create_shared(path, &mut open_options, None)
+}
@@ -27,6 +27,14 @@
 //! rely on file paths for _some_ operations. See the security documentation on
 //! the `NamedTempFile` type for more information.
 //!
+//! ## Tracing
+//!
+//! With the `tracing` feature enabled, named temporary files and directories
+//! emit debug-level [`tracing`] spans and events as candidate names are tried,
+//! when files are persisted, and when they're removed on drop, recording the
+//! paths involved and the outcome. Without the feature, none of this is
+//! compiled in.
+//!
 //! ## Examples
 //!
 //! Create a temporary file and write some data into it:
@@ -116,6 +124,7 @@
 //! [`TempDir`]: struct.TempDir.html
 //! [`NamedTempFile`]: struct.NamedTempFile.html
 //! [`std::env::temp_dir()`]: https://doc.rust-lang.org/std/env/fn.temp_dir.html
+//! [`tracing`]: https://docs.rs/tracing
 
 #![doc(
     html_logo_url = "https://www.rust-lang.org/logos/rust-logo-128x128-blk-v2.png",
@@ -132,39 +141,202 @@ extern crate cfg_if;
 const NUM_RETRIES: u32 = 1 << 31;
 const NUM_RAND_CHARS: usize = 6;
 
-use std::ffi::OsStr;
-use std::fs::OpenOptions;
-use std::path::Path;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::ffi::{OsStr, OsString};
+use std::fs::{File, OpenOptions, Permissions};
+use std::io::{Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use std::{env, io};
 
+use crate::dir::SlowCleanupHook;
+use crate::enospc::EnospcHook;
+use crate::error::IoResultExt;
+
+/// Emit a `tracing` event at debug level if the `tracing` feature is enabled;
+/// expands to nothing otherwise.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($arg)*);
+    };
+}
+
+/// Enter a `tracing` span at debug level until the end of the enclosing block
+/// if the `tracing` feature is enabled; expands to nothing otherwise.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod archive;
+mod atomic;
+#[cfg(feature = "cap-std")]
+mod cap;
+mod children;
+#[cfg(feature = "zstd")]
+mod compressed;
 mod dir;
+mod encoding;
+mod enospc;
 mod error;
 mod file;
+mod fs_kind;
+#[cfg(feature = "sha2")]
+mod hashing;
+mod isolated;
+mod keyed;
+mod lock;
+#[cfg(feature = "mime")]
+mod mime;
+mod names;
+#[cfg(unix)]
+mod open_dir;
+mod pool;
+mod quota;
+#[cfg(target_os = "linux")]
+mod ram;
+mod reservation;
+mod rewind;
+mod rotating;
+mod scope;
+#[cfg(unix)]
+mod signals;
+mod single;
+mod split;
 mod spooled;
+mod stale;
+mod temp_dir;
 mod util;
 
-pub use crate::dir::{tempdir, tempdir_in, TempDir};
+pub use crate::atomic::{atomic_writer, AtomicWriter};
+#[cfg(feature = "cap-std")]
+pub use crate::cap::CapTempFile;
+pub use crate::children::ChildTempFile;
+#[cfg(feature = "zstd")]
+pub use crate::compressed::{spooled_tempfile_compressed, CompressedSpooledTempFile};
+pub use crate::dir::{tempdir, tempdir_in, tempdir_with_rand, TempDir, TempDirGuard};
+pub use crate::encoding::Encoding;
+pub use crate::enospc::EnospcRetryingWriter;
+#[cfg(target_os = "linux")]
+pub use crate::file::tempfile_with_proc_path;
 pub use crate::file::{
-    tempfile, tempfile_in, NamedTempFile, PathPersistError, PersistError, TempPath,
+    from_reader, tempfile, tempfile_in, tempfile_with_rand, DurablePersistError, NamedTempFile,
+    PathPersistError, PersistError, TempPath,
+};
+pub use crate::fs_kind::{temp_fs_kind, FsKind};
+#[cfg(feature = "sha2")]
+pub use crate::hashing::HashingNamedTempFile;
+pub use crate::isolated::IsolatedTempFile;
+pub use crate::keyed::keyed;
+pub use crate::lock::FileLock;
+pub use crate::names::{validate_component, NameError};
+#[cfg(unix)]
+pub use crate::open_dir::OpenDirTempFile;
+pub use crate::pool::TempFilePool;
+pub use crate::quota::{QuotaFile, QuotaTempDir};
+#[cfg(target_os = "linux")]
+pub use crate::ram::{ram_scratch, RamScratch};
+pub use crate::reservation::NameReservation;
+pub use crate::rewind::RewindReader;
+pub use crate::rotating::RotatingTempFiles;
+pub use crate::scope::TempScope;
+#[cfg(unix)]
+pub use crate::signals::{cleanup_on_signals, cleanup_on_signals_chained, Signal};
+pub use crate::single::{single_instance, SingleInstanceGuard};
+pub use crate::split::{TempReader, TempWriter};
+pub use crate::spooled::{
+    spooled_from_reader, spooled_tempfile, Checkpoint, SpooledData, SpooledTempFile,
 };
-pub use crate::spooled::{spooled_tempfile, SpooledTempFile};
+pub use crate::stale::clean_stale;
+pub use crate::temp_dir::{default_temp_dir, refresh_default_temp_dir};
 
 /// Create a new temporary file or directory with custom parameters.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Builder<'a, 'b> {
     random_len: usize,
+    rand_encoding: Encoding,
+    secure_rng: bool,
+    sortable: bool,
     prefix: &'a OsStr,
     suffix: &'b OsStr,
     append: bool,
+    max_name_len: Option<usize>,
+    permissions: Option<Permissions>,
+    exact_name: Option<OsString>,
+    sync_on_drop: bool,
+    wipe_on_drop: bool,
+    seek_to: Option<u64>,
+    on_enospc: Option<EnospcHook>,
+    require_utf8: bool,
+    strict_names: bool,
+    require_safe_dir: bool,
+    inheritable: bool,
+    share_mode: Option<(bool, bool, bool)>,
+    nested_prefix: Option<PathBuf>,
+    thread_sharded: bool,
+    shared_append: bool,
+    nfs_safe: bool,
+    keep_dir_handle: bool,
+    chroot_base: Option<PathBuf>,
+    verbose_errors: bool,
+    disallow_network_fs: bool,
+    min_free_space: Option<u64>,
+    reserve_extents: Option<u64>,
+    writable_fallbacks: bool,
+    numa_dirs: Vec<(u32, PathBuf)>,
+    warn_slow_cleanup: Option<(Duration, SlowCleanupHook)>,
+    reflink_from: Option<PathBuf>,
+    path_transform: Option<util::PathTransform>,
+    #[cfg(unix)]
+    xattrs: Vec<(OsString, Vec<u8>)>,
 }
 
 impl<'a, 'b> Default for Builder<'a, 'b> {
     fn default() -> Self {
         Builder {
             random_len: crate::NUM_RAND_CHARS,
+            rand_encoding: Encoding::default(),
+            secure_rng: false,
+            sortable: false,
             prefix: OsStr::new(".tmp"),
             suffix: OsStr::new(""),
             append: false,
+            max_name_len: None,
+            permissions: None,
+            exact_name: None,
+            sync_on_drop: false,
+            wipe_on_drop: false,
+            seek_to: None,
+            on_enospc: None,
+            require_utf8: false,
+            strict_names: false,
+            require_safe_dir: false,
+            inheritable: false,
+            share_mode: None,
+            nested_prefix: None,
+            thread_sharded: false,
+            shared_append: false,
+            nfs_safe: false,
+            keep_dir_handle: false,
+            chroot_base: None,
+            verbose_errors: false,
+            disallow_network_fs: false,
+            min_free_space: None,
+            reserve_extents: None,
+            writable_fallbacks: false,
+            numa_dirs: Vec::new(),
+            warn_slow_cleanup: None,
+            reflink_from: None,
+            path_transform: None,
+            #[cfg(unix)]
+            xattrs: Vec::new(),
         }
     }
 }
@@ -270,6 +442,80 @@ impl<'a, 'b> Builder<'a, 'b> {
         self
     }
 
+    /// Set the prefix to the name of the current executable.
+    ///
+    /// This makes it easy to tell which program a leaked temporary file
+    /// belongs to: for an executable named `myapp` (or `myapp.exe`), names
+    /// look like `.myapp-AbC123`. The name is taken from
+    /// [`std::env::current_exe`] the first time it's needed and cached for
+    /// the rest of the process. If it can't be determined, the default `.tmp`
+    /// prefix is used instead.
+    ///
+    /// Like any prefix, this is replaced by a later call to [`prefix`], and
+    /// replaces an earlier one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .auto_prefix()
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`std::env::current_exe`]: https://doc.rust-lang.org/std/env/fn.current_exe.html
+    /// [`prefix`]: #method.prefix
+    pub fn auto_prefix(&mut self) -> &mut Self {
+        self.prefix = util::exe_prefix();
+        self
+    }
+
+    /// Place the temporary file or directory in a nested directory below the
+    /// base directory.
+    ///
+    /// Unlike [`prefix`], which is part of the file name, `dirs` is a relative
+    /// path such as `a/b`: the temporary resource is created at
+    /// `<base>/a/b/<prefix><random><suffix>`. Any of these directories that
+    /// don't exist yet are created first and removed again (if empty) once the
+    /// temporary resource is deleted or persisted. Paths that are absolute or
+    /// contain `..` are rejected with an [`io::ErrorKind::InvalidInput`] error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let dir = tempfile::tempdir()?;
+    /// let named_tempfile = Builder::new()
+    ///     .nested_prefix("a/b")
+    ///     .tempfile_in(&dir)?;
+    /// assert!(named_tempfile.path().starts_with(dir.path().join("a").join("b")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`prefix`]: struct.Builder.html#method.prefix
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn nested_prefix<P: AsRef<Path>>(&mut self, dirs: P) -> &mut Self {
+        self.nested_prefix = Some(dirs.as_ref().to_path_buf());
+        self
+    }
+
     /// Set a custom filename suffix.
     ///
     /// Path separators are legal but not advisable.
@@ -297,8 +543,83 @@ impl<'a, 'b> Builder<'a, 'b> {
         self
     }
 
+    /// Set the filename suffix to an extension matching a MIME type.
+    ///
+    /// This is a thin layer over [`suffix`]: `image/png` becomes `.png`,
+    /// `text/plain; charset=utf-8` becomes `.txt`, and so on. Only a small
+    /// table of common types (images, audio/video, text, and a few
+    /// application formats) is known; matching ignores case and parameters.
+    /// Unknown types fall back to `.bin`.
+    ///
+    /// Requires the `mime` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .suffix_from_mime("image/png")
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`suffix`]: #method.suffix
+    #[cfg(feature = "mime")]
+    pub fn suffix_from_mime(&mut self, mime: &str) -> &mut Self {
+        self.suffix(mime::extension(mime))
+    }
+
+    /// Use exactly `name` for the temporary file or directory.
+    ///
+    /// No random component is generated and the prefix and suffix are ignored:
+    /// a single exclusive create of `name` is attempted in the target
+    /// directory. The result is still cleaned up like any other temporary
+    /// resource.
+    ///
+    /// # Errors
+    ///
+    /// If something already exists at that name, creation fails with an
+    /// [`io::ErrorKind::AlreadyExists`] error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let dir = tempfile::tempdir()?;
+    /// let named_tempfile = Builder::new()
+    ///     .exact_name("report.txt")
+    ///     .tempfile_in(&dir)?;
+    /// assert_eq!(named_tempfile.path(), dir.path().join("report.txt"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`io::ErrorKind::AlreadyExists`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.AlreadyExists
+    pub fn exact_name<S: Into<OsString>>(&mut self, name: S) -> &mut Self {
+        self.exact_name = Some(name.into());
+        self
+    }
+
     /// Set the number of random bytes.
     ///
+    /// This is really the number of random characters, whose alphabet is set
+    /// by [`rand_encoding`].
+    ///
     /// Default: `6`.
     ///
     /// # Examples
@@ -318,15 +639,74 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # Ok(())
     /// # }
     /// ```
+    ///
+    /// [`rand_encoding`]: #method.rand_encoding
     pub fn rand_bytes(&mut self, rand: usize) -> &mut Self {
         self.random_len = rand;
         self
     }
 
-    /// Set the file to be opened in append mode.
+    /// Set the alphabet used for the random part of the name.
+    ///
+    /// See [`Encoding`] for the available alphabets and how much randomness
+    /// each character carries.
+    ///
+    /// On macOS, iOS and Windows, names use [`Encoding::Base32`] instead of
+    /// [`Encoding::Base62`] in directories on a filesystem that ignores case
+    /// (see [`FsKind::is_case_insensitive`]): there, names that only differ in
+    /// case collide, so the extra characters don't add any randomness. This
+    /// costs a filesystem query the first time `Base62` names are generated
+    /// in a directory; the answer is remembered after that.
+    ///
+    /// Default: [`Encoding::Base62`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// use tempfile::{Builder, Encoding};
+    ///
+    /// let named_tempfile = Builder::new()
+    ///     .prefix("scratch-")
+    ///     .rand_encoding(Encoding::HexLower)
+    ///     .rand_bytes(16)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Encoding`]: enum.Encoding.html
+    /// [`Encoding::Base62`]: enum.Encoding.html#variant.Base62
+    /// [`Encoding::Base32`]: enum.Encoding.html#variant.Base32
+    /// [`FsKind::is_case_insensitive`]: enum.FsKind.html#method.is_case_insensitive
+    pub fn rand_encoding(&mut self, encoding: Encoding) -> &mut Self {
+        self.rand_encoding = encoding;
+        self
+    }
+
+    /// Generate the random part of the name directly from the operating
+    /// system's random number generator (`OsRng`).
+    ///
+    /// By default, names come from `rand::thread_rng()`, which is currently a
+    /// cryptographically secure generator seeded from the OS, but `rand`
+    /// doesn't promise that it will stay that way. Use this where predictable
+    /// names could be exploited. Every name then costs a system call (e.g.
+    /// `getrandom`) per character, which is noticeably slower than the
+    /// user-space generator, though usually still cheap next to creating the
+    /// file itself.
     ///
     /// Default: `false`.
     ///
+    /// # Panics
+    ///
+    /// Creating a file panics if the OS random number generator fails.
+    ///
     /// # Examples
     ///
     /// ```
@@ -339,29 +719,64 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # fn run() -> Result<(), io::Error> {
     /// # use tempfile::Builder;
     /// let named_tempfile = Builder::new()
-    ///     .append(true)
+    ///     .secure_rng(true)
     ///     .tempfile()?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn append(&mut self, append: bool) -> &mut Self {
-        self.append = append;
+    pub fn secure_rng(&mut self, secure_rng: bool) -> &mut Self {
+        self.secure_rng = secure_rng;
         self
     }
 
-    /// Create the named temporary file.
+    /// Start the generated part of names with a timestamp, so that sorting
+    /// names lexicographically sorts them by creation time.
     ///
-    /// # Security
+    /// The timestamp is the time since the Unix epoch in nanoseconds, written
+    /// as 16 lowercase hexadecimal digits right after the prefix and followed
+    /// by the usual random characters, e.g. `.tmp17f3a2c4e5b6d7e8AbC123`.
+    /// Names with the same prefix then sort oldest-first, and the random part
+    /// still keeps them unique: on a collision, another random part is tried
+    /// with the same timestamp. This makes every name 16 bytes longer, which
+    /// counts towards [`Builder::max_name_len`].
     ///
-    /// See [the security][security] docs on `NamedTempFile`.
+    /// The timestamp comes from the system clock, so the order is only as
+    /// good as the clock: if it's adjusted backwards (or differs between
+    /// machines sharing a directory), later files can sort before earlier
+    /// ones. Files created within the same nanosecond are ordered randomly.
+    /// Has no effect together with [`Builder::exact_name`].
     ///
-    /// # Resource leaking
+    /// Default: `false`.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    /// # Examples
     ///
-    /// # Errors
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let dir = tempfile::tempdir()?;
+    /// let older = Builder::new().sortable(true).tempfile_in(&dir)?;
+    /// let newer = Builder::new().sortable(true).tempfile_in(&dir)?;
+    /// // `older` sorts before `newer`, clock permitting.
+    /// # Ok(())
+    /// # }
+    /// ```
     ///
-    /// If the file cannot be created, `Err` is returned.
+    /// [`Builder::max_name_len`]: #method.max_name_len
+    /// [`Builder::exact_name`]: #method.exact_name
+    pub fn sortable(&mut self, sortable: bool) -> &mut Self {
+        self.sortable = sortable;
+        self
+    }
+
+    /// Set the file to be opened in append mode.
+    ///
+    /// Default: `false`.
     ///
     /// # Examples
     ///
@@ -374,30 +789,66 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # }
     /// # fn run() -> Result<(), io::Error> {
     /// # use tempfile::Builder;
-    /// let tempfile = Builder::new().tempfile()?;
+    /// let named_tempfile = Builder::new()
+    ///     .append(true)
+    ///     .tempfile()?;
     /// # Ok(())
     /// # }
     /// ```
-    ///
-    /// [security]: struct.NamedTempFile.html#security
-    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
-    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
-        self.tempfile_in(&env::temp_dir())
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
     }
 
-    /// Create the named temporary file in the specified directory.
+    /// Move the temporary file's cursor to `offset` right after creating it.
     ///
-    /// # Security
+    /// This sets up the "write the body first, then rewind and fill in the
+    /// header" pattern declaratively: with `seek_to(HEADER_LEN)`, writes start
+    /// after the space reserved for the header. Seeking past the end doesn't
+    /// extend the file by itself; the gap is filled with zeros once data is
+    /// written after it. Has no effect on [`Builder::tempdir`].
     ///
-    /// See [the security][security] docs on `NamedTempFile`.
+    /// Can't be combined with [`Builder::append`] (or
+    /// [`Builder::shared_append`]), since appending ignores the cursor: trying
+    /// to create a file with both fails with an `InvalidInput` error.
     ///
-    /// # Resource leaking
+    /// Default: no seek (the cursor starts at offset 0).
     ///
-    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    /// # Examples
     ///
-    /// # Errors
+    /// ```
+    /// # use std::io::{self, Seek, SeekFrom, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let mut file = Builder::new().seek_to(4).tempfile()?;
+    /// file.write_all(b"body")?;
+    /// file.seek(SeekFrom::Start(0))?;
+    /// file.write_all(b"HEAD")?;
+    /// # Ok(())
+    /// # }
+    /// ```
     ///
-    /// If the file cannot be created, `Err` is returned.
+    /// [`Builder::tempdir`]: #method.tempdir
+    /// [`Builder::append`]: #method.append
+    /// [`Builder::shared_append`]: #method.shared_append
+    pub fn seek_to(&mut self, offset: u64) -> &mut Self {
+        self.seek_to = Some(offset);
+        self
+    }
+
+    /// Allow the temporary file's handle to be inherited by child processes.
+    ///
+    /// By default, temporary files are opened with `O_CLOEXEC` on UNIX and as
+    /// non-inheritable handles on Windows, so they don't leak into processes
+    /// you spawn. Enable this when you _do_ want to pass the open file to a
+    /// child.
+    ///
+    /// Default: `false`.
     ///
     /// # Examples
     ///
@@ -410,91 +861,2136 @@ impl<'a, 'b> Builder<'a, 'b> {
     /// # }
     /// # fn run() -> Result<(), io::Error> {
     /// # use tempfile::Builder;
-    /// let tempfile = Builder::new().tempfile_in("./")?;
+    /// let named_tempfile = Builder::new()
+    ///     .inheritable(true)
+    ///     .tempfile()?;
     /// # Ok(())
     /// # }
     /// ```
-    ///
-    /// [security]: struct.NamedTempFile.html#security
-    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
-    pub fn tempfile_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
-        util::create_helper(
-            dir.as_ref(),
-            self.prefix,
-            self.suffix,
-            self.random_len,
-            |path| file::create_named(path, OpenOptions::new().append(self.append)),
-        )
+    pub fn inheritable(&mut self, inheritable: bool) -> &mut Self {
+        self.inheritable = inheritable;
+        self
     }
 
-    /// Attempts to make a temporary directory inside of `env::temp_dir()` whose
-    /// name will have the prefix, `prefix`. The directory and
-    /// everything inside it will be automatically deleted once the
-    /// returned `TempDir` is destroyed.
+    /// Set which kinds of access other handles to temporary files may have
+    /// while this one is open, on Windows.
     ///
-    /// # Resource leaking
+    /// This maps to the `dwShareMode` argument of `CreateFileW`: `read`,
+    /// `write` and `delete` add `FILE_SHARE_READ`, `FILE_SHARE_WRITE` and
+    /// `FILE_SHARE_DELETE` respectively. An open by another process (or by
+    /// this one) that asks for access not allowed here fails with
+    /// `ERROR_SHARING_VIOLATION`. On Unix and other platforms, files can
+    /// always be opened by others and this option has no effect.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    /// Leaving out `delete` also prevents this crate from deleting or renaming
+    /// the file while its handle is open: [`NamedTempFile::persist`] fails,
+    /// and dropping the `NamedTempFile` leaks the file. To clean it up, split
+    /// it with [`NamedTempFile::into_parts`] and close the `File` before
+    /// dropping the `TempPath`.
     ///
-    /// # Errors
+    /// Overrides the share mode picked by [`Builder::tempfile_shared`]. Only
+    /// applies to named temporary files.
     ///
-    /// If the directory can not be created, `Err` is returned.
+    /// Default: the standard library's default, which shares read, write and
+    /// delete access.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::fs::File;
-    /// use std::io::Write;
-    /// use tempfile::Builder;
-    ///
     /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
     /// # fn run() -> Result<(), io::Error> {
-    /// let tmp_dir = Builder::new().tempdir()?;
+    /// # use tempfile::Builder;
+    /// // Other processes may read the file, but not write to it.
+    /// let named_tempfile = Builder::new()
+    ///     .share_mode(true, false, true)
+    ///     .tempfile()?;
     /// # Ok(())
     /// # }
     /// ```
     ///
-    /// [resource-leaking]: struct.TempDir.html#resource-leaking
-    pub fn tempdir(&self) -> io::Result<TempDir> {
-        self.tempdir_in(&env::temp_dir())
+    /// [`NamedTempFile::persist`]: struct.NamedTempFile.html#method.persist
+    /// [`NamedTempFile::into_parts`]: struct.NamedTempFile.html#method.into_parts
+    /// [`Builder::tempfile_shared`]: #method.tempfile_shared
+    pub fn share_mode(&mut self, read: bool, write: bool, delete: bool) -> &mut Self {
+        self.share_mode = Some((read, write, delete));
+        self
     }
 
-    /// Attempts to make a temporary directory inside of `dir`.
-    /// The directory and everything inside it will be automatically
-    /// deleted once the returned `TempDir` is destroyed.
+    /// Require the path of the temporary file or directory to be valid UTF-8.
     ///
-    /// # Resource leaking
+    /// The full path is checked before anything is created, so a non-UTF-8
+    /// temporary directory (e.g., from `TMPDIR`) or prefix fails fast instead
+    /// of breaking a downstream tool that only accepts UTF-8 paths.
     ///
-    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    /// Default: `false`.
     ///
     /// # Errors
     ///
-    /// If the directory can not be created, `Err` is returned.
+    /// If the path isn't valid UTF-8, creation fails with an
+    /// [`io::ErrorKind::InvalidInput`] error mentioning the path.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::fs::{self, File};
-    /// use std::io::Write;
-    /// use tempfile::Builder;
-    ///
     /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
     /// # fn run() -> Result<(), io::Error> {
-    /// let tmp_dir = Builder::new().tempdir_in("./")?;
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .require_utf8(true)
+    ///     .tempfile()?;
+    /// let path: &str = named_tempfile.path().to_str().unwrap();
     /// # Ok(())
     /// # }
     /// ```
     ///
-    /// [resource-leaking]: struct.TempDir.html#resource-leaking
-    pub fn tempdir_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<TempDir> {
-        let storage;
-        let mut dir = dir.as_ref();
-        if !dir.is_absolute() {
-            let cur_dir = env::current_dir()?;
-            storage = cur_dir.join(dir);
-            dir = &storage;
-        }
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn require_utf8(&mut self, require_utf8: bool) -> &mut Self {
+        self.require_utf8 = require_utf8;
+        self
+    }
 
-        util::create_helper(dir, self.prefix, self.suffix, self.random_len, dir::create)
+    /// Reject prefixes and suffixes that aren't safe to use in a file name.
+    ///
+    /// With this option, the prefix and suffix are checked with
+    /// [`validate_component`] before anything is created: they must not
+    /// contain NUL bytes or path separators (`/` or `\`), and must not be a
+    /// name reserved on Windows, such as `CON` or `NUL`. This is worth
+    /// enabling when they come from user input; without it, a prefix like
+    /// `../` is joined to the directory as is.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Errors
+    ///
+    /// If the prefix or suffix is rejected, creation fails with an
+    /// [`io::ErrorKind::InvalidInput`] error describing the problem.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io;
+    /// use tempfile::Builder;
+    ///
+    /// let err = Builder::new()
+    ///     .prefix("../escape")
+    ///     .strict_names(true)
+    ///     .tempfile()
+    ///     .unwrap_err();
+    /// assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    /// ```
+    ///
+    /// [`validate_component`]: fn.validate_component.html
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn strict_names(&mut self, strict_names: bool) -> &mut Self {
+        self.strict_names = strict_names;
+        self
+    }
+
+    /// Refuse to create temporary files and directories in an unsafe shared
+    /// directory.
+    ///
+    /// On Unix, a directory that is world-writable but lacks the sticky bit
+    /// lets any user delete or rename other users' files in it, and thus
+    /// replace a temporary file with their own between its creation and its
+    /// next use by path. With this option, the directory passed to
+    /// [`Builder::tempfile_in`] or [`Builder::tempdir_in`] (or the default
+    /// temporary directory) is checked before anything is created, like
+    /// `mktemp`-style tools do. Symbolic links are followed, so the check
+    /// applies to the directory actually used.
+    ///
+    /// This has no effect on other platforms.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory is world-writable without the sticky bit, creation
+    /// fails with an [`io::ErrorKind::PermissionDenied`] error mentioning the
+    /// directory. If the directory can't be inspected, that error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .require_safe_dir(true)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::tempfile_in`]: #method.tempfile_in
+    /// [`Builder::tempdir_in`]: #method.tempdir_in
+    /// [`io::ErrorKind::PermissionDenied`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.PermissionDenied
+    pub fn require_safe_dir(&mut self, require_safe_dir: bool) -> &mut Self {
+        self.require_safe_dir = require_safe_dir;
+        self
+    }
+
+    /// Create temporary files and directories in a per-thread subdirectory of
+    /// the base directory.
+    ///
+    /// When many threads create temporary files in the same directory, the
+    /// filesystem's locking of that directory can become a bottleneck. With
+    /// this option, each thread gets its own randomly named `.tmpshard*`
+    /// subdirectory, created the first time the thread needs it and removed
+    /// when the thread exits. A shard that still contains something at that
+    /// point (for example, a temporary file handed to another thread) is left
+    /// behind.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let dir = tempfile::tempdir()?;
+    /// let named_tempfile = Builder::new()
+    ///     .thread_sharded(true)
+    ///     .tempfile_in(&dir)?;
+    /// let shard = named_tempfile.path().parent().unwrap();
+    /// assert_eq!(shard.parent(), Some(dir.path()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn thread_sharded(&mut self, thread_sharded: bool) -> &mut Self {
+        self.thread_sharded = thread_sharded;
+        self
+    }
+
+    /// Share one append-only temporary file between several openers.
+    ///
+    /// Requires [`Builder::exact_name`]. Instead of failing if the file already
+    /// exists, [`Builder::tempfile`] opens it, so several threads or processes
+    /// using the same name end up appending to the same file. Everything is
+    /// opened with `O_APPEND` (or the Windows equivalent), so each write lands
+    /// at the end of the file as a whole.
+    ///
+    /// Only the `NamedTempFile` that actually created the file deletes it when
+    /// dropped (or closed); the others leave it alone. The creator doesn't wait
+    /// for the others, though: once it's gone, the others keep writing to a
+    /// deleted file (Unix) or the deletion is delayed until they close it
+    /// (Windows), and a later opener creates a fresh file. Make sure the
+    /// creator outlives the other users.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let dir = tempfile::tempdir()?;
+    /// let mut builder = Builder::new();
+    /// builder.exact_name("shared.log").shared_append(true);
+    ///
+    /// let mut creator = builder.tempfile_in(&dir)?;
+    /// let mut other = builder.tempfile_in(&dir)?;
+    /// writeln!(creator, "first")?;
+    /// writeln!(other, "second")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::exact_name`]: #method.exact_name
+    /// [`Builder::tempfile`]: #method.tempfile
+    pub fn shared_append(&mut self, shared_append: bool) -> &mut Self {
+        self.shared_append = shared_append;
+        self
+    }
+
+    /// Create temporary files in a way that guarantees unique names even on
+    /// old NFS servers.
+    ///
+    /// Temporary files are normally created with `O_CREAT | O_EXCL`, and a
+    /// name collision is detected when that fails with `EEXIST`. NFSv2 has no
+    /// exclusive create, so the client emulates it with a separate lookup and
+    /// create, and two clients can both "successfully" create the same file.
+    /// Even on later versions, a retransmitted request whose first reply was
+    /// lost can fail with `EEXIST` although it created the file.
+    ///
+    /// With this option, the file is first created under a private unique
+    /// name, which is then hard-linked to the final name. `link` is atomic on
+    /// NFS, but its result can be misreported just like `O_EXCL`'s, so success
+    /// is decided by checking that the private file's link count is now 2;
+    /// then the private name is removed. This is the classic lock-file trick
+    /// described in the `open(2)` man page. It costs a few extra round trips
+    /// per file, so only use it where needed.
+    ///
+    /// Only applies to named temporary files on Unix; it has no effect on
+    /// directories or on other platforms. Can't be combined with
+    /// [`Builder::shared_append`].
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .nfs_safe(true)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::shared_append`]: #method.shared_append
+    pub fn nfs_safe(&mut self, nfs_safe: bool) -> &mut Self {
+        self.nfs_safe = nfs_safe;
+        self
+    }
+
+    /// Keep an open handle to the temporary file's parent directory.
+    ///
+    /// Renaming a file only becomes durable once its directory has been
+    /// synchronized too. With this option, the directory is opened right
+    /// after the file is created and the handle is kept alongside it:
+    /// [`NamedTempFile::persist_durable`] then calls `fsync` on it after the
+    /// rename, and [`NamedTempFile::parent_dir`] exposes it for other uses.
+    /// This saves reopening the directory by path at persist time, when it
+    /// may have been moved or replaced.
+    ///
+    /// The sync only covers the temporary file's directory, so persist to a
+    /// path in the same directory (e.g. with [`NamedTempFile::for_target`]).
+    /// If the rename succeeds but the sync fails, the error says so and
+    /// where the file now is. The other persist methods don't sync.
+    ///
+    /// Each file created this way holds one extra file descriptor.
+    ///
+    /// Only has an effect on Unix: Windows has no way to synchronize a
+    /// directory through a handle, and [`NamedTempFile::parent_dir`] always
+    /// returns `None` there.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// # let dir = tempfile::tempdir()?;
+    /// let mut file = Builder::new().keep_dir_handle(true).tempfile_in(&dir)?;
+    /// writeln!(file, "durable")?;
+    /// file.persist_durable(dir.path().join("saved.txt"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`NamedTempFile::persist_durable`]: struct.NamedTempFile.html#method.persist_durable
+    /// [`NamedTempFile::parent_dir`]: struct.NamedTempFile.html#method.parent_dir
+    /// [`NamedTempFile::for_target`]: struct.NamedTempFile.html#method.for_target
+    pub fn keep_dir_handle(&mut self, keep_dir_handle: bool) -> &mut Self {
+        self.keep_dir_handle = keep_dir_handle;
+        self
+    }
+
+    /// Resolve absolute directories relative to `base`, as if `base` were the
+    /// root of the filesystem.
+    ///
+    /// This is meant for code that runs inside a chroot jail in production
+    /// but outside of one in tests (or the other way round): with
+    /// `chroot_base("/srv/jail")`, [`tempfile`] creates its file under
+    /// `/srv/jail/tmp` instead of `/tmp`, and `tempfile_in("/var/cache")`
+    /// under `/srv/jail/var/cache`. Relative directories passed to the `_in`
+    /// methods are left alone, since they already resolve the same way on
+    /// both sides.
+    ///
+    /// This doesn't perform a chroot, and the returned paths include `base`.
+    /// It also doesn't resolve symbolic links within `base`, so a link
+    /// pointing outside of it is followed as usual.
+    ///
+    /// Default: none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// # #[cfg(unix)]
+    /// # {
+    /// let jail = tempfile::tempdir()?;
+    /// std::fs::create_dir(jail.path().join("scratch"))?;
+    /// let named_tempfile = Builder::new()
+    ///     .chroot_base(jail.path())
+    ///     .tempfile_in("/scratch")?;
+    /// assert!(named_tempfile.path().starts_with(jail.path().join("scratch")));
+    /// # }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`tempfile`]: #method.tempfile
+    pub fn chroot_base<P: AsRef<Path>>(&mut self, base: P) -> &mut Self {
+        self.chroot_base = Some(base.as_ref().to_path_buf());
+        self
+    }
+
+    /// Report the last name collision instead of a generic error when giving
+    /// up on finding a free name.
+    ///
+    /// A randomly named temporary file or directory is retried with a new
+    /// name whenever the chosen one already exists. If every attempt fails,
+    /// the error is normally a synthetic [`io::ErrorKind::AlreadyExists`]
+    /// error saying that too many temporary files exist. With this option, the
+    /// operating system's error for the last attempt is returned instead,
+    /// along with the path that was tried, which helps when debugging why the
+    /// names keep colliding. Any other error already stops the attempts and is
+    /// returned as is, with or without this option.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .verbose_errors(true)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`io::ErrorKind::AlreadyExists`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.AlreadyExists
+    pub fn verbose_errors(&mut self, verbose_errors: bool) -> &mut Self {
+        self.verbose_errors = verbose_errors;
+        self
+    }
+
+    /// Refuse to create temporary files or directories on a network
+    /// filesystem.
+    ///
+    /// Before anything is created, the filesystem containing the directory is
+    /// identified as for [`temp_fs_kind`], and an
+    /// [`io::ErrorKind::Unsupported`] error is returned if it's NFS or SMB
+    /// (see [`FsKind::is_network`]). This catches a misconfigured temporary
+    /// directory early in latency-sensitive programs, instead of letting them
+    /// silently slow down.
+    ///
+    /// Network filesystems are detected on Linux, Android, macOS, iOS,
+    /// FreeBSD and Windows. Filesystems that can't be identified, including
+    /// every filesystem on other platforms, are reported as
+    /// [`FsKind::Unknown`] and allowed through.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .disallow_network_fs(true)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`temp_fs_kind`]: fn.temp_fs_kind.html
+    /// [`FsKind::is_network`]: enum.FsKind.html#method.is_network
+    /// [`FsKind::Unknown`]: enum.FsKind.html#variant.Unknown
+    /// [`io::ErrorKind::Unsupported`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.Unsupported
+    pub fn disallow_network_fs(&mut self, disallow_network_fs: bool) -> &mut Self {
+        self.disallow_network_fs = disallow_network_fs;
+        self
+    }
+
+    /// Refuse to create temporary files or directories unless at least
+    /// `bytes` bytes are free on the filesystem containing the directory.
+    ///
+    /// The space available to the current user is queried (with `statvfs(2)`
+    /// on Unix and `GetDiskFreeSpaceExW` on Windows) right before creation,
+    /// and an [`io::ErrorKind::StorageFull`] error is returned if it's less
+    /// than `bytes`. On other platforms, the query itself fails.
+    ///
+    /// This is only a best-effort snapshot: nothing is reserved, so the space
+    /// may be gone by the time it's needed, and other processes can still
+    /// fill the disk afterwards.
+    ///
+    /// Default: no minimum.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .min_free_space(1024 * 1024)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`io::ErrorKind::StorageFull`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.StorageFull
+    pub fn min_free_space(&mut self, bytes: u64) -> &mut Self {
+        self.min_free_space = Some(bytes);
+        self
+    }
+
+    /// Allocate disk space for the first `bytes` bytes of temporary files up
+    /// front, without changing their size.
+    ///
+    /// This is meant for files that are about to receive a large sequential
+    /// write: reserving the space in one go lets the filesystem lay it out in
+    /// few, contiguous extents instead of growing the file piecemeal, and
+    /// makes running out of space fail at creation rather than halfway
+    /// through. The file still starts out empty; writing into the reserved
+    /// range just doesn't need to allocate.
+    ///
+    /// The space is reserved with `fallocate(2)` and `FALLOC_FL_KEEP_SIZE` on
+    /// Linux and Android, `fcntl(F_PREALLOCATE)` on macOS (asking for a
+    /// contiguous run first), and `SetFileInformationByHandle` with
+    /// `FileAllocationInfo` on Windows. Where the platform or filesystem has
+    /// no way to do this, the option is silently ignored. Only applies to
+    /// named temporary files.
+    ///
+    /// Default: nothing is reserved.
+    ///
+    /// # Errors
+    ///
+    /// If the space can't be reserved, e.g. because the disk is too full,
+    /// creation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .reserve_extents(16 * 1024 * 1024)
+    ///     .tempfile()?;
+    /// assert_eq!(named_tempfile.as_file().metadata()?.len(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reserve_extents(&mut self, bytes: u64) -> &mut Self {
+        self.reserve_extents = Some(bytes);
+        self
+    }
+
+    /// Fall back to other writable directories when `std::env::temp_dir()`
+    /// is on a read-only filesystem.
+    ///
+    /// On some immutable or image-based systems, the default temporary
+    /// directory resolves to a read-only layer. With this option, if creating
+    /// a temporary file or directory in `std::env::temp_dir()` fails with
+    /// [`io::ErrorKind::ReadOnlyFilesystem`] (`EROFS`), creation is retried
+    /// in the following directories, in this order, stopping at the first one
+    /// that works:
+    ///
+    /// 1. `/run`
+    /// 2. `/var/tmp`
+    /// 3. `$XDG_RUNTIME_DIR`, if set
+    ///
+    /// Directories that don't exist are skipped. If none of them work, the
+    /// original error is returned. The fallbacks only exist on Unix; on other
+    /// platforms, this option has no effect.
+    ///
+    /// This only applies to the methods that use the default temporary
+    /// directory, such as [`Builder::tempfile`] and [`Builder::tempdir`]; an
+    /// explicitly requested directory (e.g. with [`Builder::tempfile_in`]) is
+    /// never substituted. Other errors are returned as they are.
+    ///
+    /// Default: off.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .writable_fallbacks(true)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`io::ErrorKind::ReadOnlyFilesystem`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.ReadOnlyFilesystem
+    /// [`Builder::tempfile`]: #method.tempfile
+    /// [`Builder::tempdir`]: #method.tempdir
+    /// [`Builder::tempfile_in`]: #method.tempfile_in
+    pub fn writable_fallbacks(&mut self, enabled: bool) -> &mut Self {
+        self.writable_fallbacks = enabled;
+        self
+    }
+
+    /// Call `warn` when dropping a temporary directory takes `threshold` or
+    /// longer.
+    ///
+    /// Removing a directory on drop blocks the current thread for as long as
+    /// deleting its contents takes, which for a large or pathological tree
+    /// (or a slow filesystem) can be seconds. With this set, the time spent in
+    /// `Drop` is measured, and if it reaches `threshold`, `warn` is called
+    /// with the directory's path and the time taken, e.g. to log it.
+    /// Cleanups that finish in time stay silent, and without this option the
+    /// clock isn't read at all.
+    ///
+    /// The callback runs inside `Drop`, so it shouldn't panic. It's shared by
+    /// all directories created by this builder (and its clones), and may be
+    /// called from any thread that drops one.
+    ///
+    /// Only applies to temporary directories, and only to the cleanup done by
+    /// `Drop`: [`TempDir::close`] returns control to the caller, who can time
+    /// it themselves. Deleting a temporary file is a single `unlink` and isn't
+    /// timed.
+    ///
+    /// Default: no callback.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # use std::time::Duration;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let tmp_dir = Builder::new()
+    ///     .warn_slow_cleanup(Duration::from_secs(1), |path, elapsed| {
+    ///         eprintln!("removing {} took {:?}", path.display(), elapsed);
+    ///     })
+    ///     .tempdir()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TempDir::close`]: struct.TempDir.html#method.close
+    pub fn warn_slow_cleanup<F>(&mut self, threshold: Duration, warn: F) -> &mut Self
+    where
+        F: Fn(&Path, Duration) + Send + Sync + 'static,
+    {
+        self.warn_slow_cleanup = Some((threshold, SlowCleanupHook::new(warn)));
+        self
+    }
+
+    /// Create temporary files and directories on a filesystem local to the
+    /// NUMA node the current thread is running on, picked from `dirs`.
+    ///
+    /// On multi-socket machines with a separate scratch mount per node (e.g.
+    /// a tmpfs bound to each node's memory), keeping temporary data on the
+    /// local node avoids cross-node memory traffic. There's no standard way
+    /// to discover such mounts, so the mapping must be supplied: `dirs` pairs
+    /// node numbers, as reported by the kernel, with the directory to use for
+    /// each. Calling this again replaces the mapping.
+    ///
+    /// At creation time, the calling thread's node is looked up with
+    /// `getcpu(2)` on Linux (a cheap, vDSO-backed call) and the matching
+    /// directory used in place of `std::env::temp_dir()`. The thread may
+    /// migrate to another node afterwards; the choice isn't revisited. If the
+    /// node can't be determined, which is always the case on other platforms,
+    /// or it isn't in the mapping, the default temporary directory is used.
+    /// Errors from creating the file in a mapped directory are returned as
+    /// they are, without falling back.
+    ///
+    /// This only applies to the methods that use the default temporary
+    /// directory, such as [`Builder::tempfile`] and [`Builder::tempdir`]; an
+    /// explicitly requested directory (e.g. with [`Builder::tempfile_in`]) is
+    /// never substituted.
+    ///
+    /// Default: no mapping.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .numa_local(vec![(0, "/scratch/node0"), (1, "/scratch/node1")])
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::tempfile`]: #method.tempfile
+    /// [`Builder::tempdir`]: #method.tempdir
+    /// [`Builder::tempfile_in`]: #method.tempfile_in
+    pub fn numa_local<I, P>(&mut self, dirs: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (u32, P)>,
+        P: AsRef<Path>,
+    {
+        self.numa_dirs = dirs
+            .into_iter()
+            .map(|(node, dir)| (node, dir.as_ref().to_path_buf()))
+            .collect();
+        self
+    }
+
+    /// Start temporary files off as a copy of `src`.
+    ///
+    /// This is handy for working on a scratch copy of a file, e.g. to modify
+    /// it and then persist the result over the original. Where possible, the
+    /// temporary file is created as a copy-on-write clone (reflink) of `src`,
+    /// which is nearly free regardless of its size: this is the case on Linux
+    /// with filesystems such as Btrfs and XFS, via the `FICLONE` ioctl, as long
+    /// as the temporary file is on the same filesystem as `src`. Otherwise,
+    /// including on all other platforms, the contents are copied normally.
+    /// This means macOS always copies, even on APFS: its `clonefile` can only
+    /// create a new file, while the temporary file already exists by the time
+    /// it's filled. Either way, the file's cursor starts at the beginning, and
+    /// it's cleaned up on drop like any other temporary file.
+    ///
+    /// Only the contents are copied, not the permissions or other metadata of
+    /// `src`. Has no effect on [`Builder::tempdir`]. Can't be combined with
+    /// [`Builder::shared_append`].
+    ///
+    /// Default: none.
+    ///
+    /// # Errors
+    ///
+    /// If `src` can't be opened or read, creating the temporary file fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Read};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// # let dir = tempfile::tempdir()?;
+    /// let original = dir.path().join("data.bin");
+    /// std::fs::write(&original, b"original contents")?;
+    ///
+    /// let mut scratch = Builder::new().reflink_from(&original).tempfile_in(&dir)?;
+    /// let mut contents = String::new();
+    /// scratch.read_to_string(&mut contents)?;
+    /// assert_eq!(contents, "original contents");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::tempdir`]: #method.tempdir
+    /// [`Builder::shared_append`]: #method.shared_append
+    pub fn reflink_from<P: AsRef<Path>>(&mut self, src: P) -> &mut Self {
+        self.reflink_from = Some(src.as_ref().to_path_buf());
+        self
+    }
+
+    /// Rewrite each candidate path before trying to create it.
+    ///
+    /// `transform` is called with every randomly generated path, right
+    /// before the temporary file or directory is created there, and returns
+    /// the path to use instead. This allows custom layouts, such as sharding
+    /// files into subdirectories named after the first characters of their
+    /// random name. Directories implied by the new path that don't exist yet
+    /// are created, and removed again along with the temporary resource if
+    /// they're empty by then (like with [`Builder::nested_prefix`]).
+    ///
+    /// The new path must stay within the directory the resource is created
+    /// in: if it doesn't start with the same directory, or contains `..`,
+    /// creation fails with an [`io::ErrorKind::InvalidInput`] error.
+    /// [`rand_token`] assumes that the file name keeps the generated random
+    /// characters at the same position.
+    ///
+    /// Default: none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// use std::path::PathBuf;
+    ///
+    /// let dir = tempfile::tempdir()?;
+    /// // `<dir>/.tmpAbC123` becomes `<dir>/Ab/.tmpAbC123`.
+    /// let named_tempfile = Builder::new()
+    ///     .path_transform(|path: PathBuf| {
+    ///         let name = path.file_name().unwrap().to_str().unwrap().to_owned();
+    ///         path.with_file_name(&name[4..6]).join(name)
+    ///     })
+    ///     .tempfile_in(&dir)?;
+    /// let shard = named_tempfile.path().parent().unwrap();
+    /// assert_eq!(shard.parent(), Some(dir.path()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::nested_prefix`]: #method.nested_prefix
+    /// [`rand_token`]: struct.NamedTempFile.html#method.rand_token
+    /// [`io::ErrorKind::InvalidInput`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidInput
+    pub fn path_transform<F>(&mut self, transform: F) -> &mut Self
+    where
+        F: Fn(PathBuf) -> PathBuf + Send + Sync + 'static,
+    {
+        self.path_transform = Some(util::PathTransform(Arc::new(transform)));
+        self
+    }
+
+    /// Set an extended attribute on temporary files as soon as they are
+    /// created (Unix only).
+    ///
+    /// The attribute is set with `fsetxattr` on the new file's descriptor
+    /// before the `NamedTempFile` is returned, so the file is never used
+    /// without it; this is useful for e.g. `security.selinux` labels. Call this
+    /// several times to set several attributes. Doesn't apply to temporary
+    /// directories.
+    ///
+    /// # Errors
+    ///
+    /// If an attribute can't be set, the file is deleted and the error is
+    /// returned. Extended attributes are supported on Linux, Android, macOS,
+    /// and iOS; elsewhere, creation always fails if any are requested.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .xattr("user.origin", b"download")
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(unix)]
+    pub fn xattr<N: AsRef<OsStr> + ?Sized>(&mut self, name: &N, value: &[u8]) -> &mut Self {
+        self.xattrs
+            .push((name.as_ref().to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Synchronize the temporary file to disk when it's dropped.
+    ///
+    /// If enabled, dropping the `NamedTempFile` calls [`File::sync_all`] on
+    /// the file before it's deleted and its handle is closed. This is useful
+    /// when another process reads the file by path while it exists and needs
+    /// the contents to be durable. Errors from the sync are ignored because
+    /// they can't be reported from a destructor.
+    ///
+    /// Note that this can make dropping the file noticeably slower, since it
+    /// blocks until the data has reached the disk. Consuming the file with
+    /// `persist`, `keep`, `into_file`, `into_temp_path`, or `into_parts`, or
+    /// removing it with `close`, doesn't sync it.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .sync_on_drop(true)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`File::sync_all`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.sync_all
+    pub fn sync_on_drop(&mut self, sync_on_drop: bool) -> &mut Self {
+        self.sync_on_drop = sync_on_drop;
+        self
+    }
+
+    /// Overwrite the temporary file's contents with zeros before it's
+    /// deleted.
+    ///
+    /// If enabled, dropping or [closing] the `NamedTempFile` first overwrites
+    /// the file's current length with zeros and syncs the zeros to disk, so
+    /// that sensitive scratch data doesn't linger in the freed blocks. Errors
+    /// while wiping are ignored on drop (the file is still deleted), and
+    /// returned by `close` after deleting the file. Files that are consumed
+    /// with `persist`, `keep`, `into_file`, `into_temp_path`, or `into_parts`
+    /// are handed over as they are.
+    ///
+    /// This is best-effort only. On SSDs and flash storage, wear-leveling
+    /// usually writes the zeros to different physical blocks, and copy-on-write
+    /// filesystems (such as Btrfs, ZFS and APFS), snapshots and backups can
+    /// keep the old contents around too. Data written by other handles after
+    /// the wipe isn't wiped either.
+    ///
+    /// Default: `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .wipe_on_drop(true)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [closing]: struct.NamedTempFile.html#method.close
+    pub fn wipe_on_drop(&mut self, wipe_on_drop: bool) -> &mut Self {
+        self.wipe_on_drop = wipe_on_drop;
+        self
+    }
+
+    /// Set a hook to call when writing to the temporary file fails because
+    /// the disk is full.
+    ///
+    /// The crate can't intercept writes to the underlying `File`, so the hook
+    /// only applies to writes through
+    /// [`NamedTempFile::enospc_retrying_writer`]: if such a write fails with
+    /// `ENOSPC`, `hook` is called (e.g. to evict other temporary files) and,
+    /// if it returns `Ok`, the write is retried once. If the hook returns an
+    /// error, that error is returned from the write instead.
+    ///
+    /// The hook is shared by all files created by this builder (and its
+    /// clones), and may be called from any thread that writes to them.
+    ///
+    /// Default: no hook.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let mut named_tempfile = Builder::new()
+    ///     .on_enospc(|| {
+    ///         eprintln!("disk full, retrying");
+    ///         Ok(())
+    ///     })
+    ///     .tempfile()?;
+    /// named_tempfile.enospc_retrying_writer().write_all(b"data")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`NamedTempFile::enospc_retrying_writer`]: struct.NamedTempFile.html#method.enospc_retrying_writer
+    pub fn on_enospc<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn() -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.on_enospc = Some(EnospcHook::new(hook));
+        self
+    }
+
+    /// Make the temporary file readable by all users.
+    ///
+    /// By default, temporary files are created so that only the current user
+    /// can access them (mode `0600` on UNIX). With this option they are
+    /// instead created with mode `0644` (before applying the umask). The mode
+    /// is applied atomically when the file is created, so there is no window
+    /// during which the file has different permissions.
+    ///
+    /// This only affects files; on Windows, access is governed by the
+    /// directory's ACLs and this option does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .world_readable()
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn world_readable(&mut self) -> &mut Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            self.permissions = Some(Permissions::from_mode(0o644));
+        }
+        self
+    }
+
+    /// Set the maximum length of the generated file name.
+    ///
+    /// The length of the prefix, the random part, and the suffix combined
+    /// (in bytes) is checked against this limit before anything is created.
+    /// This is useful on filesystems with short file name limits, where the
+    /// OS would otherwise fail every attempt with an obscure error.
+    ///
+    /// Default: no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let named_tempfile = Builder::new()
+    ///     .max_name_len(255)
+    ///     .tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_name_len(&mut self, max: usize) -> &mut Self {
+        self.max_name_len = Some(max);
+        self
+    }
+
+    /// Describe how [`Builder::tempfile`] will open files with the current
+    /// settings, for debugging unexpected file attributes.
+    ///
+    /// The description names the system calls and flags used on the current
+    /// platform, e.g. `open(O_RDWR | O_CREAT | O_EXCL | O_CLOEXEC, mode 0600)`
+    /// on Unix or `CreateFileW(GENERIC_READ | GENERIC_WRITE, ..., CREATE_NEW,
+    /// FILE_ATTRIBUTE_TEMPORARY, ...)` on Windows, followed by any adjustments
+    /// made after opening (such as making the handle inheritable). The exact
+    /// flags, and the format of the description, are platform-specific and
+    /// may change between versions, so it's meant for humans rather than for
+    /// parsing. Nothing is created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use tempfile::Builder;
+    /// let flags = Builder::new().inheritable(true).debug_open_flags();
+    /// println!("temporary files are opened with {}", flags);
+    /// ```
+    ///
+    /// [`Builder::tempfile`]: #method.tempfile
+    pub fn debug_open_flags(&self) -> String {
+        file::describe_open_flags(
+            self.append,
+            self.shared_append,
+            self.nfs_safe,
+            self.inheritable,
+            self.share_mode,
+            self.permissions.as_ref(),
+        )
+    }
+
+    /// Create the named temporary file.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let tempfile = Builder::new().tempfile()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        self.in_temp_dir(|dir| self.tempfile_in(dir))
+    }
+
+    /// Create the named temporary file in the specified directory.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let tempfile = Builder::new().tempfile_in("./")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [security]: struct.NamedTempFile.html#security
+    /// [resource-leaking]: struct.NamedTempFile.html#resource-leaking
+    pub fn tempfile_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
+        self.create_named_in(dir.as_ref(), false)
+    }
+
+    /// Reserve a temporary file name in the specified directory without
+    /// creating the file for real yet.
+    ///
+    /// This is meant for two-phase protocols: reserve a name, tell a peer
+    /// about it, then create the file. The name is picked exactly as for
+    /// [`Builder::tempfile_in`] and held by an empty placeholder file until
+    /// the returned [`NameReservation`] is upgraded with
+    /// [`NameReservation::into_temp_file`], released, or dropped.
+    ///
+    /// # Errors
+    ///
+    /// If the placeholder cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// # let dir = tempfile::tempdir()?;
+    /// let (path, reservation) = Builder::new().suffix(".part").reserve(&dir)?;
+    /// println!("will write to {}", path.display());
+    ///
+    /// let mut file = reservation.into_temp_file();
+    /// writeln!(file, "payload")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::tempfile_in`]: #method.tempfile_in
+    /// [`NameReservation`]: struct.NameReservation.html
+    /// [`NameReservation::into_temp_file`]: struct.NameReservation.html#method.into_temp_file
+    pub fn reserve<P: AsRef<Path>>(&self, dir: P) -> io::Result<(PathBuf, NameReservation)> {
+        let file = self.tempfile_in(dir)?;
+        Ok((file.path().to_path_buf(), NameReservation::new(file)))
+    }
+
+    /// Create a named temporary file that other processes can open by path
+    /// while this handle is still open.
+    ///
+    /// On Windows, whether another process can open a file is governed by the
+    /// _share mode_ the file was opened with. This method opens the file with
+    /// `FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE`, so an external
+    /// tool can read, write, or even delete it while you keep the handle.
+    /// That's already the standard library's default share mode, which
+    /// [`Builder::tempfile`] uses too, so this doesn't change how the file is
+    /// opened: it exists to make the intent explicit at the call site. To
+    /// share less, use [`Builder::share_mode`], which overrides it. (The
+    /// anonymous [`tempfile()`], by contrast, uses no sharing at all.)
+    ///
+    /// On other platforms, files can always be opened by other processes, so
+    /// this is equivalent to [`Builder::tempfile`] there too.
+    ///
+    /// # Security
+    ///
+    /// See [the security][security] docs on `NamedTempFile`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let tempfile = Builder::new().tempfile_shared()?;
+    /// let other_handle = std::fs::File::open(tempfile.path())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`tempfile()`]: fn.tempfile.html
+    /// [`Builder::tempfile`]: #method.tempfile
+    /// [`Builder::share_mode`]: #method.share_mode
+    /// [security]: struct.NamedTempFile.html#security
+    pub fn tempfile_shared(&self) -> io::Result<NamedTempFile> {
+        self.in_temp_dir(|dir| self.tempfile_shared_in(dir))
+    }
+
+    /// Create a named temporary file in the specified directory that other
+    /// processes can open by path while this handle is still open.
+    ///
+    /// See [`Builder::tempfile_shared`] for details.
+    ///
+    /// [`Builder::tempfile_shared`]: #method.tempfile_shared
+    pub fn tempfile_shared_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
+        self.create_named_in(dir.as_ref(), true)
+    }
+
+    /// Create a temporary file for child processes to use, in
+    /// `std::env::temp_dir()`.
+    ///
+    /// This bundles the settings for a parent that creates temporary files,
+    /// spawns children that use them (by path or through the inherited
+    /// handle), and cleans up once they're done: the file is created as with
+    /// [`Builder::tempfile_shared`] and [`Builder::inheritable`], and deleting
+    /// it is left to [`ChildTempFile::cleanup`] rather than to `Drop`. See
+    /// [`ChildTempFile`] for what this means on each platform.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io;
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let file = Builder::new().for_children()?;
+    /// // ... hand `file.path()` to children and wait for them ...
+    /// file.cleanup()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::tempfile_shared`]: #method.tempfile_shared
+    /// [`Builder::inheritable`]: #method.inheritable
+    /// [`ChildTempFile`]: struct.ChildTempFile.html
+    /// [`ChildTempFile::cleanup`]: struct.ChildTempFile.html#method.cleanup
+    pub fn for_children(&self) -> io::Result<ChildTempFile> {
+        self.in_temp_dir(|dir| self.for_children_in(dir))
+    }
+
+    /// Create a temporary file for child processes to use, in the specified
+    /// directory.
+    ///
+    /// See [`Builder::for_children`] for details.
+    ///
+    /// [`Builder::for_children`]: #method.for_children
+    pub fn for_children_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<ChildTempFile> {
+        let mut builder = self.clone();
+        builder.inheritable = true;
+        builder
+            .create_named_in(dir.as_ref(), true)
+            .map(ChildTempFile::new)
+    }
+
+    /// Create a named temporary file inside its own, newly created temporary
+    /// directory in `std::env::temp_dir()`.
+    ///
+    /// The builder's settings apply to the file; the directory always gets a
+    /// default random name. On Unix, the directory's permissions are set to
+    /// `0700`. Dropping the returned [`IsolatedTempFile`] deletes both.
+    ///
+    /// # Errors
+    ///
+    /// If the directory or the file cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let mut isolated = Builder::new().suffix(".txt").isolated()?;
+    /// assert_eq!(isolated.path().parent(), Some(isolated.dir_path()));
+    /// writeln!(isolated, "for my eyes only")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`IsolatedTempFile`]: struct.IsolatedTempFile.html
+    pub fn isolated(&self) -> io::Result<IsolatedTempFile> {
+        self.in_temp_dir(|dir| self.isolated_in(dir))
+    }
+
+    /// Create a named temporary file inside its own, newly created temporary
+    /// directory in the specified directory.
+    ///
+    /// See [`Builder::isolated`] for details.
+    ///
+    /// [`Builder::isolated`]: #method.isolated
+    pub fn isolated_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<IsolatedTempFile> {
+        self.validate()?;
+        let dir = Builder::new().tempdir_in(dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(dir.path(), Permissions::from_mode(0o700))
+                .with_err_path(|| dir.path())?;
+        }
+        let file = self.tempfile_in(&dir)?;
+        Ok(IsolatedTempFile::new(file, dir))
+    }
+
+    /// Create a temporary alternate data stream on an existing file (Windows).
+    ///
+    /// NTFS lets a file carry additional named _alternate data streams_
+    /// (`file.txt:stream`) besides its main contents. This creates a new,
+    /// uniquely named stream on `file`, using the usual prefix, suffix, and
+    /// random name settings for the stream name, and returns it as a
+    /// `NamedTempFile`. The stream is deleted when the `NamedTempFile` is
+    /// dropped, and disappears along with `file` if that is deleted first.
+    ///
+    /// The settings for opened files (such as [`Builder::share_mode`],
+    /// [`Builder::seek_to`] or [`Builder::wipe_on_drop`]) apply as for
+    /// [`Builder::tempfile_in`]. Settings about the directory (such as
+    /// [`Builder::nested_prefix`]) and [`Builder::nfs_safe`] don't make sense
+    /// for a stream, and are rejected.
+    ///
+    /// Persisting a stream to a regular path isn't supported by the OS.
+    ///
+    /// # Errors
+    ///
+    /// If the builder has a setting that isn't supported here, an error of
+    /// kind `InvalidInput` naming it is returned. If `file` doesn't exist, is
+    /// on a filesystem without alternate data stream support, or the stream
+    /// cannot be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// let mut scratch = Builder::new()
+    ///     .prefix("temp")
+    ///     .tempstream_on("C:\\data\\report.txt")?;
+    /// writeln!(scratch, "scratch data")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(windows)]
+    pub fn tempstream_on<P: AsRef<Path>>(&self, file: P) -> io::Result<NamedTempFile> {
+        let file = file.as_ref();
+        self.validate()?;
+        // Streams can't be hard linked, and there's no directory to speak of.
+        Builder::reject_settings(
+            "alternate data streams",
+            self.dir_settings()
+                .iter()
+                .chain(&[("nfs_safe", self.nfs_safe)]),
+        )?;
+        let source = self.reflink_source()?;
+        self.create_in(Path::new(""), |name| {
+            let mut stream = file.as_os_str().to_owned();
+            stream.push(":");
+            stream.push(name);
+            self.open_named(PathBuf::from(stream), false, source.as_ref())
+        })
+        .map(|(file, _)| file)
+    }
+
+    /// Create a named temporary file relative to an already open directory.
+    ///
+    /// Names are picked, and collisions retried, exactly as for
+    /// [`Builder::tempfile_in`], but the file is created with `openat` on the
+    /// directory's file descriptor and deleted with `unlinkat` when the
+    /// returned [`OpenDirTempFile`] is dropped. No path is ever resolved, so
+    /// this is race-free even if the directory is moved or a path leading to
+    /// it is swapped out. Of the other settings, only [`Builder::append`] and
+    /// [`Builder::world_readable`] apply: settings about the directory (such
+    /// as [`Builder::nested_prefix`]) or the opened file (such as
+    /// [`Builder::seek_to`] or [`Builder::wipe_on_drop`]) are rejected.
+    ///
+    /// `dir` must be a directory opened for reading, e.g. with
+    /// `File::open(dir_path)`.
+    ///
+    /// Unix only. Windows supports the same pattern through `NtCreateFile`
+    /// with a `RootDirectory` handle, which isn't exposed by the standard
+    /// library; use [`Builder::tempfile_in_dir`] there, which does that on
+    /// your behalf.
+    ///
+    /// # Errors
+    ///
+    /// If the builder has a setting that isn't supported here, an error of
+    /// kind `InvalidInput` naming it is returned. If the file cannot be
+    /// created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// use std::fs::File;
+    ///
+    /// # let tmp = tempfile::tempdir()?;
+    /// # let path = tmp.path();
+    /// let dir = File::open(path)?;
+    /// let mut file = Builder::new().suffix(".txt").tempfile_in_open_dir(&dir)?;
+    /// writeln!(file, "relative to a handle")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Builder::tempfile_in`]: #method.tempfile_in
+    /// [`Builder::append`]: #method.append
+    /// [`Builder::world_readable`]: #method.world_readable
+    /// [`Builder::nested_prefix`]: #method.nested_prefix
+    /// [`Builder::seek_to`]: #method.seek_to
+    /// [`Builder::wipe_on_drop`]: #method.wipe_on_drop
+    /// [`Builder::tempfile_in_dir`]: #method.tempfile_in_dir
+    /// [`OpenDirTempFile`]: struct.OpenDirTempFile.html
+    #[cfg(unix)]
+    pub fn tempfile_in_open_dir<'d>(
+        &self,
+        dir: &'d std::fs::File,
+    ) -> io::Result<OpenDirTempFile<'d>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        self.validate()?;
+        Builder::reject_settings(
+            "open directories",
+            self.dir_settings().iter().chain(&self.file_settings()),
+        )?;
+        let mode = self.permissions.as_ref().map_or(0o600, |p| p.mode());
+        self.create_in(Path::new(""), |name| {
+            let file = file::create_at(dir, &name, self.append, mode)
+                .with_err_path_unless_exists(|| &name)?;
+            Ok(OpenDirTempFile::new(dir, name, file))
+        })
+        .map(|(file, _)| file)
+    }
+
+    /// Create a named temporary file relative to a capability-based
+    /// [`cap_std::fs::Dir`], without ever using an absolute path.
+    ///
+    /// Names are picked, and collisions retried, exactly as for
+    /// [`Builder::tempfile_in`]; the file is opened with `Dir::open_with` and
+    /// deleted with `Dir::remove_file` when the returned [`CapTempFile`] is
+    /// dropped. Of the other settings, only [`Builder::append`] and
+    /// [`Builder::world_readable`] apply: settings about the directory (such as
+    /// [`Builder::nested_prefix`]) or the opened file (such as
+    /// [`Builder::seek_to`] or [`Builder::wipe_on_drop`]) are rejected.
+    ///
+    /// Requires the `cap-std` feature.
+    ///
+    /// # Errors
+    ///
+    /// If the builder has a setting that isn't supported here, an error of
+    /// kind `InvalidInput` naming it is returned. If the file cannot be
+    /// created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// # use tempfile::Builder;
+    /// use cap_std::ambient_authority;
+    /// use cap_std::fs::Dir;
+    ///
+    /// # let tmp = tempfile::tempdir()?;
+    /// # let path = tmp.path();
+    /// let dir = Dir::open_ambient_dir(path, ambient_authority())?;
+    /// let mut file = Builder::new().suffix(".txt").tempfile_in_dir(&dir)?;
+    /// writeln!(file, "sandboxed")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`cap_std::fs::Dir`]: https://docs.rs/cap-std/*/cap_std/fs/struct.Dir.html
+    /// [`Builder::tempfile_in`]: #method.tempfile_in
+    /// [`Builder::append`]: #method.append
+    /// [`Builder::world_readable`]: #method.world_readable
+    /// [`Builder::nested_prefix`]: #method.nested_prefix
+    /// [`Builder::seek_to`]: #method.seek_to
+    /// [`Builder::wipe_on_drop`]: #method.wipe_on_drop
+    /// [`CapTempFile`]: struct.CapTempFile.html
+    #[cfg(feature = "cap-std")]
+    pub fn tempfile_in_dir<'d>(&self, dir: &'d cap_std::fs::Dir) -> io::Result<CapTempFile<'d>> {
+        self.validate()?;
+        Builder::reject_settings(
+            "capability-based directories",
+            self.dir_settings().iter().chain(&self.file_settings()),
+        )?;
+        self.create_in(Path::new(""), |name| {
+            let mut open_options = cap_std::fs::OpenOptions::new();
+            open_options
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .append(self.append);
+            #[cfg(unix)]
+            {
+                use cap_std::fs::OpenOptionsExt;
+                open_options.mode(self.permissions.as_ref().map_or(0o600, |p| {
+                    use std::os::unix::fs::PermissionsExt;
+                    p.mode()
+                }));
+            }
+            let file = dir
+                .open_with(&name, &open_options)
+                .with_err_path_unless_exists(|| &name)?;
+            Ok(CapTempFile::new(dir, name, file))
+        })
+        .map(|(file, _)| file)
+    }
+
+    /// Attempts to make a temporary directory inside of `env::temp_dir()` whose
+    /// name will have the prefix, `prefix`. The directory and
+    /// everything inside it will be automatically deleted once the
+    /// returned `TempDir` is destroyed.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// # use std::io;
+    /// # fn run() -> Result<(), io::Error> {
+    /// let tmp_dir = Builder::new().tempdir()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [resource-leaking]: struct.TempDir.html#resource-leaking
+    pub fn tempdir(&self) -> io::Result<TempDir> {
+        self.in_temp_dir(|dir| self.tempdir_in(dir))
+    }
+
+    /// Attempts to make a temporary directory inside of `dir`.
+    /// The directory and everything inside it will be automatically
+    /// deleted once the returned `TempDir` is destroyed.
+    ///
+    /// # Resource leaking
+    ///
+    /// See [the resource leaking][resource-leaking] docs on `TempDir`.
+    ///
+    /// # Errors
+    ///
+    /// If the directory can not be created, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::{self, File};
+    /// use std::io::Write;
+    /// use tempfile::Builder;
+    ///
+    /// # use std::io;
+    /// # fn run() -> Result<(), io::Error> {
+    /// let tmp_dir = Builder::new().tempdir_in("./")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [resource-leaking]: struct.TempDir.html#resource-leaking
+    pub fn tempdir_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<TempDir> {
+        self.validate()?;
+        let storage;
+        let dir = self.jailed(dir.as_ref());
+        let mut dir = &*dir;
+        if !dir.is_absolute() {
+            // The base directory is all there is to report if this fails.
+            let cur_dir = env::current_dir().with_err_path(|| dir)?;
+            storage = cur_dir.join(dir);
+            dir = &storage;
+        }
+
+        let dir = self.base_dir(dir)?;
+        let (dir, parent_dirs) = self.create_in(&dir, dir::create)?;
+        Ok(dir
+            .set_parent_dirs(parent_dirs)
+            .set_rand_token(self.rand_token_range())
+            .set_warn_slow_cleanup(self.warn_slow_cleanup.clone()))
+    }
+
+    fn create_named_in(&self, dir: &Path, shared: bool) -> io::Result<NamedTempFile> {
+        self.validate()?;
+        let dir = self.jailed(dir);
+        let dir = self.base_dir(&dir)?;
+        let source = self.reflink_source()?;
+        let (file, parent_dirs) =
+            self.create_in(&dir, |path| self.open_named(path, shared, source.as_ref()))?;
+        Ok(file
+            .set_parent_dirs(parent_dirs)
+            .set_rand_token(self.rand_token_range()))
+    }
+
+    /// The file to fill new temporary files from, if `reflink_from` is set.
+    fn reflink_source(&self) -> io::Result<Option<File>> {
+        match self.reflink_from {
+            Some(ref src) => File::open(src).with_err_path(|| src).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Create the named temporary file at `path` and apply the builder's
+    /// settings for opened files to it, filling it from `source`.
+    fn open_named(
+        &self,
+        path: PathBuf,
+        shared: bool,
+        source: Option<&File>,
+    ) -> io::Result<NamedTempFile> {
+        let mut open_options = OpenOptions::new();
+        open_options.append(self.append);
+        if shared {
+            file::share_all(&mut open_options);
+        }
+        if let Some((read, write, delete)) = self.share_mode {
+            file::set_share_mode(&mut open_options, read, write, delete);
+        }
+        let file = if self.shared_append {
+            file::create_shared_append(path, &mut open_options, self.permissions.as_ref())?
+        } else if self.nfs_safe {
+            file::create_named_nfs_safe(path, &mut open_options, self.permissions.as_ref())?
+        } else {
+            file::create_named(path, &mut open_options, self.permissions.as_ref())?
+        }
+        .set_sync_on_drop(self.sync_on_drop)
+        .set_wipe_on_drop(self.wipe_on_drop)
+        .set_on_enospc(self.on_enospc.clone());
+        if let Some(source) = source {
+            file::fill_from(source, file.as_file()).with_err_path(|| file.path())?;
+        }
+        if let Some(len) = self.reserve_extents {
+            file::reserve_extents(file.as_file(), len).with_err_path(|| file.path())?;
+        }
+        if let Some(offset) = self.seek_to {
+            file.as_file()
+                .seek(SeekFrom::Start(offset))
+                .with_err_path(|| file.path())?;
+        }
+        if self.inheritable {
+            file::set_inheritable(file.as_file(), true).with_err_path(|| file.path())?;
+        }
+        #[cfg(unix)]
+        for (name, value) in &self.xattrs {
+            file::set_xattr(file.as_file(), name, value).with_err_path(|| file.path())?;
+        }
+        #[cfg(unix)]
+        if self.keep_dir_handle {
+            let parent = file::open_parent_dir(file.path()).with_err_path(|| file.path())?;
+            return Ok(file.set_dir_handle(parent));
+        }
+        Ok(file)
+    }
+
+    /// The alphabet to generate random names in `dir` with. Where names that
+    /// only differ in case collide, mixed-case `Base62` is replaced with
+    /// lowercase `Base32`.
+    fn name_encoding(&self, dir: &Path) -> Encoding {
+        if cfg!(any(windows, target_os = "macos", target_os = "ios"))
+            && self.rand_encoding == Encoding::Base62
+            && util::is_case_insensitive(dir)
+        {
+            Encoding::Base32
+        } else {
+            self.rand_encoding
+        }
+    }
+
+    /// Where the random token sits within the names this builder generates.
+    fn rand_token_range(&self) -> Range<usize> {
+        match self.exact_name {
+            Some(_) => 0..0,
+            None => {
+                let start = self.prefix.len() + self.timestamp_len();
+                start..start + self.random_len
+            }
+        }
+    }
+
+    /// The part of generated names that precedes the random characters: the
+    /// prefix, followed by a timestamp if `sortable` is set.
+    fn name_prefix(&self) -> Cow<'a, OsStr> {
+        if self.sortable {
+            let mut prefix = self.prefix.to_os_string();
+            prefix.push(util::timestamp_token());
+            Cow::Owned(prefix)
+        } else {
+            Cow::Borrowed(self.prefix)
+        }
+    }
+
+    fn timestamp_len(&self) -> usize {
+        if self.sortable {
+            util::TIMESTAMP_LEN
+        } else {
+            0
+        }
+    }
+
+    /// Rebase an absolute `dir` onto the `chroot_base`, if any.
+    fn jailed<'p>(&self, dir: &'p Path) -> Cow<'p, Path> {
+        match self.chroot_base {
+            Some(ref base) if dir.is_absolute() => Cow::Owned(
+                dir.components()
+                    .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+                    .fold(base.clone(), |path, c| path.join(c)),
+            ),
+            _ => Cow::Borrowed(dir),
+        }
+    }
+
+    /// The directory to create temporary files and directories in, given the
+    /// one requested by the caller.
+    fn base_dir<'p>(&self, dir: &'p Path) -> io::Result<Cow<'p, Path>> {
+        if self.require_safe_dir {
+            let checked = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            if file::is_unsafe_dir(checked).with_err_path(|| dir)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "temporary directory is world-writable without the sticky bit",
+                ))
+                .with_err_path(|| dir);
+            }
+        }
+        if self.thread_sharded {
+            util::thread_shard(dir).map(Cow::Owned)
+        } else {
+            Ok(Cow::Borrowed(dir))
+        }
+    }
+
+    /// Run `f` on the default temporary directory, retrying in the fallback directories if
+    /// that's read-only and `writable_fallbacks` is set.
+    fn in_temp_dir<F, R>(&self, f: F) -> io::Result<R>
+    where
+        F: Fn(&Path) -> io::Result<R>,
+    {
+        self.validate()?;
+        let temp_dir = match self.numa_dir() {
+            Some(dir) => dir,
+            None => default_temp_dir(),
+        };
+        let err = match f(temp_dir) {
+            Err(err)
+                if self.writable_fallbacks && err.kind() == io::ErrorKind::ReadOnlyFilesystem =>
+            {
+                err
+            }
+            result => return result,
+        };
+        for dir in util::writable_fallbacks() {
+            if dir == temp_dir || !dir.is_dir() {
+                continue;
+            }
+            if let Ok(r) = f(&dir) {
+                return Ok(r);
+            }
+        }
+        Err(err)
+    }
+
+    /// The directory mapped to the current thread's NUMA node with
+    /// `numa_local`, if there is one.
+    fn numa_dir(&self) -> Option<&Path> {
+        if self.numa_dirs.is_empty() {
+            return None;
+        }
+        let node = file::numa_node()?;
+        self.numa_dirs
+            .iter()
+            .find(|&&(n, _)| n == node)
+            .map(|(_, dir)| dir.as_path())
+    }
+
+    /// Create the temporary resource in `dir` with `f`, picking names
+    /// according to the builder's settings. The configuration must have been
+    /// checked with `validate` first, before touching the filesystem.
+    ///
+    /// Also returns the directories created for a nested prefix, which the
+    /// caller should remove along with the resource.
+    fn create_in<F, R>(&self, dir: &Path, f: F) -> io::Result<(R, Vec<PathBuf>)>
+    where
+        F: Fn(PathBuf) -> io::Result<R>,
+    {
+        if self.disallow_network_fs {
+            let kind = file::fs_kind(dir).with_err_path(|| dir)?;
+            if kind.is_network() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!(
+                        "temporary directory is on a network filesystem ({:?})",
+                        kind
+                    ),
+                ))
+                .with_err_path(|| dir);
+            }
+        }
+        if let Some(min) = self.min_free_space {
+            let free = file::free_space(dir).with_err_path(|| dir)?;
+            if free < min {
+                return Err(io::Error::new(
+                    io::ErrorKind::StorageFull,
+                    format!(
+                        "only {} bytes free, but at least {} are required",
+                        free, min
+                    ),
+                ))
+                .with_err_path(|| dir);
+            }
+        }
+        let (dir, mut parent_dirs) = match self.nested_prefix {
+            Some(ref nested) => util::create_dirs(dir, nested)?,
+            None => (dir.to_path_buf(), Vec::new()),
+        };
+        // The path and OS error code of the last name collision, kept for
+        // `verbose_errors`.
+        let last_collision = RefCell::new(None);
+        let f = |path: PathBuf| {
+            if self.require_utf8 && path.to_str().is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "temporary path is not valid UTF-8",
+                ))
+                .with_err_path(|| path);
+            }
+            if !self.verbose_errors {
+                return f(path);
+            }
+            f(path.clone()).inspect_err(|e| {
+                if let (io::ErrorKind::AlreadyExists, Some(code)) = (e.kind(), e.raw_os_error()) {
+                    last_collision.replace(Some((path, code)));
+                }
+            })
+        };
+        // The directories created for the transformed path that succeeded.
+        let transform_dirs = RefCell::new(Vec::new());
+        let f = |path: PathBuf| {
+            let transform = match self.path_transform {
+                Some(ref transform) => transform,
+                None => return f(path),
+            };
+            let path = (transform.0)(path);
+            let created = util::create_dirs_to(&dir, &path)?;
+            match f(path) {
+                Ok(resource) => {
+                    transform_dirs.replace(created);
+                    Ok(resource)
+                }
+                Err(e) => {
+                    util::remove_dirs(&created);
+                    Err(e)
+                }
+            }
+        };
+        let result = match self.exact_name {
+            // Without retries, a name collision is reported to the caller, so
+            // attach the path that `f` left off (see `with_err_path_unless_exists`).
+            Some(ref name) => match f(dir.join(name)) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    Err(e).with_err_path(|| dir.join(name))
+                }
+                res => res,
+            },
+            None => util::create_helper(
+                &dir,
+                &self.name_prefix(),
+                self.suffix,
+                self.random_len,
+                self.name_encoding(&dir),
+                self.secure_rng,
+                f,
+            )
+            .or_else(|e| match last_collision.into_inner() {
+                Some((path, code)) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    Err(io::Error::from_raw_os_error(code)).with_err_path(|| path)
+                }
+                _ => Err(e),
+            }),
+        };
+        parent_dirs.extend(transform_dirs.into_inner());
+        match result {
+            Ok(resource) => Ok((resource, parent_dirs)),
+            Err(e) => {
+                util::remove_dirs(&parent_dirs);
+                Err(e)
+            }
+        }
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        if self.shared_append && self.exact_name.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "shared_append requires an exact_name",
+            ));
+        }
+        if self.shared_append && self.nfs_safe {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "shared_append cannot be combined with nfs_safe",
+            ));
+        }
+        if (self.append || self.shared_append) && self.seek_to.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek_to cannot be combined with append",
+            ));
+        }
+        if self.shared_append && self.reflink_from.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "shared_append cannot be combined with reflink_from",
+            ));
+        }
+        if self.strict_names {
+            for (what, component) in [("prefix", self.prefix), ("suffix", self.suffix)] {
+                if let Err(e) = validate_component(component) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid temporary file {} {:?}: {}", what, component, e),
+                    ));
+                }
+            }
+        }
+        if let Some(max) = self.max_name_len {
+            let len = match self.exact_name {
+                Some(ref name) => name.len(),
+                None => {
+                    self.prefix.len() + self.timestamp_len() + self.random_len + self.suffix.len()
+                }
+            };
+            if len > max {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "temporary file name would be {} bytes long, exceeding the maximum of {}",
+                        len, max
+                    ),
+                ));
+            }
+        }
+        if let Some(ref nested) = self.nested_prefix {
+            let is_relative = nested
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+            if !is_relative {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "nested prefix must be a relative path without `..` components",
+                ))
+                .with_err_path(|| nested);
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings about the directory that files are created in, as
+    /// `(method, is_set)` pairs, for `reject_settings`.
+    #[cfg(any(unix, windows, feature = "cap-std"))]
+    fn dir_settings(&self) -> [(&'static str, bool); 7] {
+        [
+            ("nested_prefix", self.nested_prefix.is_some()),
+            ("path_transform", self.path_transform.is_some()),
+            ("chroot_base", self.chroot_base.is_some()),
+            ("require_safe_dir", self.require_safe_dir),
+            ("thread_sharded", self.thread_sharded),
+            ("disallow_network_fs", self.disallow_network_fs),
+            ("min_free_space", self.min_free_space.is_some()),
+        ]
+    }
+
+    /// The settings that `create_named_in` applies to the files it opens, as
+    /// `(method, is_set)` pairs, for `reject_settings`.
+    #[cfg(any(unix, feature = "cap-std"))]
+    fn file_settings(&self) -> [(&'static str, bool); 12] {
+        #[cfg(unix)]
+        let xattrs = !self.xattrs.is_empty();
+        #[cfg(not(unix))]
+        let xattrs = false;
+        [
+            ("share_mode", self.share_mode.is_some()),
+            ("shared_append", self.shared_append),
+            ("nfs_safe", self.nfs_safe),
+            ("keep_dir_handle", self.keep_dir_handle),
+            ("reflink_from", self.reflink_from.is_some()),
+            ("reserve_extents", self.reserve_extents.is_some()),
+            ("seek_to", self.seek_to.is_some()),
+            ("inheritable", self.inheritable),
+            ("xattr", xattrs),
+            ("sync_on_drop", self.sync_on_drop),
+            ("wipe_on_drop", self.wipe_on_drop),
+            ("on_enospc", self.on_enospc.is_some()),
+        ]
+    }
+
+    /// Fail with `InvalidInput` for the first of `settings` that's set, for
+    /// entry points that can't honour them rather than ignoring them. `what`
+    /// says where the file would have been created.
+    #[cfg(any(unix, windows, feature = "cap-std"))]
+    fn reject_settings<'s, I>(what: &str, settings: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'s (&'static str, bool)>,
+    {
+        match settings.into_iter().find(|&&(_, set)| set) {
+            Some(&(setting, _)) => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not supported for {}", setting, what),
+            )),
+            None => Ok(()),
+        }
     }
 }
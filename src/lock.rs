@@ -0,0 +1,62 @@
+//! Advisory locks on temporary files.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::error::IoResultExt;
+use crate::file;
+
+/// A lock on a temporary file, released when dropped.
+///
+/// Created by the locking methods of [`NamedTempFile`], such as
+/// [`NamedTempFile::lock_exclusive`]. The lock belongs to the file handle it
+/// was taken on: other handles to the same file, even in the same process,
+/// are subject to it, and it's also released if the file is closed.
+///
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+/// [`NamedTempFile::lock_exclusive`]: struct.NamedTempFile.html#method.lock_exclusive
+pub struct FileLock<'a> {
+    file: &'a File,
+}
+
+impl<'a> FileLock<'a> {
+    /// Lock `file`, or return a `WouldBlock` error if `blocking` isn't set
+    /// and the lock is held elsewhere.
+    pub(crate) fn acquire(
+        file: &'a File,
+        path: &Path,
+        exclusive: bool,
+        blocking: bool,
+    ) -> io::Result<FileLock<'a>> {
+        if file::lock(file, exclusive, blocking).with_err_path(|| path)? {
+            Ok(FileLock { file })
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "file is locked by another handle",
+            ))
+            .with_err_path(|| path)
+        }
+    }
+
+    /// Release the lock now, reporting any error.
+    pub fn unlock(self) -> io::Result<()> {
+        let file = self.file;
+        std::mem::forget(self);
+        file::unlock(file)
+    }
+}
+
+impl fmt::Debug for FileLock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileLock").field("file", self.file).finish()
+    }
+}
+
+impl Drop for FileLock<'_> {
+    fn drop(&mut self) {
+        let _ = file::unlock(self.file);
+    }
+}
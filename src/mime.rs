@@ -0,0 +1,43 @@
+//! A small table mapping MIME types to file extensions.
+
+/// Common MIME types and the extension (including the leading dot) used for
+/// them. This is intentionally small; anything not listed maps to `.bin`.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("application/gzip", ".gz"),
+    ("application/json", ".json"),
+    ("application/octet-stream", ".bin"),
+    ("application/pdf", ".pdf"),
+    ("application/x-tar", ".tar"),
+    ("application/xml", ".xml"),
+    ("application/zip", ".zip"),
+    ("audio/mpeg", ".mp3"),
+    ("audio/ogg", ".ogg"),
+    ("audio/wav", ".wav"),
+    ("image/gif", ".gif"),
+    ("image/jpeg", ".jpg"),
+    ("image/png", ".png"),
+    ("image/svg+xml", ".svg"),
+    ("image/webp", ".webp"),
+    ("text/css", ".css"),
+    ("text/csv", ".csv"),
+    ("text/html", ".html"),
+    ("text/javascript", ".js"),
+    ("text/markdown", ".md"),
+    ("text/plain", ".txt"),
+    ("text/xml", ".xml"),
+    ("video/mp4", ".mp4"),
+    ("video/webm", ".webm"),
+];
+
+const FALLBACK: &str = ".bin";
+
+/// Look up the extension for `mime`, ignoring case and any parameters (e.g.
+/// `; charset=utf-8`).
+pub(crate) fn extension(mime: &str) -> &'static str {
+    let essence = mime.split(';').next().unwrap_or("").trim();
+    EXTENSIONS
+        .iter()
+        .find(|(ty, _)| ty.eq_ignore_ascii_case(essence))
+        .map(|&(_, ext)| ext)
+        .unwrap_or(FALLBACK)
+}
@@ -0,0 +1,103 @@
+//! Validation of user-supplied name components.
+
+use std::ffi::OsStr;
+use std::{error, fmt, io};
+
+/// Device names that Windows reserves in every directory, compared without
+/// regard to case or extension.
+const RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Why a name component was rejected by [`validate_component`].
+///
+/// [`validate_component`]: fn.validate_component.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum NameError {
+    /// The component contains a NUL byte, which no platform allows in file
+    /// names (on Unix, the name would be silently cut short or rejected).
+    Nul,
+    /// The component contains a path separator, `/` or `\`, so it would
+    /// refer to a different directory.
+    Separator,
+    /// The component is a device name reserved on Windows, such as `CON` or
+    /// `NUL`.
+    Reserved,
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            NameError::Nul => "name contains a NUL byte",
+            NameError::Separator => "name contains a path separator",
+            NameError::Reserved => "name is reserved on Windows",
+        })
+    }
+}
+
+impl error::Error for NameError {}
+
+impl From<NameError> for io::Error {
+    fn from(error: NameError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidInput, error)
+    }
+}
+
+/// Check that `s` is safe to use as part of a file name, e.g. as a prefix or
+/// suffix passed to [`Builder`].
+///
+/// This rejects:
+///
+/// * NUL bytes.
+/// * Path separators: both `/` and `\`.
+/// * Names reserved by Windows: `CON`, `PRN`, `AUX`, `NUL`, `COM1` to `COM9`
+///   and `LPT1` to `LPT9`. These are matched case-insensitively, and also
+///   with an extension or trailing spaces (e.g. `nul.txt` or `CON `), as
+///   Windows treats those as the device too.
+///
+/// The same checks are made on every platform, so a component that passes
+/// can be used anywhere. Use [`Builder::strict_names`] to have the builder
+/// check its prefix and suffix.
+///
+/// # Errors
+///
+/// Returns the first problem found as a [`NameError`].
+///
+/// # Examples
+///
+/// ```
+/// use std::ffi::OsStr;
+/// use tempfile::{validate_component, NameError};
+///
+/// assert_eq!(validate_component(OsStr::new("report-")), Ok(()));
+/// assert_eq!(validate_component(OsStr::new("../x")), Err(NameError::Separator));
+/// assert_eq!(validate_component(OsStr::new("aux.")), Err(NameError::Reserved));
+/// ```
+///
+/// [`Builder`]: struct.Builder.html
+/// [`Builder::strict_names`]: struct.Builder.html#method.strict_names
+/// [`NameError`]: enum.NameError.html
+pub fn validate_component(s: &OsStr) -> Result<(), NameError> {
+    let bytes = s.as_encoded_bytes();
+    if bytes.contains(&0) {
+        return Err(NameError::Nul);
+    }
+    if bytes.iter().any(|&b| b == b'/' || b == b'\\') {
+        return Err(NameError::Separator);
+    }
+    let stem = match bytes.iter().position(|&b| b == b'.') {
+        Some(dot) => &bytes[..dot],
+        None => bytes,
+    };
+    let end = stem.iter().rposition(|&b| b != b' ').map_or(0, |i| i + 1);
+    let stem = &stem[..end];
+    if RESERVED
+        .iter()
+        .any(|name| name.as_bytes().eq_ignore_ascii_case(stem))
+    {
+        return Err(NameError::Reserved);
+    }
+    Ok(())
+}
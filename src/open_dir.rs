@@ -0,0 +1,125 @@
+//! Temporary files inside an already open directory.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::IoResultExt;
+use crate::file;
+
+/// A named temporary file created relative to an open directory handle.
+///
+/// Created by [`Builder::tempfile_in_open_dir`]. The file is created with
+/// `openat` and deleted with `unlinkat` through the directory's file
+/// descriptor, so it stays inside that directory even if the directory is
+/// renamed, or a path leading to it is replaced, in the meantime.
+///
+/// Unix only.
+///
+/// [`Builder::tempfile_in_open_dir`]: struct.Builder.html#method.tempfile_in_open_dir
+pub struct OpenDirTempFile<'d> {
+    dir: &'d File,
+    name: PathBuf,
+    file: File,
+}
+
+impl<'d> OpenDirTempFile<'d> {
+    pub(crate) fn new(dir: &'d File, name: PathBuf, file: File) -> OpenDirTempFile<'d> {
+        OpenDirTempFile { dir, name, file }
+    }
+
+    /// Get the temporary file's name, relative to its directory.
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+
+    /// Get a mutable reference to the underlying file.
+    pub fn as_file_mut(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Close and remove the temporary file.
+    ///
+    /// Use this if you want to detect errors in deleting the file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be deleted, `Err` is returned.
+    pub fn close(self) -> io::Result<()> {
+        let (dir, name, file) = self.into_parts();
+        drop(file);
+        file::unlink_at(dir, &name).with_err_path(|| name)
+    }
+
+    /// Persist the temporary file under `new_name` in the same directory,
+    /// replacing any existing file, and return the open file.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be renamed, `Err` is returned and the temporary file
+    /// is deleted.
+    pub fn persist<P: AsRef<Path>>(self, new_name: P) -> io::Result<File> {
+        let (dir, name, file) = self.into_parts();
+        match file::rename_at(dir, &name, new_name.as_ref()) {
+            Ok(()) => Ok(file),
+            Err(e) => {
+                let _ = file::unlink_at(dir, &name);
+                Err(e).with_err_path(|| name)
+            }
+        }
+    }
+
+    /// Take the fields apart without running the destructor.
+    fn into_parts(self) -> (&'d File, PathBuf, File) {
+        let this = std::mem::ManuallyDrop::new(self);
+        // Safe: `this` is never used (or dropped) again, so each field is
+        // moved out exactly once.
+        unsafe {
+            (
+                this.dir,
+                std::ptr::read(&this.name),
+                std::ptr::read(&this.file),
+            )
+        }
+    }
+}
+
+impl<'d> Drop for OpenDirTempFile<'d> {
+    fn drop(&mut self) {
+        let _ = file::unlink_at(self.dir, &self.name);
+    }
+}
+
+impl<'d> fmt::Debug for OpenDirTempFile<'d> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OpenDirTempFile({:?})", self.name)
+    }
+}
+
+impl<'d> Read for OpenDirTempFile<'d> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf).with_err_path(|| &self.name)
+    }
+}
+
+impl<'d> Write for OpenDirTempFile<'d> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf).with_err_path(|| &self.name)
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush().with_err_path(|| &self.name)
+    }
+}
+
+impl<'d> Seek for OpenDirTempFile<'d> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos).with_err_path(|| &self.name)
+    }
+}
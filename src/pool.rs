@@ -0,0 +1,114 @@
+//! A pool of reusable named temporary files.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::NamedTempFile;
+
+/// A pool of pre-created named temporary files that can be handed out and
+/// returned for reuse.
+///
+/// Creating a temporary file involves generating a random name and possibly
+/// retrying on collisions. When many short-lived temporary files are needed,
+/// a pool amortizes that cost: files are created up front, handed out with
+/// [`get`], and truncated and kept for later when returned with [`put`].
+///
+/// Files still in the pool are deleted when the pool is dropped. Files that
+/// have been handed out behave like any other [`NamedTempFile`].
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{self, Write};
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// use tempfile::TempFilePool;
+///
+/// let mut pool = TempFilePool::new(4)?;
+/// for i in 0..100 {
+///     let mut file = pool.get()?;
+///     writeln!(file, "job {}", i)?;
+///     pool.put(file)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`get`]: #method.get
+/// [`put`]: #method.put
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+#[derive(Debug)]
+pub struct TempFilePool {
+    dir: PathBuf,
+    capacity: usize,
+    free: Vec<NamedTempFile>,
+}
+
+impl TempFilePool {
+    /// Create a pool of `capacity` temporary files in `std::env::temp_dir()`.
+    ///
+    /// # Errors
+    ///
+    /// If any of the files can't be created, `Err` is returned and the files
+    /// created so far are deleted.
+    pub fn new(capacity: usize) -> io::Result<TempFilePool> {
+        TempFilePool::new_in(crate::default_temp_dir(), capacity)
+    }
+
+    /// Create a pool of `capacity` temporary files in `dir`.
+    ///
+    /// # Errors
+    ///
+    /// If any of the files can't be created, `Err` is returned and the files
+    /// created so far are deleted.
+    pub fn new_in<P: AsRef<Path>>(dir: P, capacity: usize) -> io::Result<TempFilePool> {
+        let dir = dir.as_ref().to_path_buf();
+        let free = (0..capacity)
+            .map(|_| NamedTempFile::new_in(&dir))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(TempFilePool {
+            dir,
+            capacity,
+            free,
+        })
+    }
+
+    /// Take a temporary file from the pool.
+    ///
+    /// The file is empty and positioned at the start. If the pool has run
+    /// dry, a new file is created in the pool's directory.
+    pub fn get(&mut self) -> io::Result<NamedTempFile> {
+        match self.free.pop() {
+            Some(file) => Ok(file),
+            None => NamedTempFile::new_in(&self.dir),
+        }
+    }
+
+    /// Return a temporary file to the pool.
+    ///
+    /// The file is truncated and rewound with [`NamedTempFile::reset`]. If the
+    /// pool is already full, the file is deleted instead. Handing back a file
+    /// that didn't come from this pool is allowed.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be reset, `Err` is returned and the file is deleted.
+    ///
+    /// [`NamedTempFile::reset`]: struct.NamedTempFile.html#method.reset
+    pub fn put(&mut self, mut file: NamedTempFile) -> io::Result<()> {
+        if self.free.len() < self.capacity {
+            file.reset()?;
+            self.free.push(file);
+        }
+        Ok(())
+    }
+
+    /// The number of files currently available in the pool.
+    pub fn available(&self) -> usize {
+        self.free.len()
+    }
+}
@@ -0,0 +1,221 @@
+//! Temporary directories with a limit on the data written to them.
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::{NamedTempFile, TempDir};
+
+/// A temporary directory that limits how much data can be written to the
+/// temporary files created in it.
+///
+/// Created by [`TempDir::with_quota`]. Files are created with [`new_file`],
+/// and every byte written through the returned [`QuotaFile`]s counts against
+/// a quota shared by all of them. Once it's used up, writes fail with an
+/// [`io::ErrorKind::StorageFull`] error, and so does creating more files.
+///
+/// The quota counts bytes written, not the space the files take up: data
+/// that's overwritten counts twice, and deleting a file or truncating it
+/// doesn't give anything back. Anything written to the directory by other
+/// means isn't counted at all.
+///
+/// The directory and everything in it is deleted when the `QuotaTempDir` is
+/// dropped, like a [`TempDir`].
+///
+/// [`TempDir::with_quota`]: struct.TempDir.html#method.with_quota
+/// [`new_file`]: #method.new_file
+/// [`QuotaFile`]: struct.QuotaFile.html
+/// [`TempDir`]: struct.TempDir.html
+/// [`io::ErrorKind::StorageFull`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.StorageFull
+pub struct QuotaTempDir {
+    dir: TempDir,
+    quota: Arc<Quota>,
+}
+
+struct Quota {
+    max_bytes: u64,
+    used: AtomicU64,
+}
+
+impl Quota {
+    /// Reserve up to `len` bytes, returning how many could be reserved.
+    fn reserve(&self, len: u64) -> u64 {
+        let mut reserved = 0;
+        let _ = self
+            .used
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                reserved = len.min(self.max_bytes.saturating_sub(used));
+                Some(used + reserved)
+            });
+        reserved
+    }
+
+    fn release(&self, len: u64) {
+        self.used.fetch_sub(len, Ordering::SeqCst);
+    }
+
+    fn remaining(&self) -> u64 {
+        self.max_bytes
+            .saturating_sub(self.used.load(Ordering::SeqCst))
+    }
+}
+
+fn quota_exceeded() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::StorageFull,
+        "temporary directory quota exceeded",
+    )
+}
+
+impl QuotaTempDir {
+    pub(crate) fn new(dir: TempDir, max_bytes: u64) -> QuotaTempDir {
+        QuotaTempDir {
+            dir,
+            quota: Arc::new(Quota {
+                max_bytes,
+                used: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Create a new temporary file in the directory.
+    ///
+    /// # Errors
+    ///
+    /// If the quota is used up, an [`io::ErrorKind::StorageFull`] error is
+    /// returned. If the file can't be created, `Err` is returned as well.
+    ///
+    /// [`io::ErrorKind::StorageFull`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.StorageFull
+    pub fn new_file(&self) -> io::Result<QuotaFile> {
+        if self.quota.remaining() == 0 {
+            return Err(quota_exceeded());
+        }
+        Ok(QuotaFile {
+            file: NamedTempFile::new_in(self.dir.path())?,
+            quota: self.quota.clone(),
+        })
+    }
+
+    /// Accesses the path to the directory.
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The number of bytes written to the directory's files so far.
+    pub fn used(&self) -> u64 {
+        self.quota.used.load(Ordering::SeqCst)
+    }
+
+    /// The number of bytes that can still be written.
+    pub fn remaining(&self) -> u64 {
+        self.quota.remaining()
+    }
+
+    /// Close and remove the directory, reporting any error. See
+    /// [`TempDir::close`].
+    ///
+    /// [`TempDir::close`]: struct.TempDir.html#method.close
+    pub fn close(self) -> io::Result<()> {
+        self.dir.close()
+    }
+}
+
+impl AsRef<Path> for QuotaTempDir {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
+
+impl fmt::Debug for QuotaTempDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuotaTempDir")
+            .field("path", &self.path())
+            .field("max_bytes", &self.quota.max_bytes)
+            .field("used", &self.used())
+            .finish()
+    }
+}
+
+/// A temporary file whose writes count against the quota of a
+/// [`QuotaTempDir`].
+///
+/// A write that would exceed the quota writes as much as still fits; once
+/// nothing fits anymore, writes fail with an [`io::ErrorKind::StorageFull`]
+/// error. Reading and seeking are unaffected.
+///
+/// [`QuotaTempDir`]: struct.QuotaTempDir.html
+/// [`io::ErrorKind::StorageFull`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.StorageFull
+pub struct QuotaFile {
+    file: NamedTempFile,
+    quota: Arc<Quota>,
+}
+
+impl QuotaFile {
+    /// Get the temporary file's path.
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Get a reference to the underlying temporary file.
+    ///
+    /// Writes made through it directly aren't counted against the quota.
+    pub fn as_named_temp_file(&self) -> &NamedTempFile {
+        &self.file
+    }
+
+    /// Stop counting writes against the quota and return the underlying
+    /// temporary file.
+    pub fn into_inner(self) -> NamedTempFile {
+        self.file
+    }
+}
+
+impl fmt::Debug for QuotaFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuotaFile")
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+impl Write for QuotaFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A `usize` always fits in a `u64` on supported platforms, and the
+        // reservation is never larger than `buf.len()`.
+        let reserved = self.quota.reserve(buf.len() as u64);
+        if reserved == 0 {
+            return Err(quota_exceeded());
+        }
+        match self.file.write(&buf[..reserved as usize]) {
+            Ok(written) => {
+                self.quota.release(reserved - written as u64);
+                Ok(written)
+            }
+            Err(e) => {
+                self.quota.release(reserved);
+                Err(e)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Read for QuotaFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for QuotaFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
@@ -0,0 +1,152 @@
+//! Scratch space on a private, RAM-backed filesystem.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::error::IoResultExt;
+use crate::file;
+use crate::TempDir;
+
+const SHM_DIR: &str = "/dev/shm";
+
+/// Create a temporary directory backed by a freshly mounted `tmpfs`.
+///
+/// A new directory is created in [`std::env::temp_dir()`] and a `tmpfs` of at
+/// most `size_bytes` bytes (rounded up to whole pages) is mounted on it. Files
+/// created inside, e.g. with [`Builder::tempfile_in`], never touch a disk.
+/// The filesystem is unmounted and the directory removed when the returned
+/// [`RamScratch`] is dropped.
+///
+/// # Privileges
+///
+/// Mounting requires `CAP_SYS_ADMIN`, i.e. running as root or inside a user
+/// and mount namespace where the caller has that capability. If mounting is
+/// not permitted, this falls back to a plain temporary directory in
+/// `/dev/shm`, which is a `tmpfs` on virtually every Linux system but is
+/// shared with other processes and not subject to `size_bytes`. Use
+/// [`RamScratch::is_mounted`] to tell the two apart.
+///
+/// # Errors
+///
+/// If `size_bytes` is zero (which `tmpfs` treats as "unlimited"), an error of
+/// kind `InvalidInput` is returned. If the directory can't be created, the
+/// mount fails for any reason other than missing privileges, or the fallback
+/// isn't available, `Err` is returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::io::{self, Write};
+/// use tempfile::Builder;
+///
+/// # fn run() -> Result<(), io::Error> {
+/// let scratch = tempfile::ram_scratch(64 * 1024 * 1024)?;
+/// let mut file = Builder::new().tempfile_in(scratch.path())?;
+/// writeln!(file, "never hits the disk")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Builder::tempfile_in`]: struct.Builder.html#method.tempfile_in
+/// [`RamScratch`]: struct.RamScratch.html
+/// [`RamScratch::is_mounted`]: struct.RamScratch.html#method.is_mounted
+pub fn ram_scratch(size_bytes: u64) -> io::Result<RamScratch> {
+    if size_bytes == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "ram scratch size must be non-zero",
+        ));
+    }
+    let dir = TempDir::new()?;
+    match file::mount_tmpfs(dir.path(), size_bytes) {
+        Ok(()) => Ok(RamScratch {
+            dir: Some(dir),
+            mounted: true,
+        }),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            drop(dir);
+            let dir = TempDir::new_in(SHM_DIR).map_err(|_| e)?;
+            Ok(RamScratch {
+                dir: Some(dir),
+                mounted: false,
+            })
+        }
+        Err(e) => Err(e).with_err_path(|| dir.path()),
+    }
+}
+
+/// A temporary directory on a RAM-backed filesystem.
+///
+/// Created by [`ram_scratch`].
+///
+/// [`ram_scratch`]: fn.ram_scratch.html
+pub struct RamScratch {
+    // Always `Some` until `close` takes it.
+    dir: Option<TempDir>,
+    mounted: bool,
+}
+
+impl RamScratch {
+    /// Get the path of the scratch directory.
+    pub fn path(&self) -> &Path {
+        self.dir.as_ref().unwrap().path()
+    }
+
+    /// Whether a private `tmpfs` is mounted on the directory, as opposed to
+    /// having fallen back to a directory in `/dev/shm`.
+    pub fn is_mounted(&self) -> bool {
+        self.mounted
+    }
+
+    /// Unmount the filesystem and remove the directory.
+    ///
+    /// Use this if you want to detect errors in cleaning up.
+    ///
+    /// # Errors
+    ///
+    /// If the filesystem can't be unmounted or the directory can't be
+    /// deleted, `Err` is returned. If unmounting fails, nothing is removed and
+    /// unmounting is tried once more on drop.
+    pub fn close(mut self) -> io::Result<()> {
+        self.unmount()?;
+        self.dir.take().unwrap().close()
+    }
+
+    fn unmount(&mut self) -> io::Result<()> {
+        if !self.mounted {
+            return Ok(());
+        }
+        let path = self.path();
+        file::unmount(path).with_err_path(|| path)?;
+        self.mounted = false;
+        Ok(())
+    }
+}
+
+impl Drop for RamScratch {
+    fn drop(&mut self) {
+        if self.unmount().is_err() {
+            // Don't delete the contents of a filesystem that's still mounted;
+            // the mount point couldn't be removed anyway.
+            if let Some(dir) = self.dir.take() {
+                dir.into_path();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for RamScratch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RamScratch")
+            .field("path", &self.dir.as_ref().map(TempDir::path))
+            .field("mounted", &self.mounted)
+            .finish()
+    }
+}
+
+impl AsRef<Path> for RamScratch {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
@@ -0,0 +1,69 @@
+//! Temporary names claimed ahead of use.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::NamedTempFile;
+
+/// A claim on a temporary file name, created by [`Builder::reserve`].
+///
+/// The name is held by an empty placeholder file, created exclusively like
+/// any other temporary file, so nobody else can take it (within this crate's
+/// and `O_EXCL`'s guarantees). Turn the reservation into the actual temporary
+/// file with [`into_temp_file`] once you're ready, or give up the name with
+/// [`release`]. If the reservation is dropped instead, the placeholder is
+/// deleted.
+///
+/// [`Builder::reserve`]: struct.Builder.html#method.reserve
+/// [`into_temp_file`]: #method.into_temp_file
+/// [`release`]: #method.release
+pub struct NameReservation {
+    file: NamedTempFile,
+}
+
+impl NameReservation {
+    pub(crate) fn new(file: NamedTempFile) -> NameReservation {
+        NameReservation { file }
+    }
+
+    /// Get the reserved path.
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    /// Turn the reservation into a temporary file at the reserved path.
+    ///
+    /// The placeholder simply becomes the file, so this can't fail and the
+    /// name is never unclaimed in between. The file is empty, open for
+    /// reading and writing, and has all the options of the [`Builder`] that
+    /// made the reservation.
+    ///
+    /// [`Builder`]: struct.Builder.html
+    pub fn into_temp_file(self) -> NamedTempFile {
+        self.file
+    }
+
+    /// Give up the reservation, deleting the placeholder.
+    ///
+    /// Use this if you want to detect errors in deleting it.
+    ///
+    /// # Errors
+    ///
+    /// If the placeholder cannot be deleted, `Err` is returned.
+    pub fn release(self) -> io::Result<()> {
+        self.file.close()
+    }
+}
+
+impl fmt::Debug for NameReservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NameReservation({:?})", self.path())
+    }
+}
+
+impl AsRef<Path> for NameReservation {
+    fn as_ref(&self) -> &Path {
+        self.path()
+    }
+}
@@ -0,0 +1,59 @@
+//! Reading a temporary file from the start without losing the cursor.
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::NamedTempFile;
+
+/// A reader over a temporary file's contents from the start, which puts the
+/// file's cursor back where it was when dropped.
+///
+/// Created by [`NamedTempFile::rewind_reader`]. The reader mutably borrows
+/// the `NamedTempFile` for as long as it's alive, so the file can't be
+/// written to (or read through any other way) until the reader is dropped or
+/// [restored]. Dropping it ignores errors restoring the position; use
+/// [`RewindReader::restore`] to check for them.
+///
+/// [`NamedTempFile::rewind_reader`]: struct.NamedTempFile.html#method.rewind_reader
+/// [restored]: #method.restore
+/// [`RewindReader::restore`]: #method.restore
+pub struct RewindReader<'a> {
+    file: &'a mut NamedTempFile,
+    position: u64,
+}
+
+impl<'a> RewindReader<'a> {
+    pub(crate) fn new(file: &'a mut NamedTempFile) -> io::Result<RewindReader<'a>> {
+        let position = file.stream_position()?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(RewindReader { file, position })
+    }
+
+    /// Put the file's cursor back now, reporting any error.
+    pub fn restore(self) -> io::Result<()> {
+        let mut this = std::mem::ManuallyDrop::new(self);
+        let position = this.position;
+        this.file.seek(SeekFrom::Start(position)).map(|_| ())
+    }
+}
+
+impl Read for RewindReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl fmt::Debug for RewindReader<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RewindReader")
+            .field("file", &*self.file)
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl Drop for RewindReader<'_> {
+    fn drop(&mut self) {
+        let _ = self.file.seek(SeekFrom::Start(self.position));
+    }
+}
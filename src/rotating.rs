@@ -0,0 +1,120 @@
+//! A fixed number of temporary files, recycled like a ring buffer.
+
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::NamedTempFile;
+
+/// A bounded set of named temporary files that are recycled oldest-first,
+/// e.g. for a rolling debug log.
+///
+/// Each call to [`next`] hands out an empty file. The first `count` calls
+/// create new files; after that, the oldest file is truncated and rewound
+/// (with [`NamedTempFile::reset`]) and handed out again as the newest one,
+/// keeping its name. So at most `count` files exist at any time, holding the
+/// output of the last `count` calls.
+///
+/// The files belong to the rotator: [`next`] only lends them out, so they
+/// can't outlive it, and they're all deleted when it's dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{self, Write};
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// use tempfile::RotatingTempFiles;
+///
+/// let mut logs = RotatingTempFiles::new(3);
+/// for i in 0..10 {
+///     writeln!(logs.next()?, "run {}", i)?;
+/// }
+/// // Only runs 7, 8 and 9 are kept.
+/// assert_eq!(logs.len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`next`]: #method.next
+/// [`NamedTempFile::reset`]: struct.NamedTempFile.html#method.reset
+#[derive(Debug)]
+pub struct RotatingTempFiles {
+    dir: PathBuf,
+    count: usize,
+    // Oldest first.
+    files: VecDeque<NamedTempFile>,
+}
+
+impl RotatingTempFiles {
+    /// Create a rotator of up to `count` temporary files in
+    /// `std::env::temp_dir()`.
+    ///
+    /// No files are created until [`next`] is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is zero.
+    ///
+    /// [`next`]: #method.next
+    pub fn new(count: usize) -> RotatingTempFiles {
+        RotatingTempFiles::new_in(crate::default_temp_dir(), count)
+    }
+
+    /// Create a rotator of up to `count` temporary files in `dir`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` is zero.
+    pub fn new_in<P: AsRef<Path>>(dir: P, count: usize) -> RotatingTempFiles {
+        assert!(
+            count > 0,
+            "RotatingTempFiles needs room for at least one file"
+        );
+        RotatingTempFiles {
+            dir: dir.as_ref().to_path_buf(),
+            count,
+            files: VecDeque::with_capacity(count),
+        }
+    }
+
+    /// Get an empty temporary file, recycling the oldest one if `count`
+    /// files already exist.
+    ///
+    /// # Errors
+    ///
+    /// If a new file can't be created or the oldest one can't be reset,
+    /// `Err` is returned. A file that couldn't be reset is deleted.
+    // Not an `Iterator`: each file is only lent out until the next call.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> io::Result<&mut NamedTempFile> {
+        let file = if self.files.len() < self.count {
+            NamedTempFile::new_in(&self.dir)?
+        } else {
+            let mut oldest = self.files.pop_front().expect("count is nonzero");
+            oldest.reset()?;
+            oldest
+        };
+        self.files.push_back(file);
+        Ok(self.files.back_mut().expect("just pushed"))
+    }
+
+    /// The files handed out so far that still hold data, oldest first.
+    pub fn files(&self) -> impl Iterator<Item = &NamedTempFile> {
+        self.files.iter()
+    }
+
+    /// The number of files that currently exist.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files have been handed out yet.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
@@ -0,0 +1,146 @@
+//! Cleaning up groups of temporary files together.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::IoResultExt;
+use crate::NamedTempFile;
+
+/// A guard that deletes all of the temporary files created through it when
+/// it's dropped.
+///
+/// Files created with [`TempScope::tempfile`] are normal [`NamedTempFile`]s
+/// that can be moved and passed around freely, except that they don't own
+/// their path: dropping or closing one of them leaves the file alone.
+/// Instead, the scope remembers every path it handed out and deletes them
+/// all at once when it's dropped or [closed], no matter where the handles
+/// ended up or whether they're still open. This centralizes cleanup for
+/// flows where individual handles are hard to keep track of.
+///
+/// Handles that outlive the scope keep working on Unix (on a file that no
+/// longer has a name); on Windows, the file disappears once they're closed.
+/// A file that was persisted is no longer at its temporary path, so the scope
+/// has nothing to delete for it.
+///
+/// The scope can be shared between threads by reference.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::{self, Write};
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// use tempfile::TempScope;
+///
+/// let scope = TempScope::new();
+/// let mut files = Vec::new();
+/// for i in 0..10 {
+///     let mut file = scope.tempfile()?;
+///     writeln!(file, "part {}", i)?;
+///     files.push(file);
+/// }
+/// let path = files[0].path().to_path_buf();
+/// drop(files);
+/// assert!(path.exists());
+///
+/// drop(scope);
+/// assert!(!path.exists());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`TempScope::tempfile`]: #method.tempfile
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+/// [closed]: #method.close
+#[derive(Default)]
+pub struct TempScope {
+    paths: Mutex<Vec<PathBuf>>,
+}
+
+impl TempScope {
+    /// Create a new, empty scope.
+    pub fn new() -> TempScope {
+        TempScope::default()
+    }
+
+    /// Create a new temporary file in `std::env::temp_dir()` that is deleted
+    /// along with the scope.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created, `Err` is returned.
+    pub fn tempfile(&self) -> io::Result<NamedTempFile> {
+        self.tempfile_in(crate::default_temp_dir())
+    }
+
+    /// Create a new temporary file in `dir` that is deleted along with the
+    /// scope.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be created, `Err` is returned.
+    pub fn tempfile_in<P: AsRef<Path>>(&self, dir: P) -> io::Result<NamedTempFile> {
+        let file = NamedTempFile::new_in(dir)?;
+        self.lock().push(file.path().to_path_buf());
+        Ok(file.disarm())
+    }
+
+    /// The number of files created through the scope so far.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether no files have been created through the scope yet.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Delete all of the scope's files now, reporting the first error.
+    ///
+    /// Files that no longer exist are skipped. All files are attempted even
+    /// if deleting one of them fails.
+    pub fn close(self) -> io::Result<()> {
+        self.remove_all()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<PathBuf>> {
+        // The list stays consistent even if a thread panicked while holding
+        // the lock.
+        self.paths.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn remove_all(&self) -> io::Result<()> {
+        let paths = mem::take(&mut *self.lock());
+        let mut result = Ok(());
+        for path in paths {
+            match fs::remove_file(&path) {
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) if result.is_ok() => result = Err(e).with_err_path(|| path),
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Debug for TempScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TempScope")
+            .field("paths", &*self.lock())
+            .finish()
+    }
+}
+
+impl Drop for TempScope {
+    fn drop(&mut self) {
+        let _ = self.remove_all();
+    }
+}
@@ -0,0 +1,315 @@
+//! Deleting temporary files when the process is killed by a signal.
+//!
+//! Once installed, every named temporary file that's created is recorded in a
+//! fixed-size table of `CString` pointers, which the signal handler walks to
+//! `unlink` whatever is still there. Both the handler and the owning
+//! `TempPath` take entries out with an atomic swap, so each pointer has
+//! exactly one owner: the handler never frees (it can't, safely), and a
+//! `TempPath` only frees an entry it got back.
+
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use libc::{c_char, c_int, c_void, siginfo_t};
+
+type SigAction = extern "C" fn(c_int, *mut siginfo_t, *mut c_void);
+
+/// How many temporary files can be registered at once. Files created while
+/// the table is full aren't cleaned up on signals.
+const CAPACITY: usize = 1024;
+
+static SLOTS: [AtomicPtr<c_char>; CAPACITY] = [const { AtomicPtr::new(ptr::null_mut()) }; CAPACITY];
+
+/// Set once a handler has been installed, after which new files register.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// A signal that can trigger cleanup, for [`cleanup_on_signals`].
+///
+/// [`cleanup_on_signals`]: fn.cleanup_on_signals.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Signal {
+    /// `SIGINT`, sent by Ctrl-C in a terminal.
+    Interrupt,
+    /// `SIGTERM`, the default signal of `kill`, also used by service managers
+    /// to stop a process.
+    Terminate,
+    /// `SIGHUP`, sent when the controlling terminal goes away.
+    Hangup,
+}
+
+const SIGNALS: [Signal; 3] = [Signal::Interrupt, Signal::Terminate, Signal::Hangup];
+
+impl Signal {
+    fn number(self) -> c_int {
+        match self {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Hangup => libc::SIGHUP,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The state of each signal's handler, indexed by `Signal::index`. The
+/// previous disposition is saved before ours is installed, and never changes
+/// afterwards.
+struct Handler {
+    installed: AtomicBool,
+    chain: AtomicBool,
+    previous_action: AtomicUsize,
+    previous_flags: AtomicUsize,
+}
+
+static HANDLERS: [Handler; 3] = [const {
+    Handler {
+        installed: AtomicBool::new(false),
+        chain: AtomicBool::new(false),
+        previous_action: AtomicUsize::new(0),
+        previous_flags: AtomicUsize::new(0),
+    }
+}; 3];
+
+/// Serializes installation; the handler itself never takes it.
+static INSTALL: Mutex<()> = Mutex::new(());
+
+/// Delete named temporary files if the process receives one of `signals`,
+/// replacing any handler the application had installed for them.
+///
+/// After cleanup, the signal's default action is restored and the signal is
+/// raised again, so the process is terminated just like it would have been
+/// without a handler (including the exit status seen by the parent). Use
+/// [`cleanup_on_signals_chained`] to keep an existing handler running
+/// instead.
+///
+/// Only files created after the first call are covered: from then on, each
+/// [`NamedTempFile`] (and [`TempPath`]) is recorded until it's deleted,
+/// persisted, kept or otherwise disarmed. Temporary directories are not
+/// covered, as removing a directory tree can't be done from a signal handler.
+/// Up to 1024 files are tracked at a time; files created beyond that are
+/// simply not cleaned up on signals. Signals that are ignored when this is
+/// called (e.g. `SIGHUP` under `nohup`) are left ignored, with no cleanup,
+/// so that they still don't terminate the process. Calling this again adds
+/// signals, or switches signals installed by [`cleanup_on_signals_chained`]
+/// to replacing the previous handler.
+///
+/// # Signal safety
+///
+/// Signal handlers may only do async-signal-safe work, so the handler does
+/// nothing but swap entries out of a preallocated table with atomic
+/// operations and `unlink(2)` each path. It doesn't allocate, lock, log or
+/// free memory (the paths of unlinked files are leaked, on the assumption
+/// that the process is about to exit). It preserves `errno` where the
+/// platform exposes it, and it's reentrant: if several signals arrive at
+/// once, on one thread or several, each file is unlinked by exactly one of
+/// them.
+///
+/// This is best-effort cleanup, not a guarantee: `SIGKILL` can't be caught,
+/// and a file that is being persisted when the signal arrives may be deleted
+/// under its old name or left behind under its new one. The paths are
+/// deleted as recorded, so if another file was put at a temporary file's
+/// path in the meantime (e.g. after [`NamedTempFile::into_parts`] and a
+/// rename), that file is deleted instead.
+///
+/// # Errors
+///
+/// If installing a handler fails, `Err` is returned; signals installed
+/// before the failing one stay installed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use tempfile::Signal;
+///
+/// # fn main() -> std::io::Result<()> {
+/// tempfile::cleanup_on_signals(&[Signal::Interrupt, Signal::Terminate])?;
+/// let file = tempfile::NamedTempFile::new()?;
+/// // Ctrl-C now deletes `file` before the process exits.
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`cleanup_on_signals_chained`]: fn.cleanup_on_signals_chained.html
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+/// [`TempPath`]: struct.TempPath.html
+/// [`NamedTempFile::into_parts`]: struct.NamedTempFile.html#method.into_parts
+pub fn cleanup_on_signals(signals: &[Signal]) -> io::Result<()> {
+    install(signals, false)
+}
+
+/// Like [`cleanup_on_signals`], but runs the handler that was previously
+/// installed for each signal after the cleanup, instead of replacing it.
+///
+/// If there was no previous handler, the default action runs, as with
+/// [`cleanup_on_signals`]; if the signal was ignored, it stays ignored after
+/// the cleanup. Otherwise, the previous handler is called with the same
+/// arguments, so an application's own shutdown logic keeps working. Note that
+/// the files are deleted before that handler runs, even if it decides not to
+/// exit: the signal is taken as the end of the temporary files' lives either
+/// way. Handlers installed by the application after this call replace the
+/// cleanup handler rather than being chained to.
+///
+/// Calling this for a signal already installed by [`cleanup_on_signals`]
+/// switches it to chaining.
+///
+/// # Errors
+///
+/// If installing a handler fails, `Err` is returned; signals installed
+/// before the failing one stay installed.
+///
+/// [`cleanup_on_signals`]: fn.cleanup_on_signals.html
+pub fn cleanup_on_signals_chained(signals: &[Signal]) -> io::Result<()> {
+    install(signals, true)
+}
+
+fn install(signals: &[Signal], chain: bool) -> io::Result<()> {
+    let _guard = INSTALL.lock().unwrap_or_else(|e| e.into_inner());
+    ENABLED.store(true, Ordering::SeqCst);
+    for &signal in signals {
+        let handler = &HANDLERS[signal.index()];
+        handler.chain.store(chain, Ordering::SeqCst);
+        if handler.installed.load(Ordering::SeqCst) {
+            continue;
+        }
+        unsafe {
+            let mut previous: libc::sigaction = mem::zeroed();
+            if libc::sigaction(signal.number(), ptr::null(), &mut previous) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // An ignored signal doesn't end the process (think `nohup`), so
+            // there's nothing to clean up after, and installing a handler
+            // would make it fatal.
+            if previous.sa_sigaction == libc::SIG_IGN {
+                continue;
+            }
+            handler
+                .previous_action
+                .store(previous.sa_sigaction, Ordering::SeqCst);
+            handler
+                .previous_flags
+                .store(previous.sa_flags as usize, Ordering::SeqCst);
+
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handle as SigAction as usize;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(signal.number(), &action, ptr::null_mut()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        handler.installed.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+/// Record `path` for deletion on signals, if a handler is installed,
+/// returning the slot to pass to `unregister`.
+pub(crate) fn register(path: &Path) -> Option<usize> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?.into_raw();
+    for (i, slot) in SLOTS.iter().enumerate() {
+        if slot
+            .compare_exchange(ptr::null_mut(), path, Ordering::SeqCst, Ordering::Relaxed)
+            .is_ok()
+        {
+            return Some(i);
+        }
+    }
+    // The table is full.
+    drop(unsafe { CString::from_raw(path) });
+    None
+}
+
+/// Forget the path registered in `slot`, unless a signal handler has already
+/// taken it.
+pub(crate) fn unregister(slot: usize) {
+    let path = SLOTS[slot].swap(ptr::null_mut(), Ordering::SeqCst);
+    if !path.is_null() {
+        drop(unsafe { CString::from_raw(path) });
+    }
+}
+
+extern "C" fn handle(signum: c_int, info: *mut siginfo_t, context: *mut c_void) {
+    let errno = errno_location();
+    let saved_errno = errno.map(|errno| unsafe { *errno });
+
+    for slot in SLOTS.iter() {
+        let path = slot.swap(ptr::null_mut(), Ordering::SeqCst);
+        if !path.is_null() {
+            unsafe { libc::unlink(path) };
+        }
+    }
+
+    let handler = SIGNALS
+        .iter()
+        .find(|signal| signal.number() == signum)
+        .map(|signal| &HANDLERS[signal.index()]);
+    if let Some(handler) = handler {
+        let previous = handler.previous_action.load(Ordering::SeqCst);
+        if handler.chain.load(Ordering::SeqCst) && previous != libc::SIG_DFL {
+            if previous != libc::SIG_IGN {
+                let flags = handler.previous_flags.load(Ordering::SeqCst) as c_int;
+                unsafe {
+                    if flags & libc::SA_SIGINFO != 0 {
+                        let f: SigAction = mem::transmute(previous);
+                        f(signum, info, context);
+                    } else {
+                        let f: extern "C" fn(c_int) = mem::transmute(previous);
+                        f(signum);
+                    }
+                }
+            }
+        } else {
+            // Die the way the signal would have killed us. It's blocked while
+            // this handler runs, so it's delivered right after we return.
+            unsafe {
+                libc::signal(signum, libc::SIG_DFL);
+                libc::raise(signum);
+            }
+        }
+    }
+
+    if let (Some(errno), Some(saved)) = (errno, saved_errno) {
+        unsafe { *errno = saved };
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "redox"))]
+fn errno_location() -> Option<*mut c_int> {
+    Some(unsafe { libc::__errno_location() })
+}
+
+#[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+fn errno_location() -> Option<*mut c_int> {
+    Some(unsafe { libc::__errno() })
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+fn errno_location() -> Option<*mut c_int> {
+    Some(unsafe { libc::__error() })
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "redox",
+    target_os = "android",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+fn errno_location() -> Option<*mut c_int> {
+    None
+}
@@ -0,0 +1,128 @@
+//! Enforcing that only one instance of a program runs at a time.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::IoResultExt;
+use crate::file;
+
+/// Make sure only one instance of a program holds the lock file `name`.
+///
+/// The file is created in [`std::env::temp_dir()`] if it doesn't exist yet,
+/// and an exclusive advisory lock is taken on it (see
+/// [`NamedTempFile::try_lock_exclusive`]). If another process (or another
+/// guard in this one) already holds the lock, `Ok(None)` is returned right
+/// away. Otherwise, the returned guard keeps the lock until it's dropped, at
+/// which point the file is deleted and the lock released.
+///
+/// # Crashes
+///
+/// If the program crashes or is killed, the lock is released by the
+/// operating system when the process exits, so there are no stale locks to
+/// clean up: the next instance finds the leftover file unlocked and takes it
+/// over. Only the file itself is left behind until then.
+///
+/// # Security
+///
+/// The name is fixed and the temporary directory is usually shared between
+/// all users, so anyone can create the file first and lock out every
+/// instance. Include something user-specific in `name` (and prefer a private
+/// directory) if that matters.
+///
+/// # Errors
+///
+/// If `name` isn't a plain file name, an error of kind `InvalidInput` is
+/// returned. If the file can't be opened or locked (e.g., because the
+/// platform doesn't support locking), `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// # use std::io;
+/// # fn run() -> Result<(), io::Error> {
+/// let guard = match tempfile::single_instance("myapp.lock")? {
+///     Some(guard) => guard,
+///     None => {
+///         eprintln!("myapp is already running");
+///         return Ok(());
+///     }
+/// };
+/// assert!(tempfile::single_instance("myapp.lock")?.is_none());
+/// drop(guard);
+/// # Ok(())
+/// # }
+/// # run().unwrap();
+/// ```
+///
+/// [`std::env::temp_dir()`]: https://doc.rust-lang.org/std/env/fn.temp_dir.html
+/// [`NamedTempFile::try_lock_exclusive`]: struct.NamedTempFile.html#method.try_lock_exclusive
+pub fn single_instance(name: &str) -> io::Result<Option<SingleInstanceGuard>> {
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "lock file name must be a single path component",
+        ));
+    }
+    let path = crate::default_temp_dir().join(name);
+    for _ in 0..crate::NUM_RETRIES {
+        let mut open_options = OpenOptions::new();
+        open_options.read(true).write(true).create(true);
+        let file = file::share_all(&mut open_options)
+            .open(&path)
+            .with_err_path(|| &path)?;
+        if !file::lock(&file, true, false).with_err_path(|| &path)? {
+            return Ok(None);
+        }
+        // The previous holder may have deleted the file between us opening
+        // and locking it, in which case someone else can create and lock a
+        // new one. Only a lock on the file that's still at `path` counts.
+        if file::is_same_file(&file, &path).with_err_path(|| &path)? {
+            return Ok(Some(SingleInstanceGuard { path, file }));
+        }
+    }
+    Err(io::Error::other(
+        "lock file kept being replaced while locking it",
+    ))
+    .with_err_path(|| path)
+}
+
+/// Proof that this is the only instance holding a lock file, returned by
+/// [`single_instance`].
+///
+/// Dropping the guard deletes the lock file and releases the lock.
+///
+/// [`single_instance`]: fn.single_instance.html
+pub struct SingleInstanceGuard {
+    path: PathBuf,
+    file: File,
+}
+
+impl SingleInstanceGuard {
+    /// Get the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Debug for SingleInstanceGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SingleInstanceGuard")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl Drop for SingleInstanceGuard {
+    fn drop(&mut self) {
+        // Delete the file while still holding the lock; the lock is released
+        // when `file` is closed right after.
+        let _ = fs::remove_file(&self.path);
+        let _ = file::unlock(&self.file);
+    }
+}
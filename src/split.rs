@@ -0,0 +1,131 @@
+//! Separate writing and reading halves of a named temporary file.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::IoResultExt;
+use crate::TempPath;
+
+/// The writing half of a [`NamedTempFile`], as returned by
+/// [`NamedTempFile::split`].
+///
+/// This is the original file handle, so its cursor is wherever the
+/// `NamedTempFile`'s was, and it keeps the options the file was opened with
+/// (e.g. [`Builder::append`]). It implements [`Write`] and [`Seek`], and its
+/// cursor is independent of the matching [`TempReader`]'s.
+///
+/// The file is deleted once both halves have been dropped.
+///
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+/// [`NamedTempFile::split`]: struct.NamedTempFile.html#method.split
+/// [`Builder::append`]: struct.Builder.html#method.append
+/// [`TempReader`]: struct.TempReader.html
+/// [`Write`]: https://doc.rust-lang.org/std/io/trait.Write.html
+/// [`Seek`]: https://doc.rust-lang.org/std/io/trait.Seek.html
+pub struct TempWriter {
+    file: File,
+    path: Arc<TempPath>,
+}
+
+/// The reading half of a [`NamedTempFile`], as returned by
+/// [`NamedTempFile::split`].
+///
+/// This is a second, independently opened handle to the same file, starting
+/// at offset 0. It implements [`Read`] and [`Seek`]; reading and seeking
+/// don't move the matching [`TempWriter`]'s cursor, and data the writer has
+/// written is visible to it right away (there's no buffering in between).
+///
+/// The file is deleted once both halves have been dropped.
+///
+/// [`NamedTempFile`]: struct.NamedTempFile.html
+/// [`NamedTempFile::split`]: struct.NamedTempFile.html#method.split
+/// [`TempWriter`]: struct.TempWriter.html
+/// [`Read`]: https://doc.rust-lang.org/std/io/trait.Read.html
+/// [`Seek`]: https://doc.rust-lang.org/std/io/trait.Seek.html
+pub struct TempReader {
+    file: File,
+    path: Arc<TempPath>,
+}
+
+pub(crate) fn split(writer: File, reader: File, path: TempPath) -> (TempWriter, TempReader) {
+    let path = Arc::new(path);
+    (
+        TempWriter {
+            file: writer,
+            path: path.clone(),
+        },
+        TempReader { file: reader, path },
+    )
+}
+
+impl TempWriter {
+    /// Get the temporary file's path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get a reference to the underlying file.
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+}
+
+impl TempReader {
+    /// Get the temporary file's path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get a reference to the underlying file.
+    ///
+    /// The handle may have been opened for writing too, but writing through
+    /// it bypasses the [`TempWriter`].
+    ///
+    /// [`TempWriter`]: struct.TempWriter.html
+    pub fn as_file(&self) -> &File {
+        &self.file
+    }
+}
+
+impl fmt::Debug for TempWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TempWriter({:?})", self.path)
+    }
+}
+
+impl fmt::Debug for TempReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TempReader({:?})", self.path)
+    }
+}
+
+impl Write for TempWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf).with_err_path(|| self.path())
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush().with_err_path(|| self.path())
+    }
+}
+
+impl Seek for TempWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos).with_err_path(|| self.path())
+    }
+}
+
+impl Read for TempReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf).with_err_path(|| self.path())
+    }
+}
+
+impl Seek for TempReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos).with_err_path(|| self.path())
+    }
+}
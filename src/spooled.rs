@@ -1,13 +1,57 @@
 use crate::file::tempfile;
 use std::fs::File;
-use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::io::{self, Cursor, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
 
+/// The data backing a [`SpooledTempFile`], as returned by
+/// [`SpooledTempFile::into_inner`].
+///
+/// [`SpooledTempFile`]: struct.SpooledTempFile.html
+/// [`SpooledTempFile::into_inner`]: struct.SpooledTempFile.html#method.into_inner
 #[derive(Debug)]
-enum SpooledInner {
+pub enum SpooledData {
+    /// The data hasn't been rolled over yet and is still in memory.
     InMemory(Cursor<Vec<u8>>),
+    /// The data has been rolled over to an anonymous temporary file.
     OnDisk(File),
 }
 
+/// The saved state of a [`SpooledTempFile`], as returned by
+/// [`SpooledTempFile::checkpoint`] and consumed by
+/// [`SpooledTempFile::restore`].
+///
+/// An in-memory checkpoint owns a copy of the data, so it's a true snapshot
+/// and can be stored anywhere (including outside the process) and rebuilt
+/// with `Checkpoint::InMemory { .. }`. An on-disk checkpoint instead holds a
+/// second handle to the same anonymous temporary file: it records *where* the
+/// data is, not what it was, so writes made after the checkpoint are visible
+/// after restoring. The file has no name, so such a checkpoint only makes
+/// sense within the current process.
+///
+/// [`SpooledTempFile`]: struct.SpooledTempFile.html
+/// [`SpooledTempFile::checkpoint`]: struct.SpooledTempFile.html#method.checkpoint
+/// [`SpooledTempFile::restore`]: struct.SpooledTempFile.html#method.restore
+#[derive(Debug)]
+pub enum Checkpoint {
+    /// The data was still in memory.
+    InMemory {
+        /// The contents of the buffer.
+        data: Vec<u8>,
+        /// The read/write position within `data`.
+        position: u64,
+        /// The size at which the restored file rolls over to disk.
+        max_size: usize,
+    },
+    /// The data had been rolled over to an anonymous temporary file.
+    OnDisk {
+        /// A handle to the backing file.
+        file: File,
+        /// The read/write position within `file`.
+        position: u64,
+        /// The size threshold the file was created with.
+        max_size: usize,
+    },
+}
+
 /// An object that behaves like a regular temporary file, but keeps data in
 /// memory until it reaches a configured size, at which point the data is
 /// written to a temporary file on disk, and further operations use the file
@@ -15,7 +59,7 @@ enum SpooledInner {
 #[derive(Debug)]
 pub struct SpooledTempFile {
     max_size: usize,
-    inner: SpooledInner,
+    inner: SpooledData,
 }
 
 /// Create a new spooled temporary file.
@@ -62,19 +106,58 @@ pub fn spooled_tempfile(max_size: usize) -> SpooledTempFile {
     SpooledTempFile::new(max_size)
 }
 
+/// Buffer everything read from `reader` in a new spooled temporary file.
+///
+/// The data is kept in memory if it fits in `max_size` bytes, and rolled
+/// over to a file on disk otherwise (see [`spooled_tempfile`]). The result is
+/// rewound, so it's ready to be read from the start.
+///
+/// # Errors
+///
+/// If reading from `reader` fails, or the data has to be rolled over to disk
+/// and that fails, `Err` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{self, Read};
+///
+/// # fn main() {
+/// #     if let Err(_) = run() {
+/// #         ::std::process::exit(1);
+/// #     }
+/// # }
+/// # fn run() -> Result<(), io::Error> {
+/// let mut file = tempfile::spooled_from_reader(1024, &b"small"[..])?;
+/// assert!(!file.is_rolled());
+/// let mut contents = String::new();
+/// file.read_to_string(&mut contents)?;
+/// assert_eq!(contents, "small");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`spooled_tempfile`]: fn.spooled_tempfile.html
+pub fn spooled_from_reader<R: Read>(max_size: usize, mut reader: R) -> io::Result<SpooledTempFile> {
+    let mut file = SpooledTempFile::new(max_size);
+    io::copy(&mut reader, &mut file)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
 impl SpooledTempFile {
     pub fn new(max_size: usize) -> SpooledTempFile {
         SpooledTempFile {
             max_size: max_size,
-            inner: SpooledInner::InMemory(Cursor::new(Vec::new())),
+            inner: SpooledData::InMemory(Cursor::new(Vec::new())),
         }
     }
 
     /// Returns true if the file has been rolled over to disk.
     pub fn is_rolled(&self) -> bool {
         match self.inner {
-            SpooledInner::InMemory(_) => false,
-            SpooledInner::OnDisk(_) => true,
+            SpooledData::InMemory(_) => false,
+            SpooledData::OnDisk(_) => true,
         }
     }
 
@@ -83,25 +166,217 @@ impl SpooledTempFile {
     pub fn roll(&mut self) -> io::Result<()> {
         if !self.is_rolled() {
             let mut file = tempfile()?;
-            if let SpooledInner::InMemory(ref mut cursor) = self.inner {
+            if let SpooledData::InMemory(ref mut cursor) = self.inner {
                 file.write_all(cursor.get_ref())?;
                 file.seek(SeekFrom::Start(cursor.position()))?;
             }
-            self.inner = SpooledInner::OnDisk(file);
+            self.inner = SpooledData::OnDisk(file);
         }
         Ok(())
     }
 
+    /// Consumes the `SpooledTempFile` and returns whichever backing it
+    /// currently has, without rolling over.
+    ///
+    /// If the data is still in memory, the buffer is handed over as is (along
+    /// with the current position): the filesystem is never touched. Otherwise,
+    /// the anonymous temporary file is returned, positioned where the
+    /// `SpooledTempFile` was.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::{spooled_tempfile, SpooledData};
+    /// use std::io::{self, Write};
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = spooled_tempfile(15);
+    /// writeln!(file, "short line")?;
+    ///
+    /// match file.into_inner() {
+    ///     SpooledData::InMemory(cursor) => assert_eq!(cursor.into_inner(), b"short line\n"),
+    ///     SpooledData::OnDisk(_) => unreachable!(),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_inner(self) -> SpooledData {
+        self.inner
+    }
+
+    /// Creates an independent copy of the `SpooledTempFile`, with the same
+    /// contents, position and size threshold.
+    ///
+    /// This is useful for handing the same data to several readers. If the
+    /// data is still in memory, the buffer is cloned. Otherwise, it's copied
+    /// into a new anonymous temporary file: an open file can't be given a
+    /// second, independent cursor portably, and sharing one (as
+    /// `File::try_clone` does) would make the copies interfere. Either way,
+    /// reads, writes and seeks on one copy never affect the other, and the
+    /// on-disk case costs a full copy of the data.
+    ///
+    /// # Errors
+    ///
+    /// If the data is on disk and can't be copied, `Err` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::spooled_tempfile;
+    /// use std::io::{self, Read, Seek, SeekFrom, Write};
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = spooled_tempfile(1024);
+    /// write!(file, "shared input")?;
+    /// file.seek(SeekFrom::Start(0))?;
+    ///
+    /// let mut copy = file.clone_contents()?;
+    /// let (mut a, mut b) = (String::new(), String::new());
+    /// file.read_to_string(&mut a)?;
+    /// copy.read_to_string(&mut b)?;
+    /// assert_eq!(a, b);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn clone_contents(&self) -> io::Result<SpooledTempFile> {
+        let inner = match self.inner {
+            SpooledData::InMemory(ref cursor) => SpooledData::InMemory(cursor.clone()),
+            SpooledData::OnDisk(ref file) => {
+                // The clone shares the cursor, so put it back afterwards.
+                let mut source = file.try_clone()?;
+                let pos = source.stream_position()?;
+                let mut copy = tempfile()?;
+                source.seek(SeekFrom::Start(0))?;
+                let copied = io::copy(&mut source, &mut copy);
+                source.seek(SeekFrom::Start(pos))?;
+                copied?;
+                copy.seek(SeekFrom::Start(pos))?;
+                SpooledData::OnDisk(copy)
+            }
+        };
+        Ok(SpooledTempFile {
+            max_size: self.max_size,
+            inner,
+        })
+    }
+
+    /// Saves the current state (data or backing file, and position) so that
+    /// it can later be turned back into a `SpooledTempFile` with
+    /// [`restore`](#method.restore).
+    ///
+    /// See [`Checkpoint`](enum.Checkpoint.html) for what's saved in each case.
+    ///
+    /// # Errors
+    ///
+    /// If the data is on disk and its handle can't be duplicated, `Err` is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tempfile::{spooled_tempfile, SpooledTempFile};
+    /// use std::io::{self, Read, Write};
+    ///
+    /// # fn main() {
+    /// #     if let Err(_) = run() {
+    /// #         ::std::process::exit(1);
+    /// #     }
+    /// # }
+    /// # fn run() -> Result<(), io::Error> {
+    /// let mut file = spooled_tempfile(1024);
+    /// write!(file, "stage one")?;
+    /// let checkpoint = file.checkpoint()?;
+    /// drop(file);
+    ///
+    /// let mut file = SpooledTempFile::restore(checkpoint)?;
+    /// write!(file, ", stage two")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn checkpoint(&self) -> io::Result<Checkpoint> {
+        Ok(match self.inner {
+            SpooledData::InMemory(ref cursor) => Checkpoint::InMemory {
+                data: cursor.get_ref().clone(),
+                position: cursor.position(),
+                max_size: self.max_size,
+            },
+            SpooledData::OnDisk(ref file) => {
+                let mut file = file.try_clone()?;
+                let position = file.stream_position()?;
+                Checkpoint::OnDisk {
+                    file,
+                    position,
+                    max_size: self.max_size,
+                }
+            }
+        })
+    }
+
+    /// Rebuilds a `SpooledTempFile` from a [`Checkpoint`], positioned where
+    /// the original was when the checkpoint was taken.
+    ///
+    /// For an on-disk checkpoint, the restored `SpooledTempFile` takes over
+    /// the checkpoint's handle to the backing file. Like any rolled-over
+    /// spooled file, it's removed by the OS once the last handle to it is
+    /// closed, so the data stays available for as long as either the restored
+    /// file or the original (or any other checkpoint of it) is alive. Both
+    /// share the same data, so only one of them should be written to.
+    ///
+    /// # Errors
+    ///
+    /// If the backing file can't be seeked to the saved position, `Err` is
+    /// returned.
+    ///
+    /// [`Checkpoint`]: enum.Checkpoint.html
+    pub fn restore(checkpoint: Checkpoint) -> io::Result<SpooledTempFile> {
+        Ok(match checkpoint {
+            Checkpoint::InMemory {
+                data,
+                position,
+                max_size,
+            } => {
+                let mut cursor = Cursor::new(data);
+                cursor.set_position(position);
+                SpooledTempFile {
+                    max_size,
+                    inner: SpooledData::InMemory(cursor),
+                }
+            }
+            Checkpoint::OnDisk {
+                mut file,
+                position,
+                max_size,
+            } => {
+                // The handle shares its cursor with the original file.
+                file.seek(SeekFrom::Start(position))?;
+                SpooledTempFile {
+                    max_size,
+                    inner: SpooledData::OnDisk(file),
+                }
+            }
+        })
+    }
+
     pub fn set_len(&mut self, size: u64) -> Result<(), io::Error> {
         if size as usize > self.max_size {
             self.roll()?; // does nothing if already rolled over
         }
         match self.inner {
-            SpooledInner::InMemory(ref mut cursor) => {
+            SpooledData::InMemory(ref mut cursor) => {
                 cursor.get_mut().resize(size as usize, 0);
                 Ok(())
             }
-            SpooledInner::OnDisk(ref mut file) => file.set_len(size),
+            SpooledData::OnDisk(ref mut file) => file.set_len(size),
         }
     }
 }
@@ -109,8 +384,15 @@ impl SpooledTempFile {
 impl Read for SpooledTempFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self.inner {
-            SpooledInner::InMemory(ref mut cursor) => cursor.read(buf),
-            SpooledInner::OnDisk(ref mut file) => file.read(buf),
+            SpooledData::InMemory(ref mut cursor) => cursor.read(buf),
+            SpooledData::OnDisk(ref mut file) => file.read(buf),
+        }
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        match self.inner {
+            SpooledData::InMemory(ref mut cursor) => cursor.read_vectored(bufs),
+            SpooledData::OnDisk(ref mut file) => file.read_vectored(bufs),
         }
     }
 }
@@ -119,7 +401,7 @@ impl Write for SpooledTempFile {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         // roll over to file if necessary
         let mut rolling = false;
-        if let SpooledInner::InMemory(ref mut cursor) = self.inner {
+        if let SpooledData::InMemory(ref mut cursor) = self.inner {
             rolling = cursor.position() as usize + buf.len() > self.max_size;
         }
         if rolling {
@@ -128,16 +410,34 @@ impl Write for SpooledTempFile {
 
         // write the bytes
         match self.inner {
-            SpooledInner::InMemory(ref mut cursor) => cursor.write(buf),
-            SpooledInner::OnDisk(ref mut file) => file.write(buf),
+            SpooledData::InMemory(ref mut cursor) => cursor.write(buf),
+            SpooledData::OnDisk(ref mut file) => file.write(buf),
+        }
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        // roll over to file if necessary
+        let mut rolling = false;
+        if let SpooledData::InMemory(ref mut cursor) = self.inner {
+            let len = bufs.iter().map(|b| b.len()).sum::<usize>();
+            rolling = cursor.position() as usize + len > self.max_size;
+        }
+        if rolling {
+            self.roll()?;
+        }
+
+        // write the bytes
+        match self.inner {
+            SpooledData::InMemory(ref mut cursor) => cursor.write_vectored(bufs),
+            SpooledData::OnDisk(ref mut file) => file.write_vectored(bufs),
         }
     }
 
     #[inline]
     fn flush(&mut self) -> io::Result<()> {
         match self.inner {
-            SpooledInner::InMemory(ref mut cursor) => cursor.flush(),
-            SpooledInner::OnDisk(ref mut file) => file.flush(),
+            SpooledData::InMemory(ref mut cursor) => cursor.flush(),
+            SpooledData::OnDisk(ref mut file) => file.flush(),
         }
     }
 }
@@ -145,8 +445,8 @@ impl Write for SpooledTempFile {
 impl Seek for SpooledTempFile {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         match self.inner {
-            SpooledInner::InMemory(ref mut cursor) => cursor.seek(pos),
-            SpooledInner::OnDisk(ref mut file) => file.seek(pos),
+            SpooledData::InMemory(ref mut cursor) => cursor.seek(pos),
+            SpooledData::OnDisk(ref mut file) => file.seek(pos),
         }
     }
 }
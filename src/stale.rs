@@ -0,0 +1,107 @@
+//! Removal of temporary files left behind by crashed processes.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use remove_dir_all::remove_dir_all;
+
+use crate::error::IoResultExt;
+
+/// Remove stale temporary files and directories from `dir`.
+///
+/// Temporary files are normally deleted when dropped, but a process that
+/// crashes or is killed leaves them behind. Call this on startup to clean up
+/// after a previous run: every entry of `dir` that looks like it was created
+/// by this crate with the given `prefix` and that hasn't been modified for at
+/// least `older_than` is deleted, recursively in the case of directories.
+/// Symbolic links are removed, not followed. Returns the number of entries
+/// removed.
+///
+/// To avoid deleting unrelated files, an entry only matches if its name
+/// consists of `prefix` followed by at least the default number of random
+/// characters (see [`Builder::rand_bytes`]). Files created with a shorter
+/// random part, or whose prefix is shared with files that aren't temporary,
+/// should not be cleaned up with this function. `dir` itself is never
+/// removed, and subdirectories are only scanned if they match.
+///
+/// Entries that disappear while scanning (e.g., because their owner deleted
+/// them) are silently skipped.
+///
+/// # Errors
+///
+/// If `prefix` is empty, an error of kind `InvalidInput` is returned. If
+/// `dir` can't be read or a matching entry can't be removed, `Err` is
+/// returned; entries removed before the error remain removed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::io;
+/// use std::time::Duration;
+///
+/// # fn run() -> Result<(), io::Error> {
+/// let removed = tempfile::clean_stale("/var/cache/myapp", ".tmp", Duration::from_secs(3600))?;
+/// println!("cleaned up {} leftover temporary files", removed);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Builder::rand_bytes`]: struct.Builder.html#method.rand_bytes
+pub fn clean_stale<P: AsRef<Path>>(
+    dir: P,
+    prefix: &str,
+    older_than: Duration,
+) -> io::Result<usize> {
+    let dir = dir.as_ref();
+    if prefix.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "refusing to clean up temporary files without a prefix",
+        ));
+    }
+    let cutoff = match SystemTime::now().checked_sub(older_than) {
+        Some(cutoff) => cutoff,
+        None => return Ok(0),
+    };
+
+    let mut removed = 0;
+    for entry in fs::read_dir(dir).with_err_path(|| dir)? {
+        let entry = entry.with_err_path(|| dir)?;
+        let name = entry.file_name();
+        if !name.to_str().is_some_and(|name| is_temp_name(name, prefix)) {
+            continue;
+        }
+        let path = entry.path();
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_err_path(|| path),
+        };
+        if metadata.modified().with_err_path(|| &path)? > cutoff {
+            continue;
+        }
+        let result = if metadata.is_dir() {
+            remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => removed += 1,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_err_path(|| path),
+        }
+    }
+    Ok(removed)
+}
+
+fn is_temp_name(name: &str, prefix: &str) -> bool {
+    name.strip_prefix(prefix).is_some_and(|rest| {
+        rest.bytes()
+            .take(crate::NUM_RAND_CHARS)
+            .filter(u8::is_ascii_alphanumeric)
+            .count()
+            == crate::NUM_RAND_CHARS
+    })
+}
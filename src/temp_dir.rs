@@ -0,0 +1,63 @@
+//! The default directory for temporary files, resolved once.
+
+use std::env;
+use std::path::Path;
+use std::sync::RwLock;
+
+static DEFAULT: RwLock<Option<&'static Path>> = RwLock::new(None);
+
+fn resolve() -> &'static Path {
+    Box::leak(env::temp_dir().into_boxed_path())
+}
+
+/// The directory temporary files and directories are created in by default,
+/// as returned by `std::env::temp_dir()`, cached after the first call.
+///
+/// Everything in this crate that doesn't take an explicit directory, such as
+/// [`tempfile()`], [`NamedTempFile::new`] and [`tempdir()`], uses this
+/// instead of calling `std::env::temp_dir()` each time, which saves an
+/// environment lookup and an allocation per temporary file.
+///
+/// The flip side is that changes to the environment (e.g. to `TMPDIR` on
+/// Unix, or `TMP` and `TEMP` on Windows) made after the first call aren't
+/// picked up until [`refresh_default_temp_dir`] is called.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(tempfile::default_temp_dir(), std::env::temp_dir());
+/// ```
+///
+/// [`tempfile()`]: fn.tempfile.html
+/// [`NamedTempFile::new`]: struct.NamedTempFile.html#method.new
+/// [`tempdir()`]: fn.tempdir.html
+/// [`refresh_default_temp_dir`]: fn.refresh_default_temp_dir.html
+pub fn default_temp_dir() -> &'static Path {
+    if let Some(dir) = *DEFAULT.read().unwrap_or_else(|e| e.into_inner()) {
+        return dir;
+    }
+    let mut cached = DEFAULT.write().unwrap_or_else(|e| e.into_inner());
+    cached.get_or_insert_with(resolve)
+}
+
+/// Resolve the default temporary directory again, e.g. after changing
+/// `TMPDIR`, and return the new value.
+///
+/// Later calls to [`default_temp_dir`] (and everything that uses it) return
+/// the new directory. Paths previously returned by [`default_temp_dir`] stay
+/// valid, as the cached paths are never freed; each refresh keeps a few bytes
+/// allocated for the rest of the program, so don't call this in a hot loop.
+///
+/// # Examples
+///
+/// ```
+/// let dir = tempfile::refresh_default_temp_dir();
+/// assert_eq!(dir, tempfile::default_temp_dir());
+/// ```
+///
+/// [`default_temp_dir`]: fn.default_temp_dir.html
+pub fn refresh_default_temp_dir() -> &'static Path {
+    let dir = resolve();
+    *DEFAULT.write().unwrap_or_else(|e| e.into_inner()) = Some(dir);
+    dir
+}
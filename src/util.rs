@@ -1,23 +1,35 @@
-use rand::distributions::Alphanumeric;
+use rand::rngs::OsRng;
 use rand::{self, Rng};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
-use std::path::{Path, PathBuf};
-use std::{io, str};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fmt, fs, io, str};
 
 use crate::error::IoResultExt;
+use crate::Encoding;
 
-fn tmpname(prefix: &OsStr, suffix: &OsStr, rand_len: usize) -> OsString {
+fn tmpname<G: Rng>(
+    prefix: &OsStr,
+    suffix: &OsStr,
+    rand_len: usize,
+    encoding: Encoding,
+    rng: &mut G,
+) -> OsString {
     let mut buf = OsString::with_capacity(prefix.len() + suffix.len() + rand_len);
     buf.push(prefix);
 
     // Push each character in one-by-one. Unfortunately, this is the only
     // safe(ish) simple way to do this without allocating a temporary
     // String/Vec.
+    let alphabet = encoding.alphabet();
     unsafe {
-        rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(rand_len)
-            .for_each(|b| buf.push(str::from_utf8_unchecked(&[b as u8])))
+        (0..rand_len)
+            .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+            .for_each(|b| buf.push(str::from_utf8_unchecked(&[b])))
     }
     buf.push(suffix);
     buf
@@ -28,11 +40,14 @@ pub fn create_helper<F, R>(
     prefix: &OsStr,
     suffix: &OsStr,
     random_len: usize,
+    encoding: Encoding,
+    secure_rng: bool,
     f: F,
 ) -> io::Result<R>
 where
     F: Fn(PathBuf) -> io::Result<R>,
 {
+    trace_span!("tempfile::create", base = %base.display());
     let num_retries = if random_len != 0 {
         crate::NUM_RETRIES
     } else {
@@ -40,16 +55,290 @@ where
     };
 
     for _ in 0..num_retries {
-        let path = base.join(tmpname(prefix, suffix, random_len));
+        let name = if secure_rng {
+            tmpname(prefix, suffix, random_len, encoding, &mut OsRng)
+        } else {
+            tmpname(
+                prefix,
+                suffix,
+                random_len,
+                encoding,
+                &mut rand::thread_rng(),
+            )
+        };
+        let path = base.join(name);
+        trace_event!(path = %path.display(), "trying temporary path");
         return match f(path) {
             Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
-            res => res,
+            res => {
+                trace_event!(error = ?res.as_ref().err(), "created temporary file or directory");
+                res
+            }
         };
     }
 
+    trace_event!("gave up after too many name collisions");
     Err(io::Error::new(
         io::ErrorKind::AlreadyExists,
         "too many temporary files exist",
     ))
     .with_err_path(|| base)
 }
+
+/// A prefix naming the current executable, like `.myapp-`, or `.tmp` if its
+/// name can't be determined. Computed on first use.
+pub fn exe_prefix() -> &'static OsStr {
+    static PREFIX: OnceLock<OsString> = OnceLock::new();
+    PREFIX.get_or_init(|| {
+        let exe = env::current_exe().ok();
+        match exe.as_deref().and_then(Path::file_stem) {
+            Some(stem) if !stem.is_empty() => {
+                let mut prefix = OsString::from(".");
+                prefix.push(stem);
+                prefix.push("-");
+                prefix
+            }
+            _ => OsString::from(".tmp"),
+        }
+    })
+}
+
+/// Whether names in `dir` that only differ in case collide (see
+/// `FsKind::is_case_insensitive`). The answer is remembered for absolute
+/// paths, so that creating many temporary files in one directory only queries
+/// the filesystem once. Directories that can't be queried count as
+/// case-sensitive.
+pub fn is_case_insensitive(dir: &Path) -> bool {
+    // Every `TempDir` that files are created in adds an entry, so start over
+    // once there are this many rather than growing without bound.
+    const MAX_ENTRIES: usize = 64;
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, bool>>> = OnceLock::new();
+
+    // Relative paths change meaning with the working directory.
+    if !dir.is_absolute() {
+        return crate::file::fs_kind(dir).is_ok_and(crate::FsKind::is_case_insensitive);
+    }
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(&known) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(dir) {
+        return known;
+    }
+    let insensitive = match crate::file::fs_kind(dir) {
+        Ok(kind) => kind.is_case_insensitive(),
+        Err(_) => return false,
+    };
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= MAX_ENTRIES {
+        cache.clear();
+    }
+    cache.insert(dir.to_path_buf(), insensitive);
+    insensitive
+}
+
+/// The part of `path`'s file name in the byte range `token`, as recorded for
+/// the random token of a temporary name. Empty if there's no such range.
+pub fn name_slice<'p>(path: &'p Path, token: &Range<usize>) -> &'p OsStr {
+    let name = path.file_name().unwrap_or_default().as_encoded_bytes();
+    let bytes = name.get(token.clone()).unwrap_or_default();
+    // Safe: random tokens consist of ASCII characters only, so the range
+    // starts and ends on character boundaries.
+    unsafe { OsStr::from_encoded_bytes_unchecked(bytes) }
+}
+
+/// Create the directories of `nested` below `base`, returning the innermost
+/// directory along with the directories that didn't exist yet, outermost
+/// first. Directories that already exist are left alone and not returned.
+pub fn create_dirs(base: &Path, nested: &Path) -> io::Result<(PathBuf, Vec<PathBuf>)> {
+    let mut dir = base.to_path_buf();
+    let mut created = Vec::new();
+    // `Builder::validate` has already rejected anything but plain names and `.`.
+    for component in nested.components() {
+        if let Component::Normal(name) = component {
+            dir.push(name);
+        } else {
+            continue;
+        }
+        match fs::create_dir(&dir) {
+            Ok(()) => created.push(dir.clone()),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => {}
+            Err(e) => {
+                remove_dirs(&created);
+                return Err(e).with_err_path(|| dir);
+            }
+        }
+    }
+    Ok((dir, created))
+}
+
+/// Create the missing ancestors of `path` (but not `path` itself), with
+/// `mode` on Unix, returning the ones that were created, outermost first.
+pub fn create_parent_dirs(path: &Path, mode: u32) -> io::Result<Vec<PathBuf>> {
+    let mut missing = Vec::new();
+    let mut dir = path.parent();
+    while let Some(d) = dir {
+        if d.as_os_str().is_empty() || fs::symlink_metadata(d).is_ok() {
+            break;
+        }
+        missing.push(d);
+        dir = d.parent();
+    }
+    #[cfg(unix)]
+    let builder = {
+        use std::os::unix::fs::DirBuilderExt;
+        let mut builder = fs::DirBuilder::new();
+        builder.mode(mode);
+        builder
+    };
+    #[cfg(not(unix))]
+    let builder = {
+        let _ = mode;
+        fs::DirBuilder::new()
+    };
+    let mut created = Vec::new();
+    for dir in missing.into_iter().rev() {
+        match builder.create(dir) {
+            Ok(()) => created.push(dir.to_path_buf()),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => {}
+            Err(e) => {
+                remove_dirs(&created);
+                return Err(e).with_err_path(|| dir);
+            }
+        }
+    }
+    Ok(created)
+}
+
+/// Create the directories between `base` and `path`, as implied by a path
+/// transform, returning the ones that didn't exist yet like `create_dirs`.
+pub fn create_dirs_to(base: &Path, path: &Path) -> io::Result<Vec<PathBuf>> {
+    let relative = match path.strip_prefix(base) {
+        Ok(relative)
+            if relative.components().count() > 0
+                && relative
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_))) =>
+        {
+            relative
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "transformed temporary path escapes the base directory",
+            ))
+            .with_err_path(|| path)
+        }
+    };
+    match relative.parent() {
+        Some(parent) => create_dirs(base, parent).map(|(_, created)| created),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The callback set with `Builder::path_transform`, wrapped so that the
+/// builder can keep deriving `Clone`, `Debug` and `Eq`; two transforms are
+/// equal if they're the same callback.
+#[derive(Clone)]
+pub struct PathTransform(pub Arc<dyn Fn(PathBuf) -> PathBuf + Send + Sync>);
+
+impl fmt::Debug for PathTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PathTransform")
+    }
+}
+
+impl PartialEq for PathTransform {
+    fn eq(&self, other: &PathTransform) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for PathTransform {}
+
+/// Remove directories previously returned by `create_dirs`, innermost first,
+/// stopping at the first one that can't be removed (e.g. because it isn't
+/// empty).
+pub fn remove_dirs(dirs: &[PathBuf]) {
+    for dir in dirs.iter().rev() {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// The per-thread subdirectories created by `thread_shard`, as
+/// `(base, shard)` pairs. They are removed when the thread exits, unless
+/// something (e.g. a temporary file that outlived the thread) is left in them.
+struct Shards(Vec<(PathBuf, PathBuf)>);
+
+impl Drop for Shards {
+    fn drop(&mut self) {
+        for (_, shard) in &self.0 {
+            let _ = fs::remove_dir(shard);
+        }
+    }
+}
+
+thread_local! {
+    static SHARDS: RefCell<Shards> = const { RefCell::new(Shards(Vec::new())) };
+}
+
+/// Get the current thread's subdirectory of `base`, creating it if needed.
+pub fn thread_shard(base: &Path) -> io::Result<PathBuf> {
+    SHARDS.with(|shards| {
+        let mut shards = shards.borrow_mut();
+        if let Some(i) = shards.0.iter().position(|(b, _)| b == base) {
+            // Something may have cleaned up the shard behind our back.
+            if shards.0[i].1.is_dir() {
+                return Ok(shards.0[i].1.clone());
+            }
+            shards.0.swap_remove(i);
+        }
+        let mut dir = base.to_path_buf();
+        if !dir.is_absolute() {
+            dir = env::current_dir().with_err_path(|| &dir)?.join(&dir);
+        }
+        let shard = create_helper(
+            &dir,
+            OsStr::new(".tmpshard"),
+            OsStr::new(""),
+            crate::NUM_RAND_CHARS,
+            Encoding::default(),
+            false,
+            |path| {
+                fs::create_dir(&path)
+                    .with_err_path_unless_exists(|| &path)
+                    .map(|_| path)
+            },
+        )?;
+        shards.0.push((base.to_path_buf(), shard.clone()));
+        Ok(shard)
+    })
+}
+
+/// The directories to try, in order, when the default temporary directory is
+/// read-only (see `Builder::writable_fallbacks`).
+pub fn writable_fallbacks() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if cfg!(unix) {
+        dirs.push(PathBuf::from("/run"));
+        dirs.push(PathBuf::from("/var/tmp"));
+        if let Some(runtime) = env::var_os("XDG_RUNTIME_DIR") {
+            if !runtime.is_empty() {
+                dirs.push(PathBuf::from(runtime));
+            }
+        }
+    }
+    dirs
+}
+
+/// The length of `timestamp_token`.
+pub const TIMESTAMP_LEN: usize = 16;
+
+/// The current time as a fixed-width token that sorts chronologically (see
+/// `Builder::sortable`).
+pub fn timestamp_token() -> String {
+    // A clock set before the epoch sorts first rather than failing.
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    format!("{:016x}", nanos)
+}
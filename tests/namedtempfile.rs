@@ -1,9 +1,10 @@
 #![deny(rust_2018_idioms)]
+#![allow(clippy::unused_io_amount)]
 
 use std::env;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Component, Path};
 use tempfile::{Builder, NamedTempFile};
 
 fn exists<P: AsRef<Path>>(path: P) -> bool {
@@ -58,6 +59,22 @@ fn test_persist() {
     std::fs::remove_file(&persist_path).unwrap();
 }
 
+#[test]
+fn test_persist_flushed() {
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    let old_path = tmpfile.path().to_path_buf();
+    let persist_path = env::temp_dir().join("persisted_flushed_temporary_file");
+    write!(tmpfile, "abcde").unwrap();
+    tmpfile.persist_flushed(&persist_path, true).unwrap();
+    assert!(!exists(&old_path));
+
+    let mut f = File::open(&persist_path).unwrap();
+    let mut buf = String::new();
+    f.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+    std::fs::remove_file(&persist_path).unwrap();
+}
+
 #[test]
 fn test_persist_noclobber() {
     let mut tmpfile = NamedTempFile::new().unwrap();
@@ -96,6 +113,234 @@ fn test_customnamed() {
     assert_eq!(name.len(), 18);
 }
 
+#[cfg(feature = "mime")]
+#[test]
+fn test_suffix_from_mime() {
+    let name = |mime| {
+        let tmpfile = Builder::new().suffix_from_mime(mime).tempfile().unwrap();
+        tmpfile
+            .path()
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    };
+    assert!(name("image/png").ends_with(".png"));
+    assert!(name("Text/Plain; charset=utf-8").ends_with(".txt"));
+    assert!(name("application/x-unknown").ends_with(".bin"));
+}
+
+#[test]
+fn test_exact_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmpfile = Builder::new()
+        .prefix("ignored")
+        .exact_name("exact.txt")
+        .tempfile_in(&dir)
+        .unwrap();
+    assert_eq!(tmpfile.path(), dir.path().join("exact.txt"));
+
+    let err = Builder::new()
+        .exact_name("exact.txt")
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert!(err.to_string().contains("exact.txt"));
+
+    drop(tmpfile);
+    assert!(!exists(dir.path().join("exact.txt")));
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn test_hashing_persist() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut tmpfile = tempfile::HashingNamedTempFile::new_in(&dir).unwrap();
+    tmpfile.write_all(b"Hello, ").unwrap();
+    tmpfile.write_all(b"world!").unwrap();
+    let tmp_path = tmpfile.path().to_path_buf();
+
+    let (path, digest) = tmpfile.finalize_and_persist(dir.path()).unwrap();
+    let hex = "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3";
+    assert_eq!(path, dir.path().join(hex));
+    assert_eq!(digest[..4], [0x31, 0x5f, 0x5b, 0xdb]);
+    assert!(!exists(&tmp_path));
+    assert_eq!(std::fs::read(&path).unwrap(), b"Hello, world!");
+}
+
+#[test]
+fn test_nested_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("a").join("b");
+
+    let tmpfile = Builder::new()
+        .nested_prefix("a/b")
+        .tempfile_in(&dir)
+        .unwrap();
+    assert_eq!(tmpfile.path().parent(), Some(nested.as_path()));
+    drop(tmpfile);
+    assert!(!exists(dir.path().join("a")));
+
+    // Existing directories are reused and left in place.
+    std::fs::create_dir(dir.path().join("a")).unwrap();
+    let tmpfile = Builder::new()
+        .nested_prefix("a/b")
+        .tempfile_in(&dir)
+        .unwrap();
+    let persisted = dir.path().join("a").join("persisted");
+    tmpfile.persist(&persisted).unwrap();
+    assert!(exists(&persisted));
+    assert!(!exists(&nested));
+
+    let err = Builder::new()
+        .nested_prefix("a/../../b")
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_require_utf8() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let tmpfile = Builder::new().require_utf8(true).tempfile().unwrap();
+    assert!(tmpfile.path().to_str().is_some());
+
+    let dir = tempfile::tempdir().unwrap();
+    let err = Builder::new()
+        .prefix(OsStr::from_bytes(b"\xff"))
+        .require_utf8(true)
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_max_name_len() {
+    let tmpfile = Builder::new()
+        .prefix("tmp")
+        .suffix(".rs")
+        .rand_bytes(6)
+        .max_name_len(12)
+        .tempfile()
+        .unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(name.len(), 12);
+
+    // The name is validated before touching the filesystem, so a missing
+    // directory doesn't mask the error.
+    let err = Builder::new()
+        .prefix("tmp")
+        .suffix(".rs")
+        .rand_bytes(6)
+        .max_name_len(11)
+        .tempfile_in("/this/directory/does/not/exist")
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    // Nor does it leave anything behind, such as a thread shard.
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.rand_bytes(6).max_name_len(5).thread_sharded(true);
+    assert!(builder.tempfile_in(&dir).is_err());
+    assert!(builder.tempdir_in(&dir).is_err());
+    assert!(builder.isolated_in(&dir).is_err());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_shared() {
+    let mut tmpfile = Builder::new().tempfile_shared().unwrap();
+    write!(tmpfile, "abcde").unwrap();
+
+    let mut other = File::open(tmpfile.path()).unwrap();
+    let mut buf = String::new();
+    other.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_world_readable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = |f: &NamedTempFile| f.as_file().metadata().unwrap().permissions().mode() & 0o777;
+
+    let private = NamedTempFile::new().unwrap();
+    assert_eq!(mode(&private), 0o600);
+
+    // Compare against a regular file to account for the umask.
+    let dir = tempfile::tempdir().unwrap();
+    let regular = File::create(dir.path().join("regular")).unwrap();
+    let expected = regular.metadata().unwrap().permissions().mode() & 0o644;
+
+    let public = Builder::new().world_readable().tempfile_in(&dir).unwrap();
+    assert_eq!(mode(&public), expected);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_tempstream_on() {
+    let host = NamedTempFile::new().unwrap();
+    let mut stream = Builder::new()
+        .prefix("temp")
+        .tempstream_on(host.path())
+        .unwrap();
+    let name = stream.path().to_str().unwrap().to_owned();
+    assert!(name.starts_with(&format!("{}:temp", host.path().display())));
+
+    write!(stream, "abcde").unwrap();
+    stream.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+    assert_eq!(host.as_file().metadata().unwrap().len(), 0);
+
+    drop(stream);
+    assert!(!exists(&name));
+    assert!(exists(host.path()));
+}
+
+#[cfg(windows)]
+#[test]
+fn test_tempstream_on_settings() {
+    let host = NamedTempFile::new().unwrap();
+    let mut stream = Builder::new()
+        .seek_to(3)
+        .tempstream_on(host.path())
+        .unwrap();
+    assert_eq!(stream.stream_position().unwrap(), 3);
+
+    for builder in &[
+        Builder::new().nested_prefix("a").clone(),
+        Builder::new().nfs_safe(true).clone(),
+    ] {
+        let err = builder.tempstream_on(host.path()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_inheritable() {
+    use std::os::unix::io::AsRawFd;
+
+    let cloexec = |f: &NamedTempFile| {
+        let flags = unsafe { libc::fcntl(f.as_raw_fd(), libc::F_GETFD) };
+        assert!(flags >= 0);
+        flags & libc::FD_CLOEXEC != 0
+    };
+
+    assert!(cloexec(&NamedTempFile::new().unwrap()));
+    assert!(!cloexec(
+        &Builder::new().inheritable(true).tempfile().unwrap()
+    ));
+}
+
 #[test]
 fn test_append() {
     let mut tmpfile = Builder::new().append(true).tempfile().unwrap();
@@ -122,6 +367,65 @@ fn test_reopen() {
     assert_eq!("abcde", buf);
 }
 
+#[test]
+fn test_reset() {
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    tmpfile.reset().unwrap();
+    assert_eq!(tmpfile.as_file().metadata().unwrap().len(), 0);
+
+    write!(tmpfile, "fg").unwrap();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!("fg", buf);
+}
+
+#[test]
+fn test_buf_writer() {
+    let mut writer = NamedTempFile::new().unwrap().buf_writer();
+    write!(writer, "abcde").unwrap();
+    let mut tmpfile = writer.into_inner().unwrap();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
+#[test]
+fn test_with_buffered_contents() {
+    let mut tmpfile = NamedTempFile::new()
+        .unwrap()
+        .with_buffered_contents(|w| {
+            write!(w, "abc")?;
+            write!(w, "de")
+        })
+        .unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
+#[test]
+fn test_sync_on_drop() {
+    let mut tmpfile = Builder::new().sync_on_drop(true).tempfile().unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let path = tmpfile.path().to_path_buf();
+    drop(tmpfile);
+    assert!(!exists(&path));
+
+    // The flag survives a failed persist.
+    let tmpfile = Builder::new().sync_on_drop(true).tempfile().unwrap();
+    let path = tmpfile.path().to_path_buf();
+    let tmpfile = tmpfile
+        .persist("/this/directory/does/not/exist/file")
+        .unwrap_err()
+        .file;
+    assert!(exists(&path));
+    drop(tmpfile);
+    assert!(!exists(&path));
+}
+
 #[test]
 fn test_into_file() {
     let mut file = NamedTempFile::new().unwrap();
@@ -138,6 +442,25 @@ fn test_into_file() {
     assert_eq!("abcde", buf);
 }
 
+#[test]
+fn test_into_anonymous() {
+    let mut file = NamedTempFile::new().unwrap();
+    let path = file.path().to_owned();
+    write!(file, "abcde").expect("write failed");
+
+    let mut file = file.into_anonymous().unwrap();
+    #[cfg(unix)]
+    assert!(!path.exists());
+
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+
+    drop(file);
+    assert!(!path.exists());
+}
+
 #[test]
 fn test_immut() {
     let tmpfile = NamedTempFile::new().unwrap();
@@ -281,3 +604,1549 @@ fn test_keep() {
     }
     std::fs::remove_file(&path).unwrap();
 }
+
+#[test]
+fn test_pool() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut pool = tempfile::TempFilePool::new_in(&dir, 2).unwrap();
+    assert_eq!(pool.available(), 2);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    let mut a = pool.get().unwrap();
+    let b = pool.get().unwrap();
+    let c = pool.get().unwrap();
+    assert_eq!(pool.available(), 0);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+    write!(a, "abcde").unwrap();
+    pool.put(a).unwrap();
+    pool.put(b).unwrap();
+    // The pool is full, so this one is deleted.
+    let c_path = c.path().to_path_buf();
+    pool.put(c).unwrap();
+    assert!(!exists(&c_path));
+    assert_eq!(pool.available(), 2);
+
+    let mut reused = pool.get().unwrap();
+    let mut buf = String::new();
+    reused.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "");
+
+    drop(reused);
+    drop(pool);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_isolated() {
+    let base = tempfile::tempdir().unwrap();
+    let mut isolated = Builder::new().suffix(".txt").isolated_in(&base).unwrap();
+    let dir = isolated.dir_path().to_path_buf();
+    assert_eq!(dir.parent(), Some(base.path()));
+    assert_eq!(isolated.path().parent(), Some(dir.as_path()));
+    assert!(isolated.path().to_str().unwrap().ends_with(".txt"));
+
+    write!(isolated, "abcde").unwrap();
+    isolated.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    isolated.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    std::fs::write(dir.join("extra"), b"extra").unwrap();
+    drop(isolated);
+    assert!(!exists(&dir));
+}
+
+#[cfg(feature = "cap-std")]
+#[test]
+fn test_tempfile_in_dir() {
+    use cap_std::ambient_authority;
+    use cap_std::fs::Dir;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = Dir::open_ambient_dir(tmp.path(), ambient_authority()).unwrap();
+
+    let mut file = Builder::new().prefix("cap").tempfile_in_dir(&dir).unwrap();
+    assert!(file.name().is_relative());
+    assert!(file.name().to_str().unwrap().starts_with("cap"));
+    let path = tmp.path().join(file.name());
+    assert!(exists(&path));
+
+    write!(file, "abcde").unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+    drop(file);
+    assert!(!exists(&path));
+
+    let file = Builder::new().tempfile_in_dir(&dir).unwrap();
+    file.persist("kept.txt").unwrap();
+    assert_eq!(std::fs::read(tmp.path().join("kept.txt")).unwrap(), b"");
+
+    // Settings that can't be applied here are rejected, not ignored.
+    for builder in &[
+        Builder::new().seek_to(1).clone(),
+        Builder::new().wipe_on_drop(true).clone(),
+        Builder::new().nested_prefix("sub").clone(),
+        Builder::new()
+            .reflink_from(tmp.path().join("kept.txt"))
+            .clone(),
+    ] {
+        let err = builder.tempfile_in_dir(&dir).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_thread_sharded() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().to_path_buf();
+
+    let shard = |base: &Path| {
+        let a = Builder::new()
+            .thread_sharded(true)
+            .tempfile_in(base)
+            .unwrap();
+        let b = Builder::new()
+            .thread_sharded(true)
+            .tempdir_in(base)
+            .unwrap();
+        let shard = a.path().parent().unwrap().to_path_buf();
+        assert_eq!(b.path().parent(), Some(shard.as_path()));
+        assert_eq!(shard.parent(), Some(base));
+        shard
+    };
+
+    let here = shard(&base);
+    let there = std::thread::spawn(move || shard(&base)).join().unwrap();
+    assert_ne!(here, there);
+    // The other thread's shard is gone now that the thread has exited.
+    assert!(!exists(&there));
+    assert!(exists(&here));
+}
+
+#[test]
+fn test_read_helpers() {
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    write!(tmpfile, "one\ntwo\r\nthree").unwrap();
+    assert_eq!(tmpfile.read_to_vec().unwrap(), b"one\ntwo\r\nthree");
+    assert_eq!(tmpfile.read_text().unwrap(), "one\ntwo\r\nthree");
+    assert_eq!(tmpfile.read_lines().unwrap(), ["one", "two", "three"]);
+
+    // The write cursor is untouched.
+    tmpfile.write_all(b"\n\xff").unwrap();
+    let err = tmpfile.read_text().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(tmpfile.read_lines().is_err());
+    assert!(tmpfile.read_to_vec().unwrap().ends_with(b"three\n\xff"));
+}
+
+#[test]
+fn test_clean_stale() {
+    use std::time::{Duration, SystemTime};
+
+    let dir = tempfile::tempdir().unwrap();
+    let old = SystemTime::now() - Duration::from_secs(7200);
+    let stale_file = Builder::new().tempfile_in(dir.path()).unwrap();
+    stale_file.as_file().set_modified(old).unwrap();
+    let stale_file = stale_file.into_temp_path().keep().unwrap();
+    let fresh = Builder::new().tempfile_in(dir.path()).unwrap();
+    let unrelated = dir.path().join(".tmpx");
+    std::fs::File::create(&unrelated)
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+
+    let removed = tempfile::clean_stale(dir.path(), ".tmp", Duration::from_secs(3600)).unwrap();
+    assert_eq!(removed, 1);
+    assert!(!stale_file.exists());
+    assert!(fresh.path().exists());
+    assert!(unrelated.exists());
+
+    assert!(tempfile::clean_stale(dir.path(), "", Duration::from_secs(0)).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_require_safe_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.require_safe_dir(true);
+
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o777)).unwrap();
+    let err = builder.tempfile_in(dir.path()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(err.to_string().contains(&*dir.path().to_string_lossy()));
+    assert!(builder.tempdir_in(dir.path()).is_err());
+    assert!(Builder::new().tempfile_in(dir.path()).is_ok());
+
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o1777)).unwrap();
+    builder.tempfile_in(dir.path()).unwrap();
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    builder.tempfile_in(dir.path()).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tempfile_in_open_dir() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = File::open(tmp.path()).unwrap();
+
+    let mut file = Builder::new()
+        .suffix(".txt")
+        .tempfile_in_open_dir(&dir)
+        .unwrap();
+    write!(file, "abcde").unwrap();
+    let path = tmp.path().join(file.name());
+    assert_eq!(std::fs::read(&path).unwrap(), b"abcde");
+    drop(file);
+    assert!(!path.exists());
+
+    // The directory is addressed by handle, not by path.
+    let moved = tmp.path().with_extension("moved");
+    std::fs::rename(tmp.path(), &moved).unwrap();
+    let file = Builder::new().tempfile_in_open_dir(&dir).unwrap();
+    let mut persisted = file.persist("kept").unwrap();
+    write!(persisted, "kept").unwrap();
+    assert_eq!(std::fs::read(moved.join("kept")).unwrap(), b"kept");
+    std::fs::rename(&moved, tmp.path()).unwrap();
+
+    // Settings that can't be applied here are rejected, not ignored.
+    for builder in &[
+        Builder::new().seek_to(1).clone(),
+        Builder::new().sync_on_drop(true).clone(),
+        Builder::new().xattr("user.test", b"x").clone(),
+        Builder::new().thread_sharded(true).clone(),
+    ] {
+        let err = builder.tempfile_in_open_dir(&dir).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_reserve() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.exact_name("reserved");
+
+    let (path, reservation) = builder.reserve(&dir).unwrap();
+    assert_eq!(reservation.path(), path);
+    assert!(builder.reserve(&dir).is_err());
+    let mut file = reservation.into_temp_file();
+    write!(file, "abcde").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"abcde");
+    drop(file);
+    assert!(!path.exists());
+
+    let (path, reservation) = builder.reserve(&dir).unwrap();
+    reservation.release().unwrap();
+    assert!(!path.exists());
+    let (path, reservation) = builder.reserve(&dir).unwrap();
+    drop(reservation);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_enospc_retrying_writer() {
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    tmpfile
+        .enospc_retrying_writer()
+        .write_all(b"abcde")
+        .unwrap();
+    assert_eq!(tmpfile.read_to_vec().unwrap(), b"abcde");
+
+    // Actually filling up a disk needs a small, private filesystem.
+    #[cfg(target_os = "linux")]
+    {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let scratch = tempfile::ram_scratch(64 * 1024).unwrap();
+        if !scratch.is_mounted() {
+            return;
+        }
+        let ballast = scratch.path().join("ballast");
+        std::fs::write(&ballast, vec![0; 48 * 1024]).unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hook_calls = calls.clone();
+        let mut tmpfile = Builder::new()
+            .on_enospc(move || {
+                hook_calls.fetch_add(1, Ordering::SeqCst);
+                std::fs::remove_file(&ballast)
+            })
+            .tempfile_in(scratch.path())
+            .unwrap();
+        tmpfile
+            .enospc_retrying_writer()
+            .write_all(&[1; 32 * 1024])
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(tmpfile.read_to_vec().unwrap().len(), 32 * 1024);
+    }
+}
+
+#[test]
+fn test_nfs_safe() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.nfs_safe(true);
+
+    let mut tmpfile = builder.tempfile_in(&dir).unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    assert_eq!(std::fs::read(tmpfile.path()).unwrap(), b"abcde");
+    // Only the file itself is left, with a single link.
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+    builder.exact_name("taken");
+    let taken = builder.tempfile_in(&dir).unwrap();
+    let err = builder.tempfile_in(&dir).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    drop(taken);
+    drop(tmpfile);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+    assert!(builder.shared_append(true).tempfile_in(&dir).is_err());
+}
+
+#[test]
+fn test_atomic_writer() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("config.toml");
+    std::fs::write(&target, b"old").unwrap();
+
+    let mut writer = tempfile::atomic_writer(&target).unwrap();
+    assert_eq!(writer.target(), target);
+    write!(writer, "new").unwrap();
+    writer.flush().unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"old");
+    writer.commit().unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"new");
+
+    let mut writer = tempfile::atomic_writer(&target).unwrap();
+    write!(writer, "abandoned").unwrap();
+    let temp_path = writer.temp_path().to_path_buf();
+    drop(writer);
+    assert!(!temp_path.exists());
+    assert_eq!(std::fs::read(&target).unwrap(), b"new");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_rand_token() {
+    let tmpfile = Builder::new()
+        .prefix("pre-")
+        .suffix(".log")
+        .rand_bytes(8)
+        .tempfile()
+        .unwrap();
+    let token = tmpfile.rand_token().to_str().unwrap();
+    assert_eq!(token.len(), 8);
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(name, format!("pre-{}.log", token));
+    assert_eq!(tmpfile.into_temp_path().rand_token().len(), 8);
+
+    let dir = Builder::new().prefix("d").tempdir().unwrap();
+    let name = dir.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(format!("d{}", dir.rand_token().to_str().unwrap()), name);
+
+    let exact = Builder::new()
+        .exact_name("exact")
+        .tempfile_in(&dir)
+        .unwrap();
+    assert!(exact.rand_token().is_empty());
+}
+
+#[test]
+fn test_keep_dir_handle() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(NamedTempFile::new_in(&dir).unwrap().parent_dir().is_none());
+
+    let mut tmpfile = Builder::new()
+        .keep_dir_handle(true)
+        .tempfile_in(&dir)
+        .unwrap();
+    if cfg!(unix) {
+        let parent = tmpfile.parent_dir().unwrap();
+        assert!(parent.metadata().unwrap().is_dir());
+    } else {
+        assert!(tmpfile.parent_dir().is_none());
+    }
+    write!(tmpfile, "abcde").unwrap();
+    let target = dir.path().join("saved");
+    tmpfile.persist_durable(&target).unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"abcde");
+
+    // Without a retained handle, the target's directory is opened instead.
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    let target = dir.path().join("saved2");
+    tmpfile.persist_durable(&target).unwrap();
+    assert!(exists(&target));
+
+    // A failed rename hands the temporary file back.
+    let tmpfile = Builder::new()
+        .keep_dir_handle(true)
+        .tempfile_in(&dir)
+        .unwrap();
+    let path = tmpfile.path().to_path_buf();
+    match tmpfile.persist_durable(dir.path().join("missing").join("saved")) {
+        Err(tempfile::DurablePersistError::Persist(err)) => assert_eq!(err.file.path(), path),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(!exists(&path));
+}
+
+#[test]
+fn test_chroot_base() {
+    let jail = tempfile::tempdir().unwrap();
+    let scratch = env::temp_dir()
+        .components()
+        .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+        .fold(jail.path().to_path_buf(), |path, c| path.join(c));
+    std::fs::create_dir_all(&scratch).unwrap();
+
+    let mut builder = Builder::new();
+    builder.chroot_base(jail.path());
+    let tmpfile = builder.tempfile().unwrap();
+    assert_eq!(tmpfile.path().parent(), Some(&*scratch));
+    let tmpdir = builder.tempdir_in(env::temp_dir()).unwrap();
+    assert_eq!(tmpdir.path().parent(), Some(&*scratch));
+}
+
+#[test]
+fn test_keyed() {
+    let key = format!("test_keyed:{}", std::process::id());
+    let mut creator = tempfile::keyed(&key).unwrap();
+    let mut other = tempfile::keyed(&key).unwrap();
+    assert_eq!(creator.path(), other.path());
+    assert_ne!(
+        tempfile::keyed(&format!("{}:other", key)).unwrap().path(),
+        creator.path()
+    );
+
+    write!(creator, "abcde").unwrap();
+    let mut buf = String::new();
+    other.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+
+    // Only the creator removes the file.
+    let path = creator.path().to_path_buf();
+    drop(other);
+    assert!(path.exists());
+    drop(creator);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_verbose_errors() {
+    // Without random characters, there's only one attempt.
+    let dir = tempfile::tempdir().unwrap();
+    File::create(dir.path().join("taken")).unwrap();
+    let mut builder = Builder::new();
+    builder.prefix("taken").rand_bytes(0);
+
+    let err = builder.tempfile_in(&dir).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert!(err.to_string().contains("too many temporary files"));
+
+    let err = builder.verbose_errors(true).tempfile_in(&dir).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert!(!err.to_string().contains("too many temporary files"));
+    assert!(err.to_string().contains("taken"));
+}
+
+#[test]
+fn test_lock() {
+    // Two handles on the same file.
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.exact_name("locked").shared_append(true);
+    let tmpfile = builder.tempfile_in(&dir).unwrap();
+    let other = builder.tempfile_in(&dir).unwrap();
+
+    let lock = tmpfile.try_lock_exclusive().unwrap();
+    let err = other.try_lock_exclusive().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert_eq!(
+        other.try_lock_shared().unwrap_err().kind(),
+        std::io::ErrorKind::WouldBlock
+    );
+    drop(lock);
+
+    let first = tmpfile.try_lock_shared().unwrap();
+    let second = other.try_lock_shared().unwrap();
+    assert!(other.try_lock_exclusive().is_err());
+    drop(first);
+    second.unlock().unwrap();
+    other.lock_exclusive().unwrap();
+}
+
+#[test]
+fn test_reflink_from() {
+    let dir = tempfile::tempdir().unwrap();
+    let original = dir.path().join("original");
+    std::fs::write(&original, b"abcde").unwrap();
+
+    let mut tmpfile = Builder::new()
+        .reflink_from(&original)
+        .tempfile_in(&dir)
+        .unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+
+    write!(tmpfile, "fghij").unwrap();
+    assert_eq!(std::fs::read(&original).unwrap(), b"abcde");
+    assert_eq!(std::fs::read(tmpfile.path()).unwrap(), b"abcdefghij");
+
+    let missing = dir.path().join("missing");
+    assert!(Builder::new()
+        .reflink_from(missing)
+        .tempfile_in(&dir)
+        .is_err());
+}
+
+#[test]
+fn test_persist_swapping() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+
+    let mut tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    write!(tmpfile, "first").unwrap();
+    let (_, old) = tmpfile.persist_swapping(&target).unwrap();
+    assert!(old.is_none());
+
+    let mut tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    write!(tmpfile, "second").unwrap();
+    let (_, old) = tmpfile.persist_swapping(&target).unwrap();
+    let mut buf = String::new();
+    old.unwrap().read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "first");
+    assert_eq!(std::fs::read(&target).unwrap(), b"second");
+}
+
+#[test]
+fn test_ensure_deleted() {
+    let tmpfile = NamedTempFile::new().unwrap();
+    let path = tmpfile.path().to_path_buf();
+    let reopened = tmpfile.reopen().unwrap();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        drop(reopened);
+    });
+    tmpfile.ensure_deleted().unwrap();
+    assert!(!path.exists());
+    handle.join().unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_as_inheritable_fd() {
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    let fd = tmpfile.as_inheritable_fd().unwrap();
+    assert_ne!(fd, tmpfile.as_file().as_raw_fd());
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    assert_eq!(flags & libc::FD_CLOEXEC, 0);
+
+    let mut dup = unsafe { File::from_raw_fd(fd) };
+    write!(dup, "abcde").unwrap();
+    drop(dup);
+    let mut buf = String::new();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+}
+
+#[test]
+fn test_auto_prefix() {
+    let exe = env::current_exe().unwrap();
+    let stem = exe.file_stem().unwrap().to_str().unwrap();
+    let tmpfile = Builder::new().auto_prefix().tempfile().unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with(&format!(".{}-", stem)));
+
+    let tmpfile = Builder::new().auto_prefix().prefix("x").tempfile().unwrap();
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with('x'));
+}
+
+#[test]
+fn test_temp_scope() {
+    let dir = tempfile::tempdir().unwrap();
+    let scope = tempfile::TempScope::new();
+    assert!(scope.is_empty());
+
+    let first = scope.tempfile_in(&dir).unwrap();
+    let second = scope.tempfile_in(&dir).unwrap();
+    let (first_path, second_path) = (first.path().to_path_buf(), second.path().to_path_buf());
+    assert_eq!(scope.len(), 2);
+
+    // Handles don't delete their files, even when closed.
+    first.close().unwrap();
+    assert!(first_path.exists());
+    let moved = std::thread::spawn(move || second).join().unwrap();
+
+    drop(scope);
+    assert!(!first_path.exists());
+    assert!(!second_path.exists());
+    drop(moved);
+
+    // Persisted files are left alone.
+    let scope = tempfile::TempScope::new();
+    let target = dir.path().join("kept");
+    scope.tempfile_in(&dir).unwrap().persist(&target).unwrap();
+    scope.close().unwrap();
+    assert!(target.exists());
+}
+
+#[test]
+fn test_case_insensitive_names() {
+    use tempfile::FsKind;
+
+    assert!(FsKind::Apfs.is_case_insensitive());
+    assert!(FsKind::Ntfs.is_case_insensitive());
+    assert!(!FsKind::Ext.is_case_insensitive());
+
+    // Names that only differ in case would collide, so they're generated
+    // from a single-case alphabet.
+    let folded = cfg!(any(windows, target_os = "macos", target_os = "ios"))
+        && tempfile::temp_fs_kind().unwrap().is_case_insensitive();
+    let has_upper = (0..50).any(|_| {
+        let tmpfile = Builder::new().rand_bytes(20).tempfile().unwrap();
+        let token = tmpfile.rand_token().to_str().unwrap().to_owned();
+        token.bytes().any(|b| b.is_ascii_uppercase())
+    });
+    assert_eq!(has_upper, !folded);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_reopen_via_fd() {
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    write!(tmpfile, "abcde").unwrap();
+
+    // Replacing the path doesn't affect the reopened handle.
+    let path = tmpfile.path().to_path_buf();
+    std::fs::remove_file(&path).unwrap();
+    std::fs::write(&path, b"other").unwrap();
+
+    let mut reader = tmpfile.reopen_via_fd().unwrap();
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+    assert!(reader.write_all(b"x").is_err());
+    assert_eq!(tmpfile.stream_position().unwrap(), 5);
+}
+
+#[test]
+fn test_single_instance() {
+    let name = format!("test_single_instance-{}.lock", std::process::id());
+    let guard = tempfile::single_instance(&name).unwrap().unwrap();
+    let path = guard.path().to_path_buf();
+    assert!(path.exists());
+    assert!(tempfile::single_instance(&name).unwrap().is_none());
+
+    drop(guard);
+    assert!(!path.exists());
+    let guard = tempfile::single_instance(&name).unwrap();
+    assert!(guard.is_some());
+
+    assert!(tempfile::single_instance("a/b").is_err());
+}
+
+#[test]
+fn test_path_transform() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.path_transform(|path: std::path::PathBuf| {
+        let name = path.file_name().unwrap().to_owned();
+        path.with_file_name("shard").join("inner").join(name)
+    });
+
+    let tmpfile = builder.tempfile_in(&dir).unwrap();
+    let shard = dir.path().join("shard");
+    assert_eq!(tmpfile.path().parent(), Some(&*shard.join("inner")));
+    assert_eq!(tmpfile.rand_token().len(), 6);
+    drop(tmpfile);
+    assert!(!shard.exists());
+
+    let tmpdir = builder.tempdir_in(&dir).unwrap();
+    assert!(tmpdir.path().starts_with(&shard));
+    drop(tmpdir);
+    assert!(!shard.exists());
+
+    let err = Builder::new()
+        .path_transform(|path: std::path::PathBuf| path.with_file_name("..").join("escaped"))
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_disallow_network_fs() {
+    let kind = tempfile::temp_fs_kind().unwrap();
+    let result = Builder::new().disallow_network_fs(true).tempfile();
+    if kind.is_network() {
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    } else {
+        result.unwrap();
+    }
+}
+
+#[test]
+fn test_copy_to() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("copy");
+    let mut tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    tmpfile.seek(SeekFrom::Start(2)).unwrap();
+
+    assert_eq!(tmpfile.copy_to(&dest).unwrap(), 5);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"abcde");
+    assert!(tmpfile.path().exists());
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "cde");
+}
+
+#[test]
+fn test_min_free_space() {
+    Builder::new().min_free_space(0).tempfile().unwrap();
+    let err = Builder::new()
+        .min_free_space(u64::MAX)
+        .tempfile()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::StorageFull);
+}
+
+#[test]
+fn test_for_children() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut file = Builder::new().for_children_in(dir.path()).unwrap();
+    write!(file.as_file_mut(), "abcde").unwrap();
+    let path = file.path().to_path_buf();
+    assert_eq!(std::fs::read(&path).unwrap(), b"abcde");
+
+    // Cleanup is deferred until requested.
+    let reopened = std::fs::File::open(&path).unwrap();
+    file.cleanup().unwrap();
+    assert!(!exists(&path));
+    drop(reopened);
+}
+
+#[test]
+fn test_debug_open_flags() {
+    let default = Builder::new().debug_open_flags();
+    let inheritable = Builder::new().inheritable(true).debug_open_flags();
+    assert_ne!(default, inheritable);
+    if cfg!(unix) {
+        assert!(default.contains("O_EXCL"));
+        assert!(default.contains("O_CLOEXEC"));
+        assert!(!default.contains("FD_CLOEXEC"));
+        assert!(inheritable.contains("FD_CLOEXEC"));
+        assert!(Builder::new()
+            .append(true)
+            .debug_open_flags()
+            .contains("O_APPEND"));
+    } else if cfg!(windows) {
+        assert!(default.contains("CREATE_NEW"));
+        assert!(inheritable.contains("HANDLE_FLAG_INHERIT"));
+    }
+}
+
+#[test]
+fn test_wipe_on_drop() {
+    for &append in &[false, true] {
+        let mut tmpfile = Builder::new()
+            .append(append)
+            .wipe_on_drop(true)
+            .tempfile()
+            .unwrap();
+        write!(tmpfile, "secret").unwrap();
+        let mut reader = tmpfile.reopen().unwrap();
+        drop(tmpfile);
+
+        // The open handle still sees the file's (wiped) contents.
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, [0; 6]);
+    }
+
+    let mut tmpfile = Builder::new().wipe_on_drop(true).tempfile().unwrap();
+    write!(tmpfile, "secret").unwrap();
+    let mut reader = tmpfile.reopen().unwrap();
+    tmpfile.close().unwrap();
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, [0; 6]);
+}
+
+#[test]
+fn test_wipe_on_drop_discarded_by_conversion() {
+    let mut tmpfile = Builder::new().wipe_on_drop(true).tempfile().unwrap();
+    write!(tmpfile, "secret").unwrap();
+    let mut reader = tmpfile.reopen().unwrap();
+    drop(tmpfile.into_file());
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"secret");
+
+    let mut tmpfile = Builder::new().wipe_on_drop(true).tempfile().unwrap();
+    write!(tmpfile, "secret").unwrap();
+    let mut reader = tmpfile.reopen().unwrap();
+    let (file, path) = tmpfile.into_parts();
+    drop(file);
+    drop(path);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"secret");
+
+    let mut tmpfile = Builder::new().wipe_on_drop(true).tempfile().unwrap();
+    write!(tmpfile, "secret").unwrap();
+    let mut reader = tmpfile.reopen().unwrap();
+    tmpfile.into_temp_path().close().unwrap();
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"secret");
+}
+
+#[test]
+fn test_rewind_reader() {
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    tmpfile.seek(SeekFrom::Start(3)).unwrap();
+
+    let mut buf = String::new();
+    tmpfile
+        .rewind_reader()
+        .unwrap()
+        .read_to_string(&mut buf)
+        .unwrap();
+    assert_eq!(buf, "abcde");
+    assert_eq!(tmpfile.stream_position().unwrap(), 3);
+
+    let mut reader = tmpfile.rewind_reader().unwrap();
+    let mut byte = [0];
+    reader.read_exact(&mut byte).unwrap();
+    assert_eq!(&byte, b"a");
+    reader.restore().unwrap();
+    write!(tmpfile, "xy").unwrap();
+    assert_eq!(tmpfile.read_to_vec().unwrap(), b"abcxy");
+}
+
+#[test]
+fn test_seek_to() {
+    let mut tmpfile = Builder::new().seek_to(4).tempfile().unwrap();
+    assert_eq!(tmpfile.stream_position().unwrap(), 4);
+    tmpfile.write_all(b"body").unwrap();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    tmpfile.write_all(b"HEAD").unwrap();
+    assert_eq!(tmpfile.read_to_vec().unwrap(), b"HEADbody");
+
+    let err = Builder::new()
+        .seek_to(4)
+        .append(true)
+        .tempfile()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_persist_creating_dirs() {
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("a/b/file.txt");
+    let mut tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let mut file = tmpfile.persist_creating_dirs(&dest, 0o700).unwrap();
+    assert_eq!(file.stream_position().unwrap(), 5);
+    assert_eq!(std::fs::read(&dest).unwrap(), b"abcde");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.path().join("a"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    // Directories created for a failed persist are removed again.
+    std::fs::create_dir(dir.path().join("c")).unwrap();
+    std::fs::write(dir.path().join("c/file"), b"").unwrap();
+    let tmpfile = NamedTempFile::new_in(dir.path()).unwrap();
+    let err = tmpfile
+        .persist_creating_dirs(dir.path().join("c/file/d/e"), 0o755)
+        .unwrap_err();
+    assert!(err.file.path().exists());
+    assert!(!dir.path().join("c/file/d").exists());
+}
+
+/// Persisting from memory-backed `/dev/shm` to the temporary directory may
+/// cross filesystems, which `persist_creating_dirs` works around.
+#[cfg(target_os = "linux")]
+#[test]
+fn test_persist_creating_dirs_cross_device() {
+    if !Path::new("/dev/shm").is_dir() {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let dest = dir.path().join("a/file.txt");
+    let mut tmpfile = NamedTempFile::new_in("/dev/shm").unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let old_path = tmpfile.path().to_path_buf();
+    tmpfile.persist_creating_dirs(&dest, 0o755).unwrap();
+    assert_eq!(std::fs::read(&dest).unwrap(), b"abcde");
+    assert!(!old_path.exists());
+}
+
+#[test]
+fn test_rotating_temp_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut files = tempfile::RotatingTempFiles::new_in(&dir, 2);
+    assert!(files.is_empty());
+    for i in 0..5 {
+        write!(files.next().unwrap(), "run {}", i).unwrap();
+    }
+    assert_eq!(files.len(), 2);
+    let contents: Vec<_> = files.files().map(|f| f.read_text().unwrap()).collect();
+    assert_eq!(contents, ["run 3", "run 4"]);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    drop(files);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_writable_fallbacks() {
+    // The default temporary directory is writable here, so nothing changes.
+    let tmpfile = Builder::new().writable_fallbacks(true).tempfile().unwrap();
+    assert!(tmpfile.path().starts_with(std::env::temp_dir()));
+    let dir = Builder::new().writable_fallbacks(true).tempdir().unwrap();
+    assert!(dir.path().starts_with(std::env::temp_dir()));
+
+    // An explicit directory is never substituted.
+    let missing = std::env::temp_dir().join("tempfile-missing-dir");
+    let err = Builder::new()
+        .writable_fallbacks(true)
+        .tempfile_in(&missing)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_strict_names() {
+    use std::ffi::OsStr;
+    use tempfile::{validate_component, NameError};
+
+    assert_eq!(validate_component(OsStr::new("")), Ok(()));
+    assert_eq!(validate_component(OsStr::new(".tmp")), Ok(()));
+    assert_eq!(validate_component(OsStr::new("console")), Ok(()));
+    assert_eq!(validate_component(OsStr::new("a\0b")), Err(NameError::Nul));
+    assert_eq!(
+        validate_component(OsStr::new("a/b")),
+        Err(NameError::Separator)
+    );
+    assert_eq!(
+        validate_component(OsStr::new("a\\b")),
+        Err(NameError::Separator)
+    );
+    for name in &["CON", "nul", "Com1", "lpt9.log", "aux .txt", "PRN "] {
+        assert_eq!(
+            validate_component(OsStr::new(name)),
+            Err(NameError::Reserved),
+            "{}",
+            name
+        );
+    }
+
+    let err = Builder::new()
+        .suffix("/x")
+        .strict_names(true)
+        .tempfile()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("suffix"));
+
+    // Without the option, a separator in the prefix is used as is.
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    let tmpfile = Builder::new().prefix("sub/").tempfile_in(&dir).unwrap();
+    assert!(tmpfile.path().starts_with(dir.path().join("sub")));
+    let tmpfile = Builder::new()
+        .prefix("ok-")
+        .strict_names(true)
+        .tempfile_in(&dir)
+        .unwrap();
+    assert!(exists(tmpfile.path()));
+}
+
+#[test]
+fn test_reserve_extents() {
+    let mut tmpfile = Builder::new()
+        .reserve_extents(1024 * 1024)
+        .tempfile()
+        .unwrap();
+    // The size doesn't change.
+    assert_eq!(tmpfile.as_file().metadata().unwrap().len(), 0);
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let blocks = tmpfile.as_file().metadata().unwrap().blocks();
+        // Filesystems without `fallocate` support leave the file alone.
+        assert!(blocks == 0 || blocks * 512 >= 1024 * 1024, "{}", blocks);
+    }
+    tmpfile.write_all(b"abcde").unwrap();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+}
+
+#[test]
+fn test_temppath_deref() {
+    fn takes_path(path: &Path) -> bool {
+        path.is_file()
+    }
+
+    let tmpfile = Builder::new()
+        .prefix("deref")
+        .suffix(".txt")
+        .tempfile()
+        .unwrap();
+    // `NamedTempFile` hands out its path too.
+    let path_buf = tmpfile.path().to_path_buf();
+    assert_eq!(AsRef::<Path>::as_ref(&tmpfile), path_buf);
+
+    let path = tmpfile.into_temp_path();
+    assert_eq!(path.extension().unwrap(), "txt");
+    assert!(path
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("deref"));
+    assert_eq!(path.parent().unwrap(), std::env::temp_dir());
+    assert!(path.is_absolute());
+    assert!(takes_path(&path));
+    assert_eq!(&*path, path_buf);
+    assert_eq!(AsRef::<Path>::as_ref(&path), path_buf);
+    std::fs::write(&path, b"x").unwrap();
+    drop(path);
+    assert!(!exists(&path_buf));
+}
+
+#[test]
+fn test_sortable() {
+    let dir = tempfile::tempdir().unwrap();
+    let files: Vec<NamedTempFile> = (0..10)
+        .map(|_| {
+            Builder::new()
+                .prefix("s-")
+                .suffix(".cache")
+                .sortable(true)
+                .tempfile_in(&dir)
+                .unwrap()
+        })
+        .collect();
+    for file in &files {
+        let name = file.path().file_name().unwrap().to_str().unwrap();
+        assert_eq!(name.len(), 2 + 16 + 6 + 6);
+        assert!(name[2..18].bytes().all(|b| b.is_ascii_hexdigit()));
+        assert_eq!(file.rand_token().len(), 6);
+        assert!(name.ends_with(&format!("{}.cache", file.rand_token().to_str().unwrap())));
+    }
+    // Creation order is preserved by a plain sort, unless two files were
+    // created within the same clock tick.
+    let mut names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    let timestamps: Vec<_> = files
+        .iter()
+        .map(|file| file.path().file_name().unwrap().to_str().unwrap()[..18].to_owned())
+        .collect();
+    let sorted: Vec<_> = names
+        .iter()
+        .map(|name| name.to_str().unwrap()[..18].to_owned())
+        .collect();
+    assert_eq!(timestamps, sorted);
+
+    let err = Builder::new()
+        .sortable(true)
+        .max_name_len(".tmp".len() + 6 + 10)
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_adopt() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // An anonymous file.
+    let mut anonymous = tempfile::tempfile().unwrap();
+    anonymous.write_all(b"anonymous").unwrap();
+    anonymous.seek(SeekFrom::Start(4)).unwrap();
+    let mut adopted = NamedTempFile::adopt(anonymous, dir.path()).unwrap();
+    assert!(adopted.path().starts_with(dir.path()));
+    assert_eq!(std::fs::read(adopted.path()).unwrap(), b"anonymous");
+    let mut buf = String::new();
+    adopted.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "ymous");
+    let path = adopted.path().to_path_buf();
+    drop(adopted);
+    assert!(!exists(&path));
+
+    // A file with a name is copied, leaving the original alone.
+    let original = dir.path().join("original");
+    std::fs::write(&original, b"named").unwrap();
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&original)
+        .unwrap();
+    let mut adopted = NamedTempFile::adopt(file, dir.path()).unwrap();
+    assert_ne!(adopted.path(), original);
+    adopted.write_all(b"N").unwrap();
+    assert_eq!(std::fs::read(adopted.path()).unwrap(), b"Named");
+    assert_eq!(std::fs::read(&original).unwrap(), b"named");
+    drop(adopted);
+    assert!(exists(&original));
+}
+
+#[test]
+fn test_share_mode() {
+    use std::fs::OpenOptions;
+
+    let tmpfile = Builder::new()
+        .share_mode(true, false, true)
+        .tempfile()
+        .unwrap();
+    assert!(OpenOptions::new().read(true).open(tmpfile.path()).is_ok());
+    let writable = OpenOptions::new().write(true).open(tmpfile.path());
+    if cfg!(windows) {
+        // ERROR_SHARING_VIOLATION
+        assert_eq!(writable.unwrap_err().raw_os_error(), Some(32));
+    } else {
+        // Files can always be opened on other platforms.
+        assert!(writable.is_ok());
+    }
+    let path = tmpfile.path().to_path_buf();
+    drop(tmpfile);
+    assert!(!exists(&path));
+
+    let tmpfile = Builder::new()
+        .share_mode(false, false, true)
+        .tempfile()
+        .unwrap();
+    let readable = OpenOptions::new().read(true).open(tmpfile.path());
+    if cfg!(windows) {
+        assert_eq!(readable.unwrap_err().raw_os_error(), Some(32));
+    } else {
+        assert!(readable.is_ok());
+    }
+
+    // Without `delete`, the handle has to be closed before the file can be
+    // removed.
+    let (file, path) = Builder::new()
+        .share_mode(true, true, false)
+        .tempfile()
+        .unwrap()
+        .into_parts();
+    assert!(OpenOptions::new().write(true).open(&path).is_ok());
+    drop(file);
+    let path_buf = path.to_path_buf();
+    path.close().unwrap();
+    assert!(!exists(&path_buf));
+}
+
+#[test]
+fn test_from_reader() {
+    let data: Vec<u8> = (0..100_000u32).map(|i| i as u8).collect();
+    let mut tmpfile = tempfile::from_reader(&data[..]).unwrap();
+    assert!(exists(tmpfile.path()));
+    assert_eq!(tmpfile.stream_position().unwrap(), 0);
+    let mut buf = Vec::new();
+    tmpfile.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, data);
+    tmpfile.seek(SeekFrom::Start(99_999)).unwrap();
+    let mut last = [0];
+    tmpfile.read_exact(&mut last).unwrap();
+    assert_eq!(last[0], data[99_999]);
+
+    struct Failing;
+    impl Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("broken stream"))
+        }
+    }
+    let err = tempfile::from_reader(Failing).unwrap_err();
+    assert_eq!(err.to_string(), "broken stream");
+}
+
+#[test]
+fn test_edit_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.txt");
+
+    // A file that doesn't exist yet starts out empty.
+    let mut edit = NamedTempFile::edit_in_place(&path).unwrap();
+    assert_eq!(edit.as_file().metadata().unwrap().len(), 0);
+    assert_eq!(edit.path().parent(), Some(dir.path()));
+    write!(edit, "first").unwrap();
+    edit.persist(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "first");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+    }
+
+    let mut edit = NamedTempFile::edit_in_place(&path).unwrap();
+    let mut buf = String::new();
+    edit.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "first");
+    write!(edit, ", second").unwrap();
+    // The original is untouched until the edit is persisted.
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "first");
+    edit.persist(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "first, second");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    // Abandoning an edit leaves the file alone.
+    let mut edit = NamedTempFile::edit_in_place(&path).unwrap();
+    write!(edit, "discarded").unwrap();
+    drop(edit);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "first, second");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_split() {
+    let mut tmp = NamedTempFile::new().unwrap();
+    tmp.write_all(b"abc").unwrap();
+    let path = tmp.path().to_path_buf();
+    let (mut writer, mut reader) = tmp.split().unwrap();
+    assert_eq!(writer.path(), path);
+    assert_eq!(reader.path(), path);
+
+    // The reader starts at the beginning; the writer carries on at the end.
+    writer.write_all(b"def").unwrap();
+    let mut buf = [0; 2];
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ab");
+    assert_eq!(writer.stream_position().unwrap(), 6);
+
+    writer.seek(SeekFrom::Start(0)).unwrap();
+    writer.write_all(b"X").unwrap();
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "cdef");
+    reader.seek(SeekFrom::Start(0)).unwrap();
+    reader.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"Xb");
+
+    // Both halves must go before the file does, in either order.
+    drop(reader);
+    assert!(exists(&path));
+    drop(writer);
+    assert!(!exists(&path));
+
+    let (writer, reader) = NamedTempFile::new().unwrap().split().unwrap();
+    let path = writer.path().to_path_buf();
+    drop(writer);
+    assert!(exists(&path));
+    drop(reader);
+    assert!(!exists(&path));
+}
+
+#[test]
+fn test_numa_local() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmp = Builder::new()
+        .numa_local((0..1024).map(|node| (node, dir.path())))
+        .tempfile()
+        .unwrap();
+    if cfg!(target_os = "linux") {
+        assert_eq!(tmp.path().parent(), Some(dir.path()));
+    } else {
+        assert_eq!(tmp.path().parent(), Some(tempfile::default_temp_dir()));
+    }
+
+    // Unmapped nodes use the default directory.
+    let tmp = Builder::new()
+        .numa_local(vec![(u32::MAX, dir.path())])
+        .tempfile()
+        .unwrap();
+    assert_eq!(tmp.path().parent(), Some(tempfile::default_temp_dir()));
+}
+
+#[test]
+fn test_metadata() {
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let metadata = tmpfile.metadata().unwrap();
+    assert!(metadata.is_file());
+    assert_eq!(metadata.len(), 5);
+
+    let path = tmpfile.into_temp_path();
+    assert_eq!(path.metadata().unwrap().len(), 5);
+
+    std::fs::remove_file(&path).unwrap();
+    let err = path.metadata().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_xattr() {
+    use std::os::unix::io::AsRawFd;
+
+    let dir = tempfile::tempdir().unwrap();
+    let tmpfile = match Builder::new()
+        .xattr("user.tempfile.a", b"one")
+        .xattr("user.tempfile.b", b"two")
+        .tempfile_in(&dir)
+    {
+        Ok(tmpfile) => tmpfile,
+        // The filesystem backing the temporary directory may not support
+        // user attributes.
+        Err(ref e) if e.to_string().contains("not supported") => return,
+        Err(e) => panic!("{}", e),
+    };
+    let mut buf = [0u8; 16];
+    let len = unsafe {
+        libc::fgetxattr(
+            tmpfile.as_raw_fd(),
+            b"user.tempfile.b\0".as_ptr() as *const libc::c_char,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    assert_eq!(&buf[..len as usize], b"two");
+    drop(tmpfile);
+
+    // Unknown namespaces are rejected, and the file is cleaned up.
+    Builder::new()
+        .xattr("bogus.attr", b"")
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+}
+
+#[test]
+fn test_shared_append() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.exact_name("shared.log").shared_append(true);
+
+    let mut creator = builder.tempfile_in(&dir).unwrap();
+    let mut other = builder.tempfile_in(&dir).unwrap();
+    assert_eq!(creator.path(), other.path());
+    writeln!(creator, "first").unwrap();
+    writeln!(other, "second").unwrap();
+    writeln!(creator, "third").unwrap();
+
+    let path = creator.path().to_path_buf();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "first\nsecond\nthird\n"
+    );
+
+    // Only the creator deletes the file.
+    drop(other);
+    assert!(exists(&path));
+    drop(creator);
+    assert!(!exists(&path));
+
+    let err = Builder::new()
+        .shared_append(true)
+        .tempfile_in(&dir)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_for_target() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("config.toml");
+
+    let mut tmpfile = NamedTempFile::for_target(&target).unwrap();
+    assert_eq!(tmpfile.path().parent(), Some(dir.path()));
+    let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with(".config.toml."));
+    assert!(name.ends_with(".toml"));
+
+    write!(tmpfile, "answer = 42").unwrap();
+    tmpfile.persist(&target).unwrap();
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "answer = 42");
+
+    let err = NamedTempFile::for_target("/").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_persist_transacted_invalid_handle() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+
+    // Without a valid transaction, the rename fails and the file is handed back.
+    let err = tmpfile
+        .persist_transacted(&target, std::ptr::null_mut())
+        .unwrap_err();
+    assert!(exists(err.file.path()));
+    assert!(!exists(&target));
+}
+
+#[test]
+fn test_rand_encoding() {
+    use tempfile::Encoding;
+
+    for &(encoding, alphabet) in &[
+        (Encoding::HexLower, "0123456789abcdef"),
+        (Encoding::Base32, "abcdefghijklmnopqrstuvwxyz234567"),
+    ] {
+        let tmpfile = Builder::new()
+            .prefix("p")
+            .rand_encoding(encoding)
+            .rand_bytes(32)
+            .tempfile()
+            .unwrap();
+        let name = tmpfile.path().file_name().unwrap().to_str().unwrap();
+        assert_eq!(name.len(), 33);
+        assert!(name[1..].chars().all(|c| alphabet.contains(c)));
+    }
+}
+
+#[test]
+fn test_ensure_unique_link() {
+    let dir = tempfile::tempdir().unwrap();
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    tmpfile.ensure_unique_link().unwrap();
+    let tmpfile = tmpfile.into_unique_link().unwrap();
+
+    let important = dir.path().join("important");
+    std::fs::hard_link(tmpfile.path(), &important).unwrap();
+    let err = tmpfile.ensure_unique_link().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    // The failed check leaves the file alone.
+    assert!(exists(tmpfile.path()));
+    tmpfile.as_file().metadata().unwrap();
+
+    let mut tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let alias = important.with_extension("2");
+    std::fs::hard_link(tmpfile.path(), &alias).unwrap();
+    let old_path = tmpfile.path().to_path_buf();
+    let mut tmpfile = tmpfile.into_unique_link().unwrap();
+    assert_ne!(tmpfile.path(), old_path);
+    assert!(!exists(&old_path));
+    tmpfile.ensure_unique_link().unwrap();
+    write!(tmpfile, "fgh").unwrap();
+    assert_eq!(std::fs::read(tmpfile.path()).unwrap(), b"abcdefgh");
+    assert_eq!(std::fs::read(&alias).unwrap(), b"abcde");
+}
+
+#[test]
+fn test_into_unique_link_keeps_shared_contents() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut tmpfile = Builder::new().wipe_on_drop(true).tempfile_in(&dir).unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    let alias = dir.path().join("alias");
+    std::fs::hard_link(tmpfile.path(), &alias).unwrap();
+    let fresh = tmpfile.into_unique_link().unwrap();
+    assert_eq!(std::fs::read(&alias).unwrap(), b"abcde");
+
+    let path = fresh.path().to_path_buf();
+    let mut reader = fresh.reopen().unwrap();
+    drop(fresh);
+    assert!(!exists(&path));
+    // The copy is still wiped.
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, [0; 5]);
+    assert_eq!(std::fs::read(&alias).unwrap(), b"abcde");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sendfile_to() {
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    let mut tmpfile = NamedTempFile::new().unwrap();
+    write!(tmpfile, "abcdefgh").unwrap();
+
+    let (tx, mut rx) = UnixStream::pair().unwrap();
+    assert_eq!(tmpfile.sendfile_to(tx.as_raw_fd(), 2, 3).unwrap(), 3);
+    // Asking for more than is left stops at the end of the file.
+    assert_eq!(tmpfile.sendfile_to(tx.as_raw_fd(), 6, 100).unwrap(), 2);
+    drop(tx);
+
+    let mut buf = String::new();
+    rx.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "cdegh");
+}
+
+#[test]
+fn test_disarmed_after_persist_and_keep() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // Once persisted, something else created at the old temporary path
+    // survives the handle's (already consumed) cleanup.
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    let old_path = tmpfile.path().to_path_buf();
+    let (file, path) = tmpfile.into_parts();
+    path.persist(dir.path().join("persisted")).unwrap();
+    std::fs::write(&old_path, b"replacement").unwrap();
+    drop(file);
+    assert!(exists(&old_path));
+
+    // Kept paths aren't deleted either.
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    let (file, path) = tmpfile.into_parts();
+    let kept = path.keep().unwrap();
+    drop(file);
+    assert!(exists(&kept));
+
+    // A failed persist hands back a handle that still cleans up.
+    let tmpfile = NamedTempFile::new_in(&dir).unwrap();
+    let tmp_path = tmpfile.path().to_path_buf();
+    let err = tmpfile.persist_noclobber(&kept).unwrap_err();
+    drop(err);
+    assert!(!exists(&tmp_path));
+}
+
+#[test]
+fn test_secure_rng() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = Builder::new();
+    builder.prefix("p").rand_bytes(12).secure_rng(true);
+    let a = builder.tempfile_in(&dir).unwrap();
+    let b = builder.tempfile_in(&dir).unwrap();
+    assert_ne!(a.path(), b.path());
+    assert_eq!(a.path().file_name().unwrap().len(), 13);
+}
@@ -0,0 +1,75 @@
+#![deny(rust_2018_idioms)]
+#![cfg(unix)]
+
+// Signal handlers are process-wide, so these tests live in their own binary,
+// where delivering a signal can't delete other tests' files.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tempfile::{Builder, NamedTempFile, Signal};
+
+static PREVIOUS_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn previous_handler(_signum: libc::c_int) {
+    PREVIOUS_CALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn exists<P: AsRef<Path>>(path: P) -> bool {
+    std::fs::metadata(path.as_ref()).is_ok()
+}
+
+#[test]
+fn test_cleanup_on_signals_chained() {
+    let previous = previous_handler as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    assert_ne!(
+        unsafe { libc::signal(libc::SIGHUP, previous) },
+        libc::SIG_ERR
+    );
+
+    let before = NamedTempFile::new().unwrap();
+    tempfile::cleanup_on_signals_chained(&[Signal::Hangup]).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let doomed = NamedTempFile::new_in(&dir).unwrap();
+    let doomed_path = doomed.into_temp_path();
+    let kept = NamedTempFile::new_in(&dir).unwrap();
+    let (_, kept_path) = kept.keep().unwrap();
+    let persisted = Builder::new().tempfile_in(&dir).unwrap();
+    persisted.persist(dir.path().join("persisted")).unwrap();
+    let dropped = NamedTempFile::new_in(&dir).unwrap();
+    let dropped_path = dropped.path().to_path_buf();
+    drop(dropped);
+
+    assert_eq!(unsafe { libc::raise(libc::SIGHUP) }, 0);
+
+    assert_eq!(PREVIOUS_CALLS.load(Ordering::SeqCst), 1);
+    assert!(!exists(&doomed_path));
+    // Files created before installation, and ones that no longer belong to
+    // us, are left alone.
+    assert!(exists(before.path()));
+    assert!(exists(&kept_path));
+    assert!(exists(dir.path().join("persisted")));
+    assert!(!exists(&dropped_path));
+    // The cleanup already happened, so closing reports the missing file.
+    assert!(doomed_path.close().is_err());
+
+    // Files created afterwards are covered by the next signal.
+    let next = NamedTempFile::new_in(&dir).unwrap();
+    assert_eq!(unsafe { libc::raise(libc::SIGHUP) }, 0);
+    assert_eq!(PREVIOUS_CALLS.load(Ordering::SeqCst), 2);
+    assert!(!exists(next.path()));
+}
+
+#[test]
+fn test_cleanup_on_signals_keeps_ignored() {
+    assert_ne!(
+        unsafe { libc::signal(libc::SIGINT, libc::SIG_IGN) },
+        libc::SIG_ERR
+    );
+    tempfile::cleanup_on_signals(&[Signal::Interrupt]).unwrap();
+
+    // Still ignored: if it weren't, this would kill the test process.
+    assert_eq!(unsafe { libc::raise(libc::SIGINT) }, 0);
+    let current = unsafe { libc::signal(libc::SIGINT, libc::SIG_IGN) };
+    assert_eq!(current, libc::SIG_IGN);
+}
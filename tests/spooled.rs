@@ -1,8 +1,9 @@
 #![deny(rust_2018_idioms)]
+#![allow(clippy::seek_from_current)]
 
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
 
-use tempfile::{spooled_tempfile, SpooledTempFile};
+use tempfile::{spooled_tempfile, Checkpoint, SpooledData, SpooledTempFile};
 
 #[test]
 fn test_automatic_rollover() {
@@ -305,3 +306,224 @@ fn test_set_len_rollover() {
     assert_eq!(t.read_to_end(&mut buf).unwrap(), 20);
     assert_eq!(buf.as_slice(), b"abcde\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
 }
+
+// called by test_vectored_{buffer, file, rollover}
+// assumes t is empty and offset is 0 to start
+fn test_vectored(t: &mut SpooledTempFile) {
+    let bufs = [IoSlice::new(b"abcde"), IoSlice::new(b"fghij")];
+    assert_eq!(t.write_vectored(&bufs).unwrap(), 10);
+    assert_eq!(t.seek(SeekFrom::Current(0)).unwrap(), 10);
+
+    assert_eq!(t.seek(SeekFrom::Start(0)).unwrap(), 0);
+    let mut scalar = Vec::new();
+    assert_eq!(t.read_to_end(&mut scalar).unwrap(), 10);
+    assert_eq!(scalar.as_slice(), b"abcdefghij");
+
+    assert_eq!(t.seek(SeekFrom::Start(0)).unwrap(), 0);
+    let (mut a, mut b) = ([0u8; 4], [0u8; 6]);
+    let mut bufs = [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)];
+    assert_eq!(t.read_vectored(&mut bufs).unwrap(), 10);
+    assert_eq!(&a, b"abcd");
+    assert_eq!(&b, b"efghij");
+}
+
+#[test]
+fn test_vectored_buffer() {
+    let mut t = spooled_tempfile(100);
+    test_vectored(&mut t);
+    assert!(!t.is_rolled());
+}
+
+#[test]
+fn test_vectored_file() {
+    let mut t = spooled_tempfile(100);
+    t.roll().unwrap();
+    test_vectored(&mut t);
+}
+
+#[test]
+fn test_vectored_rollover() {
+    let mut t = spooled_tempfile(8);
+    test_vectored(&mut t);
+    assert!(t.is_rolled());
+}
+
+#[test]
+fn test_into_inner() {
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcde").unwrap();
+    match t.into_inner() {
+        SpooledData::InMemory(cursor) => {
+            assert_eq!(cursor.position(), 5);
+            assert_eq!(cursor.into_inner(), b"abcde");
+        }
+        SpooledData::OnDisk(_) => panic!("rolled over too early"),
+    }
+
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcdefghijkl").unwrap();
+    match t.into_inner() {
+        SpooledData::InMemory(_) => panic!("not rolled over"),
+        SpooledData::OnDisk(mut file) => {
+            let mut buf = Vec::new();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            file.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, b"abcdefghijkl");
+        }
+    }
+}
+
+#[test]
+fn test_clone_contents() {
+    for &roll in &[false, true] {
+        let mut t = spooled_tempfile(10);
+        t.write_all(b"abcde").unwrap();
+        if roll {
+            t.roll().unwrap();
+        }
+        t.seek(SeekFrom::Start(2)).unwrap();
+
+        let mut copy = t.clone_contents().unwrap();
+        assert_eq!(copy.is_rolled(), roll);
+        let mut buf = Vec::new();
+        copy.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"cde");
+
+        // The copies are independent.
+        copy.write_all(b"fgh").unwrap();
+        let mut buf = Vec::new();
+        t.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, b"cde");
+    }
+}
+
+#[test]
+fn test_checkpoint_restore() {
+    let mut t = spooled_tempfile(10);
+    t.write_all(b"abcde").unwrap();
+    t.seek(SeekFrom::Start(1)).unwrap();
+    let checkpoint = t.checkpoint().unwrap();
+    match checkpoint {
+        Checkpoint::InMemory {
+            ref data, position, ..
+        } => {
+            assert_eq!(data, b"abcde");
+            assert_eq!(position, 1);
+        }
+        Checkpoint::OnDisk { .. } => panic!("rolled over too early"),
+    }
+    // An in-memory checkpoint is a snapshot.
+    t.write_all(b"xyz").unwrap();
+    drop(t);
+
+    let mut t = SpooledTempFile::restore(checkpoint).unwrap();
+    assert!(!t.is_rolled());
+    let mut buf = Vec::new();
+    t.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"bcde");
+
+    t.write_all(b"fghijkl").unwrap();
+    assert!(t.is_rolled());
+    t.seek(SeekFrom::Start(2)).unwrap();
+    let checkpoint = t.checkpoint().unwrap();
+    assert!(matches!(checkpoint, Checkpoint::OnDisk { position: 2, .. }));
+    t.seek(SeekFrom::End(0)).unwrap();
+    drop(t);
+
+    // The backing file outlives the original through the checkpoint.
+    let mut t = SpooledTempFile::restore(checkpoint).unwrap();
+    assert!(t.is_rolled());
+    let mut buf = Vec::new();
+    t.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"cdefghijkl");
+}
+
+#[test]
+fn test_spooled_from_reader() {
+    let mut t = tempfile::spooled_from_reader(10, &b"abcde"[..]).unwrap();
+    assert!(!t.is_rolled());
+    assert_eq!(t.stream_position().unwrap(), 0);
+    let mut buf = String::new();
+    t.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcde");
+
+    let mut t = tempfile::spooled_from_reader(10, &b"abcdefghijklmno"[..]).unwrap();
+    assert!(t.is_rolled());
+    assert_eq!(t.stream_position().unwrap(), 0);
+    let mut buf = String::new();
+    t.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "abcdefghijklmno");
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_compressed_spooled() {
+    let mut t = tempfile::spooled_tempfile_compressed(10, 0);
+    t.write_all(b"abcde").unwrap();
+    assert!(!t.is_rolled());
+    assert_eq!(t.compressed_len(), None);
+
+    // Several blocks' worth, so that reads and writes cross block boundaries.
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i / 1000) as u8).collect();
+    t.write_all(&data).unwrap();
+    assert!(t.is_rolled());
+    t.flush().unwrap();
+    assert!(t.compressed_len().unwrap() < data.len() as u64 / 10);
+
+    t.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = Vec::new();
+    t.read_to_end(&mut buf).unwrap();
+    assert_eq!(&buf[..5], b"abcde");
+    assert_eq!(&buf[5..], &data[..]);
+
+    // Overwrite across a block boundary, then read it back out of order.
+    t.seek(SeekFrom::Start(65_530)).unwrap();
+    t.write_all(b"0123456789").unwrap();
+    t.seek(SeekFrom::Start(200_000)).unwrap();
+    let mut far = [0; 4];
+    t.read_exact(&mut far).unwrap();
+    assert_eq!(far, [data[199_995]; 4]);
+    t.seek(SeekFrom::Start(65_528)).unwrap();
+    let mut near = [0; 14];
+    t.read_exact(&mut near).unwrap();
+    assert_eq!(&near[2..12], b"0123456789");
+    assert_eq!(near[..2], data[65_523..65_525]);
+
+    // Writing past the end leaves a hole of zeros.
+    let end = t.seek(SeekFrom::End(0)).unwrap();
+    t.seek(SeekFrom::Current(100_000)).unwrap();
+    t.write_all(b"tail").unwrap();
+    t.seek(SeekFrom::Start(end)).unwrap();
+    let mut buf = Vec::new();
+    t.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), 100_004);
+    assert!(buf[..100_000].iter().all(|&b| b == 0));
+    assert_eq!(&buf[100_000..], b"tail");
+    assert!(t.seek(SeekFrom::Current(-1_000_000)).is_err());
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn test_compressed_spooled_set_len() {
+    let mut t = tempfile::spooled_tempfile_compressed(10, 1);
+    t.write_all(&[1; 100_000]).unwrap();
+    t.set_len(70_000).unwrap();
+    t.set_len(140_000).unwrap();
+    t.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = Vec::new();
+    t.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf.len(), 140_000);
+    assert!(buf[..70_000].iter().all(|&b| b == 1));
+    assert!(buf[70_000..].iter().all(|&b| b == 0));
+
+    let mut t = tempfile::spooled_tempfile_compressed(100, 1);
+    t.write_all(b"abc").unwrap();
+    t.set_len(200).unwrap();
+    assert!(t.is_rolled());
+    assert_eq!(t.seek(SeekFrom::End(0)).unwrap(), 200);
+    t.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = Vec::new();
+    t.read_to_end(&mut buf).unwrap();
+    assert_eq!(&buf[..3], b"abc");
+    assert!(buf[3..].iter().all(|&b| b == 0));
+}
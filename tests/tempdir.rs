@@ -12,9 +12,12 @@
 
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
 use tempfile::{Builder, TempDir};
 
@@ -27,6 +30,7 @@ macro_rules! t {
     };
 }
 
+#[allow(dead_code)]
 trait PathExt {
     fn exists(&self) -> bool;
     fn is_dir(&self) -> bool;
@@ -43,7 +47,7 @@ impl PathExt for Path {
 
 fn test_tempdir() {
     let path = {
-        let p = t!(Builder::new().prefix("foobar").tempdir_in(&Path::new(".")));
+        let p = t!(Builder::new().prefix("foobar").tempdir_in(Path::new(".")));
         let p = p.path();
         assert!(p.to_str().unwrap().contains("foobar"));
         p.to_path_buf()
@@ -65,6 +69,418 @@ fn test_customnamed() {
     assert_eq!(name.len(), 24);
 }
 
+#[test]
+fn test_nested_prefix() {
+    let base = TempDir::new().unwrap();
+    let tmpdir = Builder::new()
+        .nested_prefix("a/b")
+        .tempdir_in(base.path())
+        .unwrap();
+    assert_eq!(
+        tmpdir.path().parent(),
+        Some(base.path().join("a").join("b").as_path())
+    );
+    t!(tmpdir.close());
+    assert!(!base.path().join("a").exists());
+}
+
+#[test]
+fn test_into_parts() {
+    let (path, guard) = TempDir::new().unwrap().into_parts();
+    assert_eq!(guard.path(), path);
+    let tmpdir = TempDir::from_parts(guard);
+    assert!(path.exists());
+
+    let (path, guard) = tmpdir.into_parts();
+    drop(guard);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_disarmed_after_into_path() {
+    let base = TempDir::new().unwrap();
+    let tmpdir = TempDir::new_in(base.path()).unwrap();
+    let leaked = tmpdir.into_path();
+    t!(fs::remove_dir(&leaked));
+    // Something else takes the name; nothing may delete it.
+    t!(fs::create_dir(&leaked));
+
+    let (path, guard) = TempDir::new_in(base.path()).unwrap().into_parts();
+    t!(guard.close());
+    assert!(!path.exists());
+    assert!(leaked.exists());
+}
+
+#[test]
+fn test_persist_replace() {
+    let base = TempDir::new().unwrap();
+    let dest = base.path().join("site");
+
+    let first = TempDir::new_in(base.path()).unwrap();
+    t!(fs::write(first.path().join("v1"), b"1"));
+    t!(first.persist_replace(&dest));
+    assert!(dest.join("v1").exists());
+
+    let second = TempDir::new_in(base.path()).unwrap();
+    let second_path = second.path().to_path_buf();
+    t!(fs::write(second.path().join("v2"), b"2"));
+    t!(second.persist_replace(&dest));
+    assert!(dest.join("v2").exists());
+    assert!(!dest.join("v1").exists());
+    assert!(!second_path.exists());
+
+    // Neither the old tree nor any renamed-aside copy is left behind.
+    assert_eq!(fs::read_dir(base.path()).unwrap().count(), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_ram_scratch() {
+    assert!(tempfile::ram_scratch(0).is_err());
+
+    let scratch = t!(tempfile::ram_scratch(1 << 20));
+    let path = scratch.path().to_path_buf();
+    t!(fs::write(path.join("scratch"), b"data"));
+    t!(scratch.close());
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_scaffold() {
+    let tmpdir = t!(TempDir::scaffold(&[
+        ("a/b.txt", Some(&b"hi"[..])),
+        ("a/empty", None),
+        ("c/", None),
+        ("./d/e/", None),
+    ]));
+    let root = tmpdir.path().to_path_buf();
+    assert_eq!(t!(fs::read(root.join("a").join("b.txt"))), b"hi");
+    assert_eq!(t!(fs::read(root.join("a").join("empty"))), b"");
+    assert!(root.join("c").is_dir());
+    assert!(root.join("d").join("e").is_dir());
+    drop(tmpdir);
+    assert!(!root.exists());
+
+    assert!(TempDir::scaffold(&[("../escape", None::<&[u8]>)]).is_err());
+    assert!(TempDir::scaffold(&[("a/../../escape", None::<&[u8]>)]).is_err());
+    assert!(TempDir::scaffold(&[("dir/", Some(b"x"))]).is_err());
+    #[cfg(unix)]
+    assert!(TempDir::scaffold(&[("/etc/escape", None::<&[u8]>)]).is_err());
+}
+
+#[test]
+fn test_from_existing() {
+    let parent = TempDir::new().unwrap();
+    let path = parent.path().join("adopted");
+    fs::create_dir_all(path.join("sub")).unwrap();
+    fs::write(path.join("sub").join("file"), b"data").unwrap();
+
+    let dir = TempDir::from_existing(path.clone());
+    assert_eq!(dir.path(), path);
+    assert_eq!(dir.rand_token(), "");
+    drop(dir);
+    assert!(!path.exists());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn test_from_existing_root() {
+    let _ = TempDir::from_existing(PathBuf::from("/"));
+}
+
+#[test]
+fn test_with_quota() {
+    let dir = TempDir::with_quota(10).unwrap();
+    let mut first = dir.new_file().unwrap();
+    first.write_all(b"abcdef").unwrap();
+    assert_eq!(dir.used(), 6);
+
+    // A write that doesn't fit is cut short.
+    let mut second = dir.new_file().unwrap();
+    assert_eq!(second.write(b"ghijkl").unwrap(), 4);
+    assert_eq!(dir.remaining(), 0);
+    let err = first.write(b"x").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+    assert_eq!(
+        dir.new_file().unwrap_err().kind(),
+        io::ErrorKind::StorageFull
+    );
+
+    assert_eq!(fs::read(second.path()).unwrap(), b"ghij");
+    let path = dir.path().to_path_buf();
+    drop((first, second));
+    dir.close().unwrap();
+    assert!(!path.exists());
+}
+
+/// Append a ustar entry of type `kind` to `archive`.
+#[cfg(feature = "tar")]
+fn tar_entry(archive: &mut Vec<u8>, name: &str, kind: u8, mode: u32, data: &[u8]) {
+    let mut header = [0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..107].copy_from_slice(format!("{:07o}", mode).as_bytes());
+    header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[148..156].copy_from_slice(b"        ");
+    let sum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+    header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+    archive.extend_from_slice(&header);
+    archive.extend_from_slice(data);
+    archive.resize(archive.len().div_ceil(512) * 512, 0);
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn test_from_tar() {
+    let mut archive = Vec::new();
+    tar_entry(&mut archive, "a/", b'5', 0o755, b"");
+    tar_entry(&mut archive, "a/b.txt", b'0', 0o640, b"hello");
+    tar_entry(&mut archive, "c/d.sh", b'0', 0o755, b"#!/bin/sh\n");
+    tar_entry(&mut archive, "e/", b'5', 0o555, b"");
+    tar_entry(&mut archive, "e/f.txt", b'0', 0o444, b"read-only");
+    archive.resize(archive.len() + 1024, 0);
+
+    let dir = TempDir::from_tar(&archive[..]).unwrap();
+    assert_eq!(fs::read(dir.path().join("a/b.txt")).unwrap(), b"hello");
+    assert_eq!(fs::read(dir.path().join("c/d.sh")).unwrap(), b"#!/bin/sh\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |p: &str| {
+            fs::metadata(dir.path().join(p))
+                .unwrap()
+                .permissions()
+                .mode()
+        };
+        assert_eq!(mode("a/b.txt") & 0o777, 0o640);
+        assert_eq!(mode("c/d.sh") & 0o777, 0o755);
+        assert_eq!(mode("a") & 0o777, 0o755);
+        // Directories are made owner-writable so that the tree can be removed.
+        assert_eq!(mode("e") & 0o777, 0o755);
+        assert_eq!(mode("e/f.txt") & 0o777, 0o444);
+    }
+    let path = dir.path().to_path_buf();
+    drop(dir);
+    assert!(!path.exists());
+
+    for bad in &["../escape.txt", "/etc/escape.txt", "a/../../escape.txt"] {
+        let mut archive = Vec::new();
+        tar_entry(&mut archive, bad, b'0', 0o644, b"x");
+        let err = TempDir::from_tar(&archive[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    let mut archive = Vec::new();
+    tar_entry(&mut archive, "link", b'2', 0o777, b"");
+    let err = TempDir::from_tar(&archive[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut archive = Vec::new();
+    tar_entry(&mut archive, "a.txt", b'0', 0o644, b"x");
+    archive[148] ^= 1;
+    assert!(TempDir::from_tar(&archive[..]).is_err());
+}
+
+/// Build a zip archive of uncompressed entries made on Unix, from
+/// `(name, mode, data)` triples.
+#[cfg(feature = "zip")]
+fn zip_archive(entries: &[(&str, u32, &[u8])]) -> Vec<u8> {
+    fn crc32(data: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &b in data {
+            crc ^= u32::from(b);
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    let (mut archive, mut central) = (Vec::new(), Vec::new());
+    for &(name, mode, data) in entries {
+        let offset = archive.len() as u32;
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        common.extend_from_slice(&0u16.to_le_bytes()); // flags
+        common.extend_from_slice(&0u16.to_le_bytes()); // stored
+        common.extend_from_slice(&0u16.to_le_bytes()); // time
+        common.extend_from_slice(&0x21u16.to_le_bytes()); // 1980-01-01
+        common.extend_from_slice(&crc32(data).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra field
+
+        archive.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&(3u16 << 8 | 20).to_le_bytes()); // made on Unix
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&[0; 6]); // comment, disk, internal attributes
+        central.extend_from_slice(&(mode << 16).to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = archive.len() as u32;
+    archive.extend_from_slice(&central);
+    archive.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]); // disk numbers
+    archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    archive.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    archive.extend_from_slice(&central_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes()); // comment
+    archive
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn test_from_zip() {
+    let archive = zip_archive(&[
+        ("a/", 0o040_755, b""),
+        ("a/b.txt", 0o100_640, b"hello"),
+        ("c/d.sh", 0o100_755, b"#!/bin/sh\n"),
+        ("e/", 0o040_555, b""),
+    ]);
+    let dir = TempDir::from_zip(io::Cursor::new(archive)).unwrap();
+    assert_eq!(fs::read(dir.path().join("a/b.txt")).unwrap(), b"hello");
+    assert_eq!(fs::read(dir.path().join("c/d.sh")).unwrap(), b"#!/bin/sh\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |p: &str| {
+            fs::metadata(dir.path().join(p))
+                .unwrap()
+                .permissions()
+                .mode()
+        };
+        assert_eq!(mode("a/b.txt") & 0o777, 0o640);
+        assert_eq!(mode("c/d.sh") & 0o777, 0o755);
+        assert_eq!(mode("e") & 0o777, 0o755);
+    }
+    let path = dir.path().to_path_buf();
+    drop(dir);
+    assert!(!path.exists());
+
+    for bad in &["../escape.txt", "/etc/escape.txt", "a/../../escape.txt"] {
+        let archive = zip_archive(&[(bad, 0o100_644, b"x")]);
+        let err = TempDir::from_zip(io::Cursor::new(archive)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    let archive = zip_archive(&[("link", 0o120_777, b"target")]);
+    let err = TempDir::from_zip(io::Cursor::new(archive)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    assert!(TempDir::from_zip(io::Cursor::new(b"not a zip archive".to_vec())).is_err());
+}
+
+#[test]
+fn test_close_async() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("a/b")).unwrap();
+    fs::write(dir.path().join("a/b/c.txt"), b"abc").unwrap();
+    let path = dir.path().to_path_buf();
+
+    let cleanup = dir.close_async();
+    cleanup.join().unwrap().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_error_mentions_base() {
+    let parent = TempDir::new().unwrap();
+    let missing = parent.path().join("missing");
+    // Paths are quoted in errors, like with `{:?}`.
+    let mentions = |err: &io::Error, path: &std::path::Path| {
+        let quoted = format!("{:?}", path);
+        err.to_string().contains(quoted.trim_end_matches('"'))
+    };
+    let err = TempDir::new_in(&missing).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(mentions(&err, &missing), "{}", err);
+
+    let err = Builder::new()
+        .prefix("context")
+        .tempdir_in(&missing)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(mentions(&err, &missing), "{}", err);
+
+    // The same goes for a plain file in the way.
+    let not_dir = parent.path().join("file");
+    fs::write(&not_dir, b"").unwrap();
+    let err = TempDir::new_in(&not_dir).unwrap_err();
+    assert!(mentions(&err, &not_dir), "{}", err);
+}
+
+#[test]
+fn test_warn_slow_cleanup() {
+    let parent = tempfile::tempdir().unwrap();
+    let warned = Arc::new(Mutex::new(Vec::new()));
+    let sink = warned.clone();
+    let dir = Builder::new()
+        .warn_slow_cleanup(Duration::from_secs(0), move |path, _| {
+            sink.lock().unwrap().push(path.to_path_buf())
+        })
+        .tempdir_in(parent.path())
+        .unwrap();
+    let path = dir.path().to_path_buf();
+    fs::write(path.join("file"), b"data").unwrap();
+    drop(dir);
+    assert!(!path.exists());
+    assert_eq!(*warned.lock().unwrap(), vec![path]);
+
+    let sink = warned.clone();
+    let dir = Builder::new()
+        .warn_slow_cleanup(Duration::from_secs(3600), move |path, _| {
+            sink.lock().unwrap().push(path.to_path_buf())
+        })
+        .tempdir_in(parent.path())
+        .unwrap();
+    let path = dir.path().to_path_buf();
+    drop(dir);
+    assert!(!path.exists());
+    assert_eq!(warned.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_for_each_entry() {
+    let tmpdir = TempDir::new().unwrap();
+    t!(fs::create_dir_all(tmpdir.path().join("a").join("b")));
+    t!(fs::write(tmpdir.path().join("a").join("b").join("c"), b"c"));
+    t!(fs::write(tmpdir.path().join("d"), b"d"));
+
+    let mut seen = Vec::new();
+    t!(tmpdir.for_each_entry(|path| {
+        seen.push(path.strip_prefix(tmpdir.path()).unwrap().to_path_buf());
+        Ok(())
+    }));
+    seen.sort();
+    let expected: Vec<_> = ["a", "a/b", "a/b/c", "d"].iter().map(Path::new).collect();
+    assert_eq!(seen, expected);
+
+    // Errors short-circuit the walk.
+    let mut count = 0;
+    let err = tmpdir
+        .for_each_entry(|_| {
+            count += 1;
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "stop"))
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "stop");
+    assert_eq!(count, 1);
+}
+
 fn test_rm_tempdir() {
     let (tx, rx) = channel();
     let f = move || -> () {
@@ -159,17 +575,17 @@ fn recursive_mkdir_rel() {
         cwd.display(),
         path.exists()
     );
-    t!(fs::create_dir(&path));
+    t!(fs::create_dir(path));
     assert!(path.is_dir());
-    t!(fs::create_dir_all(&path));
+    t!(fs::create_dir_all(path));
     assert!(path.is_dir());
 }
 
 fn recursive_mkdir_dot() {
     let dot = Path::new(".");
-    t!(fs::create_dir_all(&dot));
+    t!(fs::create_dir_all(dot));
     let dotdot = Path::new("..");
-    t!(fs::create_dir_all(&dotdot));
+    t!(fs::create_dir_all(dotdot));
 }
 
 fn recursive_mkdir_rel_2() {
@@ -181,7 +597,7 @@ fn recursive_mkdir_rel_2() {
         cwd.display(),
         path.exists()
     );
-    t!(fs::create_dir_all(&path));
+    t!(fs::create_dir_all(path));
     assert!(path.is_dir());
     assert!(path.parent().unwrap().is_dir());
     let path2 = Path::new("quux/blat");
@@ -191,7 +607,7 @@ fn recursive_mkdir_rel_2() {
         cwd.display()
     );
     t!(fs::create_dir("quux"));
-    t!(fs::create_dir_all(&path2));
+    t!(fs::create_dir_all(path2));
     assert!(path2.is_dir());
     assert!(path2.parent().unwrap().is_dir());
 }
@@ -204,9 +620,9 @@ pub fn test_remove_dir_all_ok() {
 
     println!("making {}", root.display());
     t!(fs::create_dir(&root));
-    t!(fs::create_dir(&root.join("foo")));
-    t!(fs::create_dir(&root.join("foo").join("bar")));
-    t!(fs::create_dir(&root.join("foo").join("bar").join("blat")));
+    t!(fs::create_dir(root.join("foo")));
+    t!(fs::create_dir(root.join("foo").join("bar")));
+    t!(fs::create_dir(root.join("foo").join("bar").join("blat")));
     t!(fs::remove_dir_all(&root));
     assert!(!root.exists());
     assert!(!root.join("bar").exists());
@@ -61,3 +61,68 @@ fn test_pathological_cleaner() {
     drop(tx);
     cleaner_thread.join().expect("The cleaner thread failed");
 }
+
+#[cfg(unix)]
+#[test]
+fn test_cloexec() {
+    use std::os::unix::io::AsRawFd;
+
+    let tmpfile = tempfile::tempfile().unwrap();
+    let flags = unsafe { libc::fcntl(tmpfile.as_raw_fd(), libc::F_GETFD) };
+    assert!(flags >= 0);
+    assert!(flags & libc::FD_CLOEXEC != 0);
+}
+
+#[test]
+fn test_temp_fs_kind() {
+    use tempfile::FsKind;
+
+    // The result depends on the machine; just check that the temporary
+    // directory can be queried.
+    let kind = tempfile::temp_fs_kind().unwrap();
+    assert_eq!(
+        kind.is_network(),
+        kind == FsKind::Nfs || kind == FsKind::Smb
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_tempfile_with_proc_path() {
+    let (mut file, path) = tempfile::tempfile_with_proc_path().unwrap();
+    write!(file, "abcde").unwrap();
+    let mut buf = String::new();
+    fs::File::open(&path)
+        .unwrap()
+        .read_to_string(&mut buf)
+        .unwrap();
+    assert_eq!("abcde", buf);
+}
+
+#[test]
+fn test_with_rand() {
+    let mut tmpfile = tempfile::tempfile_with_rand(16).unwrap();
+    write!(tmpfile, "abcde").unwrap();
+    tmpfile.seek(SeekFrom::Start(0)).unwrap();
+    let mut buf = String::new();
+    tmpfile.read_to_string(&mut buf).unwrap();
+    assert_eq!("abcde", buf);
+
+    let dir = tempfile::tempdir_with_rand(16).unwrap();
+    let name = dir.path().file_name().unwrap().to_str().unwrap();
+    assert_eq!(name.len(), ".tmp".len() + 16);
+}
+
+#[test]
+fn test_default_temp_dir() {
+    let dir = tempfile::default_temp_dir();
+    assert_eq!(dir, std::env::temp_dir());
+    // Cached: the same path is handed out every time.
+    assert!(std::ptr::eq(dir, tempfile::default_temp_dir()));
+    let refreshed = tempfile::refresh_default_temp_dir();
+    assert_eq!(refreshed, dir);
+    assert!(std::ptr::eq(refreshed, tempfile::default_temp_dir()));
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    assert!(file.path().starts_with(tempfile::default_temp_dir()));
+}